@@ -0,0 +1,300 @@
+use std::time::{Duration, Instant};
+
+use ggez::{
+    graphics::{Color, DrawParam},
+    nalgebra::Point2,
+};
+
+use crate::{
+    game::Game,
+    localization::tr,
+    move_list::BoardView,
+    move_struct::MoveType,
+    piece::piece::{get_piece_rect, Piece, PieceType},
+    render_utilities::translate_to_coords,
+};
+
+// The board state is always updated right away, the animations only change how it is drawn.
+// New moves are found by comparing the move history with the moves that have already been shown,
+// so moves from the network, resyncs and spectating are animated the same way as the player's own moves.
+
+const MOVE_DURATION: Duration = Duration::from_millis(200);
+const FADE_DURATION: Duration = Duration::from_millis(250);
+
+/// The animation speeds which can be chosen with the keys of their names, a speed of 0 turns animations off
+pub(crate) const ANIMATION_SPEEDS: [(&str, f32); 4] = [
+    ("animation.off", 0.0),
+    ("animation.slow", 0.5),
+    ("animation.normal", 1.0),
+    ("animation.fast", 2.0),
+];
+
+/// A piece sliding from one tile to another
+struct Tween {
+    piece: Piece,
+    from: usize,
+    to: usize,
+    start: Instant,
+}
+
+/// A piece fading in or out on its tile, e.g. a captured piece
+struct Fade {
+    piece: Piece,
+    index: usize,
+    appearing: bool,
+    start: Instant,
+}
+
+/// A tile being revealed or covered by the fog of war
+struct FogFade {
+    index: usize,
+    revealing: bool,
+    start: Instant,
+}
+
+pub(crate) struct Animations {
+    pub(crate) speed: f32,
+    pub(crate) dropped: bool, // The player's next move already shows the piece at its destination, e.g. a piece dropped by the mouse
+    shown_moves: usize,       // Moves of the history which have been animated
+    visible: Vec<usize>,      // The visible tiles when the board was last drawn
+    tweens: Vec<Tween>,
+    fades: Vec<Fade>,
+    fog: Vec<FogFade>,
+}
+
+impl Default for Animations {
+    fn default() -> Self {
+        Animations {
+            speed: 1.0,
+            dropped: false,
+            shown_moves: 0,
+            visible: Vec::new(),
+            tweens: Vec::new(),
+            fades: Vec::new(),
+            fog: Vec::new(),
+        }
+    }
+}
+
+// Starts and ends slowly
+fn ease(progress: f32) -> f32 {
+    progress * progress * (3.0 - 2.0 * progress)
+}
+
+impl Animations {
+    /// How far an animation has come, from 0 to 1
+    fn progress(&self, start: Instant, duration: Duration) -> f32 {
+        if self.speed <= 0.0 {
+            return 1.0;
+        }
+        (start.elapsed().as_secs_f32() * self.speed / duration.as_secs_f32()).min(1.0)
+    }
+
+    fn clear(&mut self) {
+        self.tweens.clear();
+        self.fades.clear();
+        self.fog.clear();
+    }
+
+    /// Whether the piece on the tile is drawn by an animation instead of in its place
+    pub(crate) fn hides_piece(&self, index: usize) -> bool {
+        self.tweens.iter().any(|tween| tween.to == index)
+            || self
+                .fades
+                .iter()
+                .any(|fade| fade.appearing && fade.index == index)
+    }
+
+    /// How much of the tile is covered by fog, from 0 for a visible tile to 1 for a hidden tile
+    pub(crate) fn fog_amount(&self, index: usize, visible: bool) -> f32 {
+        let fade = self.fog.iter().find(|fade| fade.index == index);
+        match fade {
+            Some(fade) if fade.revealing == visible => {
+                let progress = ease(self.progress(fade.start, FADE_DURATION));
+                if visible {
+                    1.0 - progress
+                } else {
+                    progress
+                }
+            }
+            _ if visible => 0.0,
+            _ => 1.0,
+        }
+    }
+}
+
+impl Game {
+    /// Starts animations for the moves and changes of vision since the board was last drawn
+    pub(crate) fn update_animations(&mut self, view: &BoardView) {
+        let now = Instant::now();
+        let dropped = std::mem::take(&mut self.animations.dropped);
+        let own_color = self.own_color();
+        let animations = &mut self.animations;
+
+        // Finished animations are removed
+        let (speed, previous) = (animations.speed, animations.visible.clone());
+        animations
+            .tweens
+            .retain(|tween| speed > 0.0 && now < tween.start + MOVE_DURATION.div_f32(speed));
+        animations
+            .fades
+            .retain(|fade| speed > 0.0 && now < fade.start + FADE_DURATION.div_f32(speed));
+        animations
+            .fog
+            .retain(|fade| speed > 0.0 && now < fade.start + FADE_DURATION.div_f32(speed));
+
+        // Earlier positions are shown without animations, and a new game starts without them
+        let moves = self.move_history.len();
+        if !view.live || moves < animations.shown_moves || speed <= 0.0 {
+            animations.clear();
+            animations.shown_moves = moves;
+            animations.visible = view.visible.clone();
+            return;
+        }
+
+        for move_ in &self.move_history[animations.shown_moves..] {
+            let own_move = move_.piece.color == own_color && self.spectating.is_none();
+
+            // The pieces which move, as they were before the move. Castling moves both the king and the rook.
+            let source = move_.piece.index;
+            let mut moved = vec![(source, move_.piece_dest_index, move_.piece)];
+            if let MoveType::Castle = move_.move_type {
+                let rook = |index| Piece {
+                    piece_type: PieceType::Rook(false),
+                    index,
+                    ..move_.piece
+                };
+                moved = if move_.piece_dest_index < source {
+                    vec![
+                        (source, source - 2, move_.piece),
+                        (source - 3, source - 1, rook(source - 3)),
+                    ]
+                } else {
+                    vec![
+                        (source, source + 2, move_.piece),
+                        (source + 4, source + 1, rook(source + 4)),
+                    ]
+                };
+            }
+
+            for (from, to, before) in moved {
+                // Only what the player could see is animated, so nothing is given away about hidden tiles.
+                // A piece leaving into the fog fades out as it was, so a hidden promotion isn't shown.
+                let seen_from = own_move || previous.contains(&from);
+                let seen_to = view.visible.contains(&to);
+                if seen_from && !seen_to {
+                    animations.fades.push(Fade {
+                        piece: Piece {
+                            index: from,
+                            ..before
+                        },
+                        index: from,
+                        appearing: false,
+                        start: now,
+                    });
+                    continue;
+                }
+                let piece = match view.board[to] {
+                    Some(piece) => piece,
+                    None => continue,
+                };
+                match (seen_from, seen_to) {
+                    (true, true) if !(own_move && dropped && from == source) => {
+                        animations.tweens.push(Tween {
+                            piece,
+                            from,
+                            to,
+                            start: now,
+                        })
+                    }
+                    (false, true) => animations.fades.push(Fade {
+                        piece,
+                        index: to,
+                        appearing: true,
+                        start: now,
+                    }),
+                    _ => {}
+                }
+            }
+
+            if let Some(captured) = move_.captured_piece {
+                if own_move || previous.contains(&captured.index) {
+                    animations.fades.push(Fade {
+                        piece: captured,
+                        index: captured.index,
+                        appearing: false,
+                        start: now,
+                    });
+                }
+            }
+        }
+        animations.shown_moves = moves;
+
+        // Tiles entering or leaving the player's vision
+        for index in 0..view.board.len() {
+            let (was_visible, visible) = (previous.contains(&index), view.visible.contains(&index));
+            if was_visible != visible {
+                animations.fog.retain(|fade| fade.index != index);
+                animations.fog.push(FogFade {
+                    index,
+                    revealing: visible,
+                    start: now,
+                });
+            }
+        }
+        animations.visible = view.visible.clone();
+    }
+
+    /// The sprites of the pieces which are being animated
+    pub(crate) fn animation_sprites(&self) -> Vec<DrawParam> {
+        let animations = &self.animations;
+        let board = self.board_geometry;
+        let position = |index: usize| {
+            let (x, y) = translate_to_coords(self.screen_index(index));
+            board.tile_position(x, y)
+        };
+
+        let mut sprites = Vec::new();
+        for fade in &animations.fades {
+            let progress = ease(animations.progress(fade.start, FADE_DURATION));
+            let alpha = if fade.appearing {
+                progress
+            } else {
+                1.0 - progress
+            };
+            sprites.push(
+                DrawParam::default()
+                    .src(get_piece_rect(&self.theme().pieces, &fade.piece))
+                    .dest(position(fade.index))
+                    .scale(board.sprite_scale())
+                    .color(Color::new(1.0, 1.0, 1.0, alpha)),
+            );
+        }
+        for tween in &animations.tweens {
+            let progress = ease(animations.progress(tween.start, MOVE_DURATION));
+            let (from, to) = (position(tween.from), position(tween.to));
+            sprites.push(
+                DrawParam::default()
+                    .src(get_piece_rect(&self.theme().pieces, &tween.piece))
+                    .dest(Point2::new(
+                        from.x + (to.x - from.x) * progress,
+                        from.y + (to.y - from.y) * progress,
+                    ))
+                    .scale(board.sprite_scale()),
+            );
+        }
+        sprites
+    }
+
+    /// Switches to the next animation speed
+    pub(crate) fn cycle_animation_speed(&mut self) {
+        let current = ANIMATION_SPEEDS
+            .iter()
+            .position(|(_, speed)| *speed == self.animations.speed)
+            .unwrap_or(0);
+        let (name, speed) = ANIMATION_SPEEDS[(current + 1) % ANIMATION_SPEEDS.len()];
+        self.animations.speed = speed;
+        println!("Animation speed: {}", tr(name));
+        self.save_settings();
+    }
+}
@@ -0,0 +1,187 @@
+use ggez::{
+    event::KeyMods,
+    graphics::{self, Color, DrawMode, MeshBuilder},
+    input::{keyboard, mouse},
+    nalgebra::{Point2, Vector2},
+    Context, GameResult,
+};
+
+use crate::{
+    game::Game,
+    render_utilities::{translate_to_coords, translate_to_index},
+};
+
+// Right-clicking a tile highlights it and right-dragging between two tiles draws an arrow, e.g. to mark
+// where hidden pieces might be. The modifier keys pick the color. Annotations are stored by board index,
+// so they follow the pieces when the board is flipped.
+
+/// The colors of the annotations, chosen by the modifier keys held when the right button is pressed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum AnnotationColor {
+    Green,  // No modifier
+    Red,    // Shift
+    Blue,   // Alt or Ctrl
+    Yellow, // Shift and Alt or Ctrl
+}
+
+impl AnnotationColor {
+    fn from_mods(mods: KeyMods) -> AnnotationColor {
+        let shift = mods.contains(KeyMods::SHIFT);
+        let other = mods.intersects(KeyMods::ALT | KeyMods::CTRL);
+        match (shift, other) {
+            (false, false) => AnnotationColor::Green,
+            (true, false) => AnnotationColor::Red,
+            (false, true) => AnnotationColor::Blue,
+            (true, true) => AnnotationColor::Yellow,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            AnnotationColor::Green => Color::from_rgba(21, 120, 27, 160),
+            AnnotationColor::Red => Color::from_rgba(191, 43, 33, 160),
+            AnnotationColor::Blue => Color::from_rgba(0, 48, 136, 160),
+            AnnotationColor::Yellow => Color::from_rgba(230, 160, 0, 160),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Arrow {
+    pub(crate) from: usize,
+    pub(crate) to: usize,
+    pub(crate) color: AnnotationColor,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Annotations {
+    pub(crate) highlights: Vec<(usize, AnnotationColor)>,
+    pub(crate) arrows: Vec<Arrow>,
+    drawing: Option<(usize, AnnotationColor)>, // The tile where the right button was pressed
+}
+
+impl Annotations {
+    /// Adds the highlight, or removes it if the tile already has one in the same color
+    fn toggle_highlight(&mut self, index: usize, color: AnnotationColor) {
+        let existing = self.highlights.iter().position(|(i, _)| *i == index);
+        if let Some(position) = existing {
+            let (_, old_color) = self.highlights.remove(position);
+            if old_color == color {
+                return;
+            }
+        }
+        self.highlights.push((index, color));
+    }
+
+    /// Adds the arrow, or removes it if the same arrow is already drawn in the same color
+    fn toggle_arrow(&mut self, arrow: Arrow) {
+        let existing = self
+            .arrows
+            .iter()
+            .position(|a| a.from == arrow.from && a.to == arrow.to);
+        if let Some(position) = existing {
+            let old = self.arrows.remove(position);
+            if old.color == arrow.color {
+                return;
+            }
+        }
+        self.arrows.push(arrow);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.highlights.clear();
+        self.arrows.clear();
+        self.drawing = None;
+    }
+}
+
+impl Game {
+    // The board index of the tile under the cursor
+    fn annotated_tile(&self, x: f32, y: f32) -> Option<usize> {
+        let (x_tile, y_tile) = self.board_geometry.tile_at(x, y)?;
+        Some(self.screen_index(translate_to_index(x_tile, y_tile)))
+    }
+
+    pub(crate) fn annotation_mouse_down(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        let color = AnnotationColor::from_mods(keyboard::active_mods(ctx));
+        self.annotations.drawing = self.annotated_tile(x, y).map(|index| (index, color));
+    }
+
+    /// Releasing the right button on the tile it was pressed on highlights the tile, otherwise an arrow is drawn
+    pub(crate) fn annotation_mouse_up(&mut self, x: f32, y: f32) {
+        let (from, color) = match self.annotations.drawing.take() {
+            Some(drawing) => drawing,
+            None => return,
+        };
+        match self.annotated_tile(x, y) {
+            Some(to) if to == from => self.annotations.toggle_highlight(from, color),
+            Some(to) => self.annotations.toggle_arrow(Arrow { from, to, color }),
+            None => {}
+        }
+    }
+
+    // The center of a tile on screen
+    fn tile_center(&self, index: usize) -> Point2<f32> {
+        let (x, y) = translate_to_coords(self.screen_index(index));
+        let tile_size = self.board_geometry.tile_size;
+        self.board_geometry.tile_position(x, y) + Vector2::new(tile_size, tile_size) / 2.0
+    }
+}
+
+/// Draws the highlighted tiles, which are drawn below the pieces
+pub(crate) fn render_highlights(game: &Game, ctx: &mut Context) -> GameResult<()> {
+    if game.annotations.highlights.is_empty() {
+        return Ok(());
+    }
+    let mut mesh = MeshBuilder::new();
+    for (index, color) in &game.annotations.highlights {
+        let (x, y) = translate_to_coords(game.screen_index(*index));
+        mesh.rectangle(
+            DrawMode::fill(),
+            game.board_geometry.tile_rect(x, y),
+            color.color(),
+        );
+    }
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, (Point2::<f32>::new(0.0, 0.0),))
+}
+
+/// Draws the arrows above the pieces, including the one being dragged
+pub(crate) fn render_arrows(game: &Game, ctx: &mut Context) -> GameResult<()> {
+    let mut arrows = game.annotations.arrows.clone();
+    if let Some((from, color)) = game.annotations.drawing {
+        let position = mouse::position(ctx);
+        if let Some(to) = game.annotated_tile(position.x, position.y) {
+            if to != from {
+                arrows.push(Arrow { from, to, color });
+            }
+        }
+    }
+    if arrows.is_empty() {
+        return Ok(());
+    }
+
+    let tile_size = game.board_geometry.tile_size;
+    let (width, head_length) = (tile_size * 0.15, tile_size * 0.4);
+    let mut mesh = MeshBuilder::new();
+    for arrow in arrows {
+        let (from, to) = (game.tile_center(arrow.from), game.tile_center(arrow.to));
+        let direction = (to - from).normalize();
+        let normal = Vector2::new(-direction.y, direction.x);
+
+        // The line stops where the head begins, so the overlap isn't drawn twice
+        let head_base = to - direction * head_length;
+        mesh.line(&[from, head_base], width, arrow.color.color())?;
+        mesh.polygon(
+            DrawMode::fill(),
+            &[
+                to,
+                head_base + normal * head_length * 0.6,
+                head_base - normal * head_length * 0.6,
+            ],
+            arrow.color.color(),
+        )?;
+    }
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, (Point2::<f32>::new(0.0, 0.0),))
+}
@@ -0,0 +1,64 @@
+use std::time::{Instant, SystemTime};
+#[cfg(test)]
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+/// Where the clocks get the current time from
+pub(crate) trait TimeSource {
+    fn now(&self) -> Instant;
+    /// The wall clock time, used for correspondence deadlines
+    fn system_time(&self) -> SystemTime;
+}
+
+/// The real time, used when playing
+pub(crate) struct SystemClock;
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock which only moves when told to. Clones share the same time,
+/// so one clone can be given to `Time` while another one drives it.
+#[cfg(test)]
+#[derive(Clone)]
+pub(crate) struct FakeClock {
+    start: Instant,
+    start_system_time: SystemTime,
+    elapsed: Rc<Cell<Duration>>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+    pub(crate) fn new() -> FakeClock {
+        FakeClock {
+            start: Instant::now(),
+            start_system_time: SystemTime::now(),
+            elapsed: Rc::new(Cell::new(Duration::from_secs(0))),
+        }
+    }
+
+    pub(crate) fn advance(&self, duration: Duration) {
+        self.elapsed.set(self.elapsed.get() + duration);
+    }
+
+    /// Sets the time passed since the clock was created, e.g. from a recorded game
+    pub(crate) fn set_elapsed(&self, elapsed: Duration) {
+        self.elapsed.set(elapsed);
+    }
+}
+
+#[cfg(test)]
+impl TimeSource for FakeClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed.get()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.start_system_time + self.elapsed.get()
+    }
+}
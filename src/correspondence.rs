@@ -0,0 +1,371 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    default_board_state::generate_default_board,
+    game::Game,
+    localization::{tr, tr_args},
+    move_struct::{apply_move, white_to_move, Move},
+    scene::Scene,
+};
+
+const CORRESPONDENCE_DIR: &str = "correspondence";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// The days per move that can be selected on the clock screen, zero being a live game
+pub(crate) const CORRESPONDENCE_DAYS: [u64; 5] = [0, 1, 3, 7, 14];
+
+pub(crate) fn days_label(days_per_move: Option<u64>) -> String {
+    match days_per_move {
+        Some(1) => tr("clock.correspondence_day"),
+        Some(days) => tr_args("clock.correspondence_days", &[("days", days.to_string())]),
+        None => tr("clock.live"),
+    }
+}
+
+pub(crate) fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+/// Formats the time left until a deadline, e.g. "2d 05:13:00"
+pub(crate) fn format_deadline(time_left: Duration) -> String {
+    let seconds = time_left.as_secs();
+    tr_args(
+        "clock.deadline",
+        &[
+            ("days", (seconds / SECONDS_PER_DAY).to_string()),
+            (
+                "time",
+                format!(
+                    "{:02}:{:02}:{:02}",
+                    seconds % SECONDS_PER_DAY / 3600,
+                    seconds % 3600 / 60,
+                    seconds % 60
+                ),
+            ),
+        ],
+    )
+}
+
+/// A correspondence game as it is saved between sessions
+#[derive(Clone, Debug)]
+pub(crate) struct CorrespondenceGame {
+    pub(crate) room_id: String,
+    pub(crate) playing_as_white: bool,
+    pub(crate) is_admin: bool,
+    pub(crate) opponent_name: String,
+    pub(crate) days_per_move: u64,
+    pub(crate) last_move_time: u64, // Unix time in seconds
+    pub(crate) moves: Vec<Move>,
+}
+
+impl fmt::Display for CorrespondenceGame {
+    /// Format: "room|color|admin|opponent|days|last_move_time|move;move;..."
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let moves: Vec<String> = self.moves.iter().map(|move_| move_.to_string()).collect();
+        write!(
+            f,
+            "{}|{}|{}|{}|{}|{}|{}",
+            self.room_id,
+            if self.playing_as_white {
+                "white"
+            } else {
+                "black"
+            },
+            self.is_admin,
+            self.opponent_name.replace(['|', '#', '\n'], ""),
+            self.days_per_move,
+            self.last_move_time,
+            moves.join(";")
+        )
+    }
+}
+
+impl CorrespondenceGame {
+    /// Parses the games sent by the server, separated by '#'
+    pub(crate) fn parse_list(data: &str) -> Vec<CorrespondenceGame> {
+        data.split('#')
+            .filter_map(CorrespondenceGame::parse)
+            .collect()
+    }
+
+    pub(crate) fn parse(string: &str) -> Option<CorrespondenceGame> {
+        let mut split = string.trim().split('|');
+        Some(CorrespondenceGame {
+            room_id: split.next()?.to_string(),
+            playing_as_white: split.next()? == "white",
+            is_admin: split.next()? == "true",
+            opponent_name: split.next()?.to_string(),
+            days_per_move: split.next()?.parse::<u64>().ok()?,
+            last_move_time: split.next()?.parse::<u64>().ok()?,
+            moves: split
+                .next()?
+                .split(';')
+                .filter(|part| !part.is_empty())
+                .map(Move::parse)
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    pub(crate) fn waiting_for_you(&self) -> bool {
+        white_to_move(self.moves.len()) == self.playing_as_white
+    }
+
+    pub(crate) fn time_left(&self) -> Duration {
+        let deadline = self.last_move_time + self.days_per_move * SECONDS_PER_DAY;
+        Duration::from_secs(deadline.saturating_sub(unix_time(SystemTime::now())))
+    }
+
+    pub(crate) fn list_text(&self) -> String {
+        if self.waiting_for_you() {
+            tr_args(
+                "list.your_move",
+                &[
+                    ("opponent", self.opponent_name.clone()),
+                    ("time", format_deadline(self.time_left())),
+                ],
+            )
+        } else {
+            tr_args(
+                "list.their_move",
+                &[("opponent", self.opponent_name.clone())],
+            )
+        }
+    }
+}
+
+/// The correspondence games of this player, one file per game in the config directory
+pub(crate) struct CorrespondenceStore {
+    pub(crate) games: Vec<CorrespondenceGame>,
+    dir: PathBuf,
+}
+
+impl CorrespondenceStore {
+    pub(crate) fn load(config_dir: &Path) -> CorrespondenceStore {
+        let dir = config_dir.join(CORRESPONDENCE_DIR);
+        let mut games = Vec::new();
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                if let Some(game) = fs::read_to_string(entry.path())
+                    .ok()
+                    .and_then(|data| CorrespondenceGame::parse(&data))
+                {
+                    games.push(game);
+                }
+            }
+        }
+        // Games waiting for your move are listed first
+        games.sort_by_key(|game| !game.waiting_for_you());
+
+        CorrespondenceStore { games, dir }
+    }
+
+    fn path(&self, room_id: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.txt", room_id.replace(['/', '\\', '.'], "")))
+    }
+
+    /// Saves a game, replacing an older version of it
+    pub(crate) fn save(&mut self, game: CorrespondenceGame) {
+        fs::create_dir_all(&self.dir).ok();
+        if let Err(e) = fs::write(self.path(&game.room_id), game.to_string()) {
+            eprintln!("Could not save correspondence game: {}", e);
+        }
+        self.games
+            .retain(|existing| existing.room_id != game.room_id);
+        self.games.push(game);
+        self.games.sort_by_key(|game| !game.waiting_for_you());
+    }
+
+    /// Adds a game received from the server, unless the saved one has at least as many moves
+    pub(crate) fn merge(&mut self, game: CorrespondenceGame) {
+        let newer = match self
+            .games
+            .iter()
+            .find(|existing| existing.room_id == game.room_id)
+        {
+            Some(existing) => game.moves.len() > existing.moves.len(),
+            None => true,
+        };
+        if newer {
+            self.save(game);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, room_id: &str) {
+        fs::remove_file(self.path(room_id)).ok();
+        self.games.retain(|game| game.room_id != room_id);
+    }
+
+    pub(crate) fn get(&self, room_id: &str) -> Option<CorrespondenceGame> {
+        self.games
+            .iter()
+            .find(|game| game.room_id == room_id)
+            .cloned()
+    }
+}
+
+impl Game {
+    pub(crate) fn is_correspondence(&self) -> bool {
+        self.time.days_per_move.is_some() && self.spectating.is_none()
+    }
+
+    pub(crate) fn cycle_correspondence_days(&mut self) {
+        if !self.is_admin {
+            return;
+        }
+        let current = CORRESPONDENCE_DAYS
+            .iter()
+            .position(|days| *days == self.time.days_per_move.unwrap_or(0))
+            .unwrap_or(0);
+        let next = CORRESPONDENCE_DAYS[(current + 1) % CORRESPONDENCE_DAYS.len()];
+        self.time.days_per_move = if next == 0 { None } else { Some(next) };
+        self.update_time_labels();
+    }
+
+    /// Time left until the player to move has to make a move
+    pub(crate) fn correspondence_time_left(&self) -> Duration {
+        let days = self.time.days_per_move.unwrap_or(0);
+        let deadline = self.time.last_move_time + Duration::from_secs(days * SECONDS_PER_DAY);
+        deadline
+            .duration_since(self.time.source.system_time())
+            .unwrap_or_default()
+    }
+
+    fn current_correspondence_game(&self) -> Option<CorrespondenceGame> {
+        let read_state = &self.connection.state;
+        Some(CorrespondenceGame {
+            room_id: read_state.room_id.clone()?.replace("\"", ""),
+            playing_as_white: self.playing_as_white,
+            is_admin: self.is_admin,
+            opponent_name: read_state
+                .event_validation
+                .opponent_name
+                .clone()
+                .unwrap_or(tr("game.opponent")),
+            days_per_move: self.time.days_per_move?,
+            last_move_time: unix_time(self.time.last_move_time),
+            moves: self.move_history.clone(),
+        })
+    }
+
+    /// Saves the game to disk and to the server, called after every move
+    pub(crate) fn save_correspondence(&mut self) {
+        if !self.is_correspondence() {
+            return;
+        }
+        if let Some(game) = self.current_correspondence_game() {
+            self.connection
+                .send("save_correspondence", &game.to_string());
+            self.correspondence.save(game);
+        }
+    }
+
+    /// Removes a finished game from the saved games
+    pub(crate) fn finish_correspondence(&mut self) {
+        if !self.is_correspondence() {
+            return;
+        }
+        if let Some(room_id) = self.connection.state.room_id.clone() {
+            let room_id = room_id.replace("\"", "");
+            self.connection.send("finish_correspondence", &room_id);
+            self.correspondence.remove(&room_id);
+        }
+    }
+
+    /// Restores a saved game, the fogged board is rebuilt from the move history
+    pub(crate) fn open_correspondence_game(&mut self, room_id: String) {
+        let game = match self.correspondence.get(&room_id) {
+            Some(game) => game,
+            None => return,
+        };
+
+        self.reset_game();
+        self.playing_as_white = game.playing_as_white;
+        self.is_admin = game.is_admin;
+        self.board = generate_default_board();
+        for move_ in &game.moves {
+            apply_move(&mut self.board, move_);
+        }
+        self.move_history = game.moves;
+        self.time.days_per_move = Some(game.days_per_move);
+        self.time.last_move_time = UNIX_EPOCH + Duration::from_secs(game.last_move_time);
+        self.game_active = !self.move_history.is_empty();
+        self.active_turn = white_to_move(self.move_history.len()) == self.playing_as_white;
+        self.scenes.reset(Scene::InGame);
+        self.update_available_moves();
+
+        let state = &mut self.connection.state;
+        state.room_id = Some(game.room_id.clone());
+        state.event_validation.opponent_name = Some(game.opponent_name);
+        state.opponent_online = false;
+
+        // The server puts us back in the room, where the opponent might be waiting
+        self.connection.send("resume_correspondence", &game.room_id);
+    }
+
+    /// Sends our moves to an opponent who just came online, with the time the last one was made.
+    /// Format: "last_move_time|move;move;..."
+    pub(crate) fn send_correspondence_sync(&mut self) {
+        let moves: Vec<String> = self
+            .move_history
+            .iter()
+            .map(|move_| move_.to_string())
+            .collect();
+        let data = format!(
+            "{}|{}",
+            unix_time(self.time.last_move_time),
+            moves.join(";")
+        );
+        self.connection.send("correspondence_sync", &data);
+    }
+
+    /// Takes the opponent's moves if they continue our game, for example moves made while we were offline.
+    /// If our game is the longer one it is sent back instead.
+    pub(crate) fn apply_correspondence_sync(&mut self, data: &str) {
+        let mut split = data.splitn(2, '|');
+        let last_move_time = split.next().and_then(|time| time.parse::<u64>().ok());
+        let moves: Option<Vec<Move>> = split.next().and_then(|moves| {
+            moves
+                .split(';')
+                .filter(|part| !part.is_empty())
+                .map(Move::parse)
+                .collect()
+        });
+        let (last_move_time, moves) = match (last_move_time, moves) {
+            (Some(last_move_time), Some(moves)) => (last_move_time, moves),
+            _ => {
+                eprintln!("Malformed correspondence sync: {}", data);
+                return;
+            }
+        };
+
+        let extends_ours = moves.len() > self.move_history.len()
+            && self
+                .move_history
+                .iter()
+                .zip(moves.iter())
+                .all(|(ours, theirs)| ours.to_string() == theirs.to_string());
+
+        if extends_ours {
+            self.board = generate_default_board();
+            for move_ in &moves {
+                apply_move(&mut self.board, move_);
+            }
+            self.move_history = moves;
+            self.pending_move = None;
+            // The deadline runs from when the move was made, not from when we received it
+            self.time.last_move_time = UNIX_EPOCH + Duration::from_secs(last_move_time);
+            self.game_active = true;
+            self.active_turn = white_to_move(self.move_history.len()) == self.playing_as_white;
+            self.update_available_moves();
+            self.save_correspondence();
+        } else if moves.len() < self.move_history.len() {
+            self.send_correspondence_sync();
+        }
+    }
+}
@@ -1,78 +1,158 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use ggez::{
-    event::{EventHandler, MouseButton},
+    event::{self, EventHandler, KeyCode, KeyMods, MouseButton},
     graphics,
     nalgebra::Point2,
     Context, GameResult,
 };
 
 use crate::{
-    game::{BACKGROUND_COLOR, LIGHT_COLOR},
-    menu::clickable::ClickableGroup,
-    render_utilities::{flip_index, translate_to_index},
-    Game, SCREEN_HEIGHT, SCREEN_WIDTH, STATE,
+    correspondence::CorrespondenceGame,
+    game::{ERROR_COLOR, LIGHT_COLOR},
+    localization::{tr, tr_args},
+    menu::widgets::MOVE_FIELD,
+    move_struct::white_to_move,
+    render_utilities::translate_to_index,
+    scene::Scene,
+    sound::SoundEvent,
+    spectator::{Perspective, Spectator},
+    Game, STATE,
 };
 
 use ggez::timer;
 
 use crate::piece::piece::PieceColor;
 
-use crate::{
-    piece::{self, piece::PieceColor::*},
-    render_utilities,
-};
+use crate::{annotation, piece::piece::PieceColor::*, render_utilities};
 
 pub(crate) const BOARD_SIZE: usize = 8;
-pub(crate) const TILE_SIZE: i32 = 100;
-pub(crate) const BOARD_WIDTH: i32 = BOARD_SIZE as i32 * TILE_SIZE;
-
-pub(crate) const BOARD_ORIGO_X: f32 = SCREEN_WIDTH / 2.0 - (BOARD_WIDTH / 2) as f32;
-pub(crate) const BOARD_ORIGO_Y: f32 = SCREEN_HEIGHT / 2.0 - (BOARD_WIDTH / 2) as f32;
 
 impl EventHandler for Game {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
         while ggez::timer::check_update_time(ctx, 60) {
-            if self.game_active {
-                if self.active_turn {
-                    if self.time.current_time_left < self.time.turn_start.elapsed() {
-                        if self.playing_as_white {
-                            self.game_over(White);
-                            return Ok(());
-                        } else {
-                            self.game_over(Black);
-                            return Ok(());
-                        }
-                    }
+            if self.game_active && self.time.days_per_move.is_none() {
+                // The color of the player to move
+                let white = self.active_turn == self.playing_as_white;
+                if let Some(winner) = self.time.flag_winner(self.active_turn, white) {
+                    self.game_over(winner);
+                    return Ok(());
+                }
+            }
+
+            // In a correspondence game the player to move loses when the deadline passes.
+            // Only the room admin decides this, so clients with different clocks can't both end the game.
+            if self.game_active
+                && self.is_correspondence()
+                && self.is_admin
+                && self.correspondence_time_left() == Duration::ZERO
+            {
+                let winner = if white_to_move(self.move_history.len()) {
+                    Black
                 } else {
-                    if self.time.opponent_time_left < self.time.turn_start.elapsed() {
-                        if self.playing_as_white {
-                            self.game_over(Black);
-                            return Ok(());
-                        } else {
-                            self.game_over(White);
-                            return Ok(());
-                        }
-                    }
+                    White
+                };
+                self.connection
+                    .send("correspondence_timeout", winner.to_str());
+                self.game_over(winner);
+                return Ok(());
+            }
+
+            self.connection.poll();
+
+            let state_read = self.connection.state.clone();
+
+            if !state_read.incoming_moves.is_empty() {
+                // After the moves have been performed we remove them
+                let moves: Vec<_> = self.connection.state.incoming_moves.drain(..).collect();
+                for (ply, hash, move_) in moves {
+                    self.receive_move(ply, hash, move_);
                 }
             }
 
-            let state_read = STATE.get().read().unwrap().clone();
+            if let Some(ply) = state_read.event_validation.move_ack {
+                self.acknowledge_move(ply);
+                self.connection.state.event_validation.move_ack = None;
+            }
+            // Moves made while a correspondence opponent is offline are exchanged when they return
+            if state_read.opponent_online || !self.is_correspondence() {
+                self.resend_unacknowledged_move();
+            }
 
-            let incoming_move = state_read.incoming_move;
-            match incoming_move {
-                Some(move_) => {
-                    self.move_piece_from_board(move_);
-                    // After move has been performed we remove the values
-                    STATE.get().write().unwrap().incoming_move = None;
+            if state_read.event_validation.request_resync {
+                if self.is_admin {
+                    self.send_resync();
+                }
+                self.connection.state.event_validation.request_resync = false;
+            }
+            if let Some(data) = &state_read.event_validation.resync {
+                self.apply_resync(data);
+                self.connection.state.event_validation.resync = None;
+            }
+
+            if let Some(data) = &state_read.event_validation.correspondence_sync {
+                if self.is_correspondence() {
+                    self.apply_correspondence_sync(data);
+                }
+                self.connection.state.event_validation.correspondence_sync = None;
+            }
+
+            if let Some(winner) = state_read.event_validation.correspondence_timeout {
+                if self.is_correspondence() && !self.is_admin {
+                    self.game_over(winner);
+                }
+                self.connection
+                    .state
+                    .event_validation
+                    .correspondence_timeout = None;
+            }
+
+            // Saved games from the server, which might have moves we haven't seen
+            if let Some(data) = &state_read.event_validation.correspondence_games {
+                for game in CorrespondenceGame::parse_list(data) {
+                    self.correspondence.merge(game);
+                }
+                if self.menu.showing_my_games {
+                    self.refresh_menu_list();
+                }
+                self.connection.state.event_validation.correspondence_games = None;
+            }
+
+            // Back in the room of a saved correspondence game
+            if state_read.event_validation.join_room && self.is_correspondence() {
+                self.connection.send("send_name", "");
+                self.connection.send("get_opponent_name", "");
+                self.connection.state.event_validation.join_room = false;
+            }
+
+            // Moves of the spectated game
+            if !state_read.incoming_spectator_moves.is_empty() {
+                let moves: Vec<_> = self
+                    .connection
+                    .state
+                    .incoming_spectator_moves
+                    .drain(..)
+                    .collect();
+                if self.spectating.is_some() {
+                    for (move_, clocks) in moves {
+                        self.apply_spectate_move(move_, clocks);
+                    }
+                }
+            }
+
+            // Show the moves which have passed the broadcast delay to the spectators
+            if self.is_admin {
+                for (move_, clocks) in self.broadcast.release() {
+                    self.send_spectate_move(move_, clocks);
                 }
-                None => {}
             }
 
             if self.active_turn {
                 if let Some((piece, piece_dest_index)) = self.premove.take() {
                     if let Some(piece) = self.board[piece.get_index()].take() {
-                        self.attempt_move(piece, piece_dest_index)
+                        // The premoved piece is already shown at its destination
+                        self.animations.dropped = true;
+                        self.attempt_move(piece, piece_dest_index, None)
                     } else {
                         self.board[piece.get_index()] = Some(piece)
                     }
@@ -82,57 +162,106 @@ impl EventHandler for Game {
             // Check if network state has updated
             let event_validation = state_read.event_validation;
 
-            if self.menu.visible || self.winner.is_some() {
-                // Check if lobbies have changed
-                if self.lobby_sync != state_read.lobby_sync {
-                    self.menu.clear_list_items_from_list();
-                    self.menu.generate_list_item_from_list(&state_read.lobbies);
-                    self.lobby_sync = state_read.lobby_sync;
-                }
+            self.update_scene();
 
+            if self.scenes.contains(Scene::MainMenu) || self.scenes.contains(Scene::GameOver) {
                 if event_validation.create_room {
-                    self.menu.visible = false;
+                    self.scenes.reset(Scene::RoomSetup);
                     self.active_turn = true;
                     self.playing_as_white = true;
                     self.is_admin = true;
-                    self.time.turn_start = Instant::now();
+                    self.apply_default_time_control();
+                    self.time.start_turn();
                     self.update_available_moves();
-                    STATE.get().write().unwrap().event_validation.create_room = false;
+                    self.connection.state.event_validation.create_room = false;
                 }
 
                 // This user successfully joined the room
                 if event_validation.join_room {
-                    self.menu.visible = false;
+                    self.scenes.reset(Scene::RoomSetup);
 
                     // Send name to opponent
                     self.connection.send("send_name", "");
                     // Ask server for opponent name
                     self.connection.send("get_opponent_name", "");
                     self.update_available_moves();
-                    STATE.get().write().unwrap().opponent_online = true;
-                    STATE.get().write().unwrap().event_validation.join_room = false;
+                    self.connection.state.opponent_online = true;
+                    self.connection.state.event_validation.join_room = false;
+                }
+
+                // This user is now watching the room
+                if event_validation.spectate_room {
+                    self.is_admin = false;
+                    self.reset_game();
+                    self.spectating = Some(Spectator {
+                        perspective: Perspective::White,
+                        white_name: tr("color.white"),
+                        black_name: tr("color.black"),
+                    });
+                    self.playing_as_white = true;
+                    self.active_turn = true;
+                    // The clock is set by the room admin, which is received with the game sync
+                    self.scenes.reset(Scene::InGame);
+                    self.update_available_moves();
+                    self.connection.state.event_validation.spectate_room = false;
                 }
 
                 if event_validation.play_again {
                     self.reset_game();
                     self.playing_as_white = !self.playing_as_white;
                     self.active_turn = self.playing_as_white;
-                    self.time.turn_start = Instant::now();
+                    self.time.start_turn();
 
                     self.update_available_moves();
-                    STATE.get().write().unwrap().event_validation.play_again = false;
+                    if self.is_admin {
+                        self.send_spectate_sync();
+                    }
+                    self.connection.state.event_validation.play_again = false;
                 }
             }
 
+            // A spectator joined the room, the admin sends it the game so far
+            if event_validation.spectator_connect {
+                if self.is_admin {
+                    self.send_spectate_sync();
+                }
+                self.connection.state.event_validation.spectator_connect = false;
+            }
+
+            if let Some(data) = &event_validation.spectate_sync {
+                if self.spectating.is_some() {
+                    self.apply_spectate_sync(data);
+                }
+                self.connection.state.event_validation.spectate_sync = None;
+            }
+
+            // Keep the time control of a game hosted on the local network up to date
+            if !state_read.opponent_online && !self.is_correspondence() {
+                self.connection.announce_time(
+                    self.time.initial_time.as_secs(),
+                    self.time.increment.as_secs(),
+                    self.time.preset.as_deref().unwrap_or(""),
+                );
+            }
+
             // A new connection joined the game
-            if event_validation.opponent_connect {
+            // The opponent of a correspondence game came online, the color and clock are already known
+            if event_validation.opponent_connect && self.is_correspondence() {
+                self.connection.send("send_name", "");
+                self.send_correspondence_sync();
+                self.play_sound(SoundEvent::OpponentConnected);
+                self.connection.state.event_validation.opponent_connect = false;
+            } else if event_validation.opponent_connect {
                 println!("Opponent connect parsed!");
+                self.play_sound(SoundEvent::OpponentConnected);
+                // The game is no longer open for others on the local network
+                self.connection.stop_announcing();
                 // If the user is still in end game screen we force him into the game
                 if self.winner.is_some() {
                     self.reset_game();
                     self.playing_as_white = !self.playing_as_white;
                     self.active_turn = self.playing_as_white;
-                    self.time.turn_start = Instant::now();
+                    self.time.start_turn();
                 }
 
                 let color = if (self.winner.is_some() && self.playing_as_white)
@@ -149,24 +278,18 @@ impl EventHandler for Game {
                 // Tell the new connection which color it should have
                 // And what the clock should start at
                 self.connection.send("set_opponent_color", &color);
-                self.connection.send(
-                    "set_clock_time",
-                    &format!(
-                        "{}:{}",
-                        self.time.initial_time.as_secs(),
-                        self.time.increment.as_secs()
-                    )[..],
-                );
+                self.connection
+                    .send("set_clock_time", &self.time.control_string());
 
-                STATE
-                    .get()
-                    .write()
-                    .unwrap()
-                    .event_validation
-                    .opponent_connect = false;
+                self.connection.state.event_validation.opponent_connect = false;
             }
 
-            if event_validation.opponent_disconnect {
+            // Correspondence games continue while the opponent is offline
+            if event_validation.opponent_disconnect && self.is_correspondence() {
+                self.play_sound(SoundEvent::OpponentDisconnected);
+                self.connection.state.event_validation.opponent_disconnect = false;
+            } else if event_validation.opponent_disconnect && self.spectating.is_none() {
+                self.play_sound(SoundEvent::OpponentDisconnected);
                 if self.playing_as_white {
                     self.game_over(PieceColor::White);
                 } else {
@@ -174,50 +297,47 @@ impl EventHandler for Game {
                 }
 
                 // Clear opponent
-                STATE.get().write().unwrap().event_validation.opponent_name = None;
+                self.connection.state.event_validation.opponent_name = None;
 
-                STATE
-                    .get()
-                    .write()
-                    .unwrap()
-                    .event_validation
-                    .opponent_disconnect = false;
+                self.connection.state.event_validation.opponent_disconnect = false;
             }
 
             match event_validation.set_color {
                 Some(White) => {
                     self.playing_as_white = true;
                     self.active_turn = true;
-                    self.time.turn_start = Instant::now();
+                    self.time.start_turn();
 
                     self.update_available_moves();
-                    STATE.get().write().unwrap().event_validation.set_color = None;
+                    self.connection.state.event_validation.set_color = None;
                 }
                 Some(Black) => {
                     self.playing_as_white = false;
                     self.active_turn = false;
-                    self.time.turn_start = Instant::now();
+                    self.time.start_turn();
 
                     self.update_available_moves();
-                    STATE.get().write().unwrap().event_validation.set_color = None;
+                    self.connection.state.event_validation.set_color = None;
                 }
                 _ => {}
             }
             if event_validation.resign {
                 let winner = if self.playing_as_white { White } else { Black };
                 self.game_over(winner);
-                STATE.get().write().unwrap().event_validation.resign = false;
+                self.connection.state.event_validation.resign = false;
             }
 
-            match event_validation.time {
-                Some((total_time, increment)) => {
-                    self.time.time_set = true;
-                    self.time.initial_time = Duration::from_secs(total_time);
-                    self.time.increment = Duration::from_secs(increment);
+            match &event_validation.time {
+                Some(data) => {
+                    // The room admin has set the clock, which starts the game
+                    if self.scenes.current() == Scene::RoomSetup {
+                        self.scenes.replace(Scene::InGame);
+                    }
+                    self.time.apply_control_string(data);
+                    self.update_time_labels();
 
-                    self.time.current_time_left = self.time.initial_time;
-                    self.time.opponent_time_left = self.time.initial_time;
-                    STATE.get().write().unwrap().event_validation.time = None;
+                    self.reset_clocks();
+                    self.connection.state.event_validation.time = None;
                 }
                 None => {}
             }
@@ -226,12 +346,7 @@ impl EventHandler for Game {
                 ggez::input::mouse::set_cursor_grabbed(ctx, false)
                     .expect("Could not deselect cursor");
                 ggez::input::mouse::set_cursor_type(ctx, ggez::input::mouse::MouseCursor::Default);
-                STATE
-                    .get()
-                    .write()
-                    .unwrap()
-                    .event_validation
-                    .deselect_cursor = false;
+                self.connection.state.event_validation.deselect_cursor = false;
             }
         }
 
@@ -245,14 +360,12 @@ impl EventHandler for Game {
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx, graphics::BLACK);
 
-        let read_state = STATE.get().read().unwrap().clone();
-
         // Draw background
         match graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::fill(),
-            graphics::Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT),
-            graphics::Color::from(BACKGROUND_COLOR),
+            self.menu.layout.screen(),
+            self.theme().background,
         ) {
             Ok(background) => {
                 graphics::draw(ctx, &background, graphics::DrawParam::default())
@@ -261,323 +374,328 @@ impl EventHandler for Game {
             Err(_) => {}
         }
 
-        if read_state.entering_name {
-            self.menu.render_name_interface(ctx);
-            self.menu
-                .draw_clickables(ctx, vec![ClickableGroup::EnterName]);
-            return graphics::present(ctx);
+        // Overlays such as the game over menu are drawn on top of the scene below them
+        for scene in self.scenes.visible() {
+            self.draw_scene(ctx, scene)?;
         }
 
-        // If menu is active we don't bother showing the rest of the game
-        if self.menu.visible {
-            self.menu.render(ctx);
-            return graphics::present(ctx);
+        graphics::present(ctx)
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        match button {
+            MouseButton::Left => self.scene_mouse_down(ctx, x, y),
+            MouseButton::Right => self.scene_right_mouse_down(ctx, x, y),
+            _ => {}
+        }
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        button: ggez::event::MouseButton,
+        x: f32,
+        y: f32,
+    ) {
+        match button {
+            MouseButton::Left => self.scene_mouse_up(ctx, x, y),
+            MouseButton::Right => self.scene_right_mouse_up(x, y),
+            _ => {}
         }
+    }
 
-        if !self.time.time_set {
-            self.render_time_interface(ctx);
-            return graphics::present(ctx);
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        self.scene_mouse_move(ctx, x, y);
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) {
+        self.scene_mouse_wheel(ctx, y);
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        self.scene_text_input(character);
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        match keycode {
+            KeyCode::Escape => event::quit(ctx),
+            KeyCode::F3 => self.cycle_theme(ctx),
+            KeyCode::F4 => self.cycle_animation_speed(),
+            KeyCode::F11 => self.toggle_fullscreen(ctx),
+            _ => self.scene_key_down(keycode),
         }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        // Draw in window pixels instead of stretching the old screen
+        graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height))
+            .expect("Could not set screen coordinates");
+        self.resize(ctx, width, height);
+    }
+}
+
+impl Game {
+    pub(crate) fn draw_game(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let read_state = self.connection.state.clone();
+
+        let board = self.board_geometry;
+        let (top_bar, bottom_bar) = (
+            self.menu.layout.rect("top_bar"),
+            self.menu.layout.rect("bottom_bar"),
+        );
 
         // Draws the background board
         graphics::draw(
             ctx,
             &self.board_mesh,
-            (Point2::<f32>::new(BOARD_ORIGO_X, BOARD_ORIGO_Y),),
+            (Point2::<f32>::new(board.x, board.y),),
         )?;
 
         // Draw room code
         if let Some(id) = &read_state.room_id {
+            let mut room_text = tr_args("game.room_code", &[("code", id.replace("\"", ""))]);
+            if let Some(preset) = &self.time.preset {
+                room_text.push_str(&format!(" ({})", preset));
+            }
             self.menu.draw_text(
                 ctx,
-                format!("Room code: {}", id.clone().replace("\"", "")),
-                (BOARD_ORIGO_X, 50.0 / 2.0 - 40.0 / 2.0),
-                (BOARD_WIDTH as f32, 40.0),
+                room_text,
+                (board.x, top_bar.y),
+                (board.width(), top_bar.h),
                 graphics::Color::from(LIGHT_COLOR),
                 graphics::Align::Right,
             );
         }
 
-        render_utilities::render_movement_indication(&self, ctx)?;
+        let view = self.board_view();
+        self.update_animations(&view);
+        render_utilities::render_movement_indication(&self, &view, ctx)?;
+        annotation::render_highlights(self, ctx)?;
 
-        render_utilities::render_fog_and_pieces(&self, ctx)?;
-
-        piece::promotion::render_promotion_interface(&self, ctx)?;
+        render_utilities::render_fog_and_pieces(&self, &view, ctx)?;
+        render_utilities::render_coordinates(&self, ctx)?;
+        annotation::render_arrows(self, ctx)?;
 
         // Draw opponent name
-        let mut display_name = String::from("Awaiting player...");
+        let mut display_name = tr("game.awaiting_player");
         let opponent_name = read_state.event_validation.opponent_name.clone();
         if let Some(name) = opponent_name {
             display_name = name;
         }
+        // Spectators see the names of both players, the bottom one being the side they watch from
+        let mut name = STATE.get().read().unwrap().name.clone();
+        if let Some(spectator) = &self.spectating {
+            if self.playing_as_white {
+                display_name = spectator.black_name.clone();
+                name = spectator.white_name.clone();
+            } else {
+                display_name = spectator.white_name.clone();
+                name = spectator.black_name.clone();
+            }
+        }
+        // When the view is flipped the player at the top is shown at the bottom
+        if self.board_flipped {
+            std::mem::swap(&mut display_name, &mut name);
+        }
 
+        let display_name_width = self.menu.text_width(ctx, &display_name, top_bar.h);
         self.menu.draw_text(
             ctx,
             display_name,
-            (BOARD_ORIGO_X, 50.0 / 2.0 - 40.0 / 2.0),
-            (
-                BOARD_WIDTH as f32,
-                40.0, // Same height as the room code text
-            ),
+            (board.x, top_bar.y),
+            (board.width(), top_bar.h), // Same height as the room code text
             graphics::Color::from(LIGHT_COLOR),
             graphics::Align::Left,
         );
 
         // Draw name
-        let name = read_state.name.clone();
+        let name_width = self.menu.text_width(ctx, &name, bottom_bar.h);
         self.menu.draw_text(
             ctx,
             name,
-            (BOARD_ORIGO_X, SCREEN_HEIGHT - 50.0 / 2.0 - 40.0 / 2.0),
-            (
-                BOARD_WIDTH as f32,
-                40.0, // Same height as the room code text
-            ),
+            (board.x, bottom_bar.y),
+            (board.width(), bottom_bar.h),
             graphics::Color::from(LIGHT_COLOR),
             graphics::Align::Left,
         );
 
-        self.render_time(ctx);
-
-        self.menu.draw_clickables(ctx, vec![ClickableGroup::InGame]);
-
-        // Draw game over menu
-        if self.winner.is_some() {
-            self.menu.render_game_over(ctx, self.winner);
-        }
-
-        graphics::present(ctx)
-    }
-
-    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
-        match button {
-            MouseButton::Left => {
-                let read_state = STATE.get().read().unwrap().clone();
-
-                // UI logic
-
-                let mut parsing_groups: Vec<ClickableGroup> = Vec::new();
-                if read_state.entering_name {
-                    parsing_groups.push(ClickableGroup::EnterName);
-                } else if self.menu.visible {
-                    parsing_groups.push(ClickableGroup::MainMenu);
-                    parsing_groups.push(ClickableGroup::MainMenuList);
-                } else if self.winner.is_some() {
-                    parsing_groups.push(ClickableGroup::GameOverMenu);
-                } else if !self.time.time_set {
-                    parsing_groups.push(ClickableGroup::TimeSelection);
+        // The pieces each player has captured are shown after their name
+        let (bottom_color, top_color) = if self.white_at_bottom() {
+            (White, Black)
+        } else {
+            (Black, White)
+        };
+        let tray = |bar: graphics::Rect, name_width: f32| {
+            let x = board.x + name_width + 20.0;
+            graphics::Rect::new(
+                x,
+                bar.y + bar.h * 0.1,
+                board.x + board.width() - x,
+                bar.h * 0.8,
+            )
+        };
+        self.draw_captures(ctx, top_color, tray(top_bar, display_name_width))?;
+        self.draw_captures(ctx, bottom_color, tray(bottom_bar, name_width))?;
+
+        // Draw the move box with the response to the last typed move below it
+        if self.spectating.is_none() {
+            let move_input = self.menu.layout.rect("move_input");
+            MOVE_FIELD.draw(&mut self.menu, ctx, &self.move_entry.text, move_input);
+            let feedback = self.menu.layout.rect("move_feedback");
+            self.menu.draw_text(
+                ctx,
+                self.move_entry.feedback.clone(),
+                (feedback.x, feedback.y),
+                (feedback.w, feedback.h),
+                graphics::Color::from(if self.move_entry.is_error {
+                    ERROR_COLOR
                 } else {
-                    parsing_groups.push(ClickableGroup::InGame);
-                }
-                // Button logic
-                self.button_parsing(parsing_groups);
+                    LIGHT_COLOR
+                }),
+                graphics::Align::Left,
+            );
+        }
 
-                if read_state.entering_name
-                    || self.menu.visible
-                    || self.winner.is_some()
-                    || !self.time.time_set
-                {
-                    return;
-                }
+        self.render_time(ctx);
+        self.draw_move_list(ctx)?;
 
-                //------------------------------------------------------
+        let groups = self.clickable_groups(Scene::InGame);
+        self.menu.draw_clickables(ctx, groups);
 
-                // If there is no opponent we cannot make moves
-                if !read_state.opponent_online {
-                    return;
-                }
+        Ok(())
+    }
 
-                // Cursor out of bounds checking
-                if x > BOARD_ORIGO_X + BOARD_WIDTH as f32
-                    || y > BOARD_ORIGO_Y + BOARD_WIDTH as f32
-                    || x < BOARD_ORIGO_X
-                    || y < BOARD_ORIGO_Y
-                {
-                    // Lets you cancel your premoves by clicking on something that's not interactive
-                    self.premove = None;
-                    self.selected_piece = None;
-                    return;
-                }
-                // Calculates (on screen) list index (if cursor is in bounds) of the clicked tile
-                let x_tile = (x - BOARD_ORIGO_X) as usize / TILE_SIZE as usize;
-                let y_tile = (y - BOARD_ORIGO_Y) as usize / TILE_SIZE as usize;
+    pub(crate) fn board_mouse_down(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        let read_state = self.connection.state.clone();
 
-                let mut clicked_index = translate_to_index(x_tile, y_tile);
-                if self.playing_as_white {
-                    clicked_index = flip_index(clicked_index);
-                }
+        // Clicking the board while previewing an earlier position returns to the live game
+        if self.move_list.preview.is_some() {
+            if self.board_geometry.tile_at(x, y).is_some() {
+                self.preview_position(self.move_history.len());
+            }
+            return;
+        }
 
-                // Pawn promotion interface
-                piece::promotion::check_promotion(self, x_tile, y_tile);
+        // If there is no opponent we cannot make moves, and spectators can never make moves
+        // Correspondence games are the exception, the opponent gets the moves when they return
+        if (!read_state.opponent_online && !self.is_correspondence()) || self.spectating.is_some() {
+            return;
+        }
 
-                let mut had_selected = false;
+        // Calculates (on screen) list index (if cursor is in bounds) of the clicked tile
+        let (x_tile, y_tile) = match self.board_geometry.tile_at(x, y) {
+            Some(tile) => tile,
+            None => {
+                // Lets you cancel your premoves by clicking on something that's not interactive
+                self.premove = None;
+                self.selected_piece = None;
+                return;
+            }
+        };
 
-                // If a piece has been selected by clicking, try to move to the clicked tile
-                if let Some(piece) = self.selected_piece.take() {
-                    had_selected = true;
-                    let mut piece_dest_index = translate_to_index(x_tile, y_tile);
+        let clicked_index = self.screen_index(translate_to_index(x_tile, y_tile));
 
-                    if self.playing_as_white {
-                        piece_dest_index = flip_index(piece_dest_index);
-                    }
+        let mut had_selected = false;
 
-                    // If the player has selected a piece that's present on the board, attempt to move
-                    if let Some(piece) = self.board[piece.get_index()].take() {
-                        self.attempt_move(piece, piece_dest_index);
-                    }
+        // If a piece has been selected by clicking, try to move to the clicked tile
+        if let Some(piece) = self.selected_piece.take() {
+            had_selected = true;
+            let piece_dest_index = self.screen_index(translate_to_index(x_tile, y_tile));
 
-                    // Prevents attempting to grab a piece which has just been unselected
-                    if piece.index == clicked_index {
-                        return;
-                    }
-                }
-
-                // Attempt to grab a piece from the clicked tile
-                if let Some(piece) = self.board[clicked_index].clone().take() {
-                    // Prevents you from grabbing the piece you just premoved
-                    if let Some((p, _d)) = &self.premove {
-                        if p.get_index() == clicked_index {
-                            return;
-                        }
-                    }
+            // If the player has selected a piece that's present on the board, attempt to move
+            if let Some(piece) = self.board[piece.get_index()].take() {
+                self.attempt_move(piece, piece_dest_index, None);
+            }
 
-                    if let Some(m) = self.move_history.last() {
-                        // Prevents the player from grabbing directly after making a move by selecting-by-clicking
-                        if m.piece_dest_index == piece.get_index() && had_selected {
-                            return;
-                        }
-                    }
-                    match &piece.color {
-                        White if !self.playing_as_white => {
-                            // Cancel premoves if attempting to select an opposing piece
-                            if !had_selected {
-                                self.premove.take();
-                            }
-
-                            return;
-                        }
-                        Black if self.playing_as_white => {
-                            // Cancel premoves if attempting to select an opposing piece
-                            if !had_selected {
-                                self.premove.take();
-                            }
-                            return;
-                        }
-                        _ => {}
-                    }
-                    self.grabbed_piece = Some(piece);
-                    // Lock the cursor inside the application
-                    ggez::input::mouse::set_cursor_grabbed(ctx, true).expect("Cursor grab failed");
-                    ggez::input::mouse::set_cursor_type(ctx, ggez::input::mouse::MouseCursor::Hand)
-                }
-                // If a piece was selected when this function was called, don't interpret a the click as a premove cancel
-                else if !had_selected {
-                    // Lets you cancel your premoves by clicking on something that's not interactive
-                    self.premove.take();
-                }
+            // Prevents attempting to grab a piece which has just been unselected
+            if piece.index == clicked_index {
+                return;
             }
-            _ => {}
         }
-    }
 
-    fn mouse_button_up_event(
-        &mut self,
-        ctx: &mut ggez::Context,
-        button: ggez::event::MouseButton,
-        x: f32,
-        y: f32,
-    ) {
-        match button {
-            MouseButton::Left => {
-                // UI logic
-                if self.menu.visible || self.winner.is_some() || !self.time.time_set {
+        // Attempt to grab a piece from the clicked tile
+        if let Some(piece) = self.board[clicked_index].clone().take() {
+            // Prevents you from grabbing the piece you just premoved
+            if let Some((p, _d)) = &self.premove {
+                if p.get_index() == clicked_index {
                     return;
                 }
+            }
 
-                //------------------------------------------------------
-
-                ggez::input::mouse::set_cursor_grabbed(ctx, false).expect("Cursor release fail");
-                ggez::input::mouse::set_cursor_type(ctx, ggez::input::mouse::MouseCursor::Default);
-
-                if let Some(piece) = self.grabbed_piece.take() {
-                    // Calculates list index (if in bounds) of the clicked tile
-                    let x_tile = ((x - BOARD_ORIGO_X) / TILE_SIZE as f32) as usize;
-                    let y_tile = ((y - BOARD_ORIGO_Y) / TILE_SIZE as f32) as usize;
-
-                    let mut piece_dest_index = translate_to_index(x_tile, y_tile);
-                    let piece_source_index = piece.index;
-
-                    if self.playing_as_white {
-                        piece_dest_index = flip_index(piece_dest_index);
-                    }
-
-                    // If the cursor is released on the same tile as it was grabbed on, go into "click & select" mode instead of "drag & drop" mode
-                    if piece_dest_index == piece_source_index {
-                        self.selected_piece = Some(piece);
-                        return;
+            if let Some(m) = self.move_history.last() {
+                // Prevents the player from grabbing directly after making a move by selecting-by-clicking
+                if m.piece_dest_index == piece.get_index() && had_selected {
+                    return;
+                }
+            }
+            match &piece.color {
+                White if !self.playing_as_white => {
+                    // Cancel premoves if attempting to select an opposing piece
+                    if !had_selected {
+                        self.premove.take();
                     }
 
-                    // Out of bounds checking
-                    if x - BOARD_ORIGO_X > BOARD_WIDTH as f32
-                        || y - BOARD_ORIGO_Y > BOARD_WIDTH as f32
-                        || x < BOARD_ORIGO_X
-                        || y < BOARD_ORIGO_Y
-                    {
-                        // If we are out of bounds then the grab is cancelled
-                        println!("Out of bounds");
-                        return;
-                    }
-                    if self.premove.is_some() {
-                        self.attempt_move(piece, piece_dest_index);
-                    } else if let Some(piece) = self.board[piece.get_index()].take() {
-                        self.attempt_move(piece, piece_dest_index);
+                    return;
+                }
+                Black if self.playing_as_white => {
+                    // Cancel premoves if attempting to select an opposing piece
+                    if !had_selected {
+                        self.premove.take();
                     }
-                } else {
                     return;
                 }
+                _ => {}
             }
-            _ => {}
+            self.grabbed_piece = Some(piece);
+            // Lock the cursor inside the application
+            ggez::input::mouse::set_cursor_grabbed(ctx, true).expect("Cursor grab failed");
+            ggez::input::mouse::set_cursor_type(ctx, ggez::input::mouse::MouseCursor::Hand)
         }
-    }
-
-    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
-        let read_state = STATE.get().read().unwrap();
-        // Entering name state
-        let mut parsing_groups: Vec<ClickableGroup> = Vec::new();
-        if read_state.entering_name {
-            parsing_groups.push(ClickableGroup::EnterName);
-        } else if self.menu.visible {
-            parsing_groups.push(ClickableGroup::MainMenu);
-            parsing_groups.push(ClickableGroup::MainMenuList);
-        } else if self.winner.is_some() {
-            parsing_groups.push(ClickableGroup::GameOverMenu);
-        } else if !self.time.time_set {
-            parsing_groups.push(ClickableGroup::TimeSelection);
-        } else {
-            parsing_groups.push(ClickableGroup::InGame);
+        // If a piece was selected when this function was called, don't interpret a the click as a premove cancel
+        else if !had_selected {
+            // Lets you cancel your premoves by clicking on something that's not interactive
+            self.premove.take();
         }
-
-        self.menu.on_mouse_move(ctx, x, y, parsing_groups);
     }
 
-    fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) {
-        self.menu.on_mouse_wheel(ctx, y);
-    }
+    pub(crate) fn board_mouse_up(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        ggez::input::mouse::set_cursor_grabbed(ctx, false).expect("Cursor release fail");
+        ggez::input::mouse::set_cursor_type(ctx, ggez::input::mouse::MouseCursor::Default);
+
+        if let Some(piece) = self.grabbed_piece.take() {
+            // Calculates list index (if in bounds) of the clicked tile
+            let (x_tile, y_tile) = match self.board_geometry.tile_at(x, y) {
+                Some(tile) => tile,
+                None => {
+                    // If we are out of bounds then the grab is cancelled
+                    println!("Out of bounds");
+                    return;
+                }
+            };
 
-    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
-        // Name input when the game is launched
-        if STATE.get().read().unwrap().entering_name {
-            let mut name = STATE.get().read().unwrap().name.clone();
+            let piece_dest_index = self.screen_index(translate_to_index(x_tile, y_tile));
+            let piece_source_index = piece.index;
 
-            // 8u8 is the ASCII code for backspace
-            if character == (8u8 as char) {
-                name.pop();
-            } else if character != ' ' {
-                name.push(character);
+            // If the cursor is released on the same tile as it was grabbed on, go into "click & select" mode instead of "drag & drop" mode
+            if piece_dest_index == piece_source_index {
+                self.selected_piece = Some(piece);
+                return;
             }
-            if name.len() <= 20 {
-                STATE.get().write().unwrap().name = String::from(name);
+            // The piece is dropped at its destination, so the move isn't animated
+            self.animations.dropped = true;
+            if self.premove.is_some() {
+                self.attempt_move(piece, piece_dest_index, None);
+            } else if let Some(piece) = self.board[piece.get_index()].take() {
+                self.attempt_move(piece, piece_dest_index, None);
             }
+        } else {
+            return;
         }
     }
 }
@@ -1,50 +1,52 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use ggez::{
-    audio::{SoundSource, Source},
-    graphics::{Color, DrawMode, Mesh, MeshBuilder, Rect},
+    conf::FullscreenType,
+    graphics::{self, DrawMode, Mesh, MeshBuilder, Rect},
     Context,
 };
 
 use crate::{
+    animation::Animations,
+    annotation::Annotations,
+    clock::{SystemClock, TimeSource},
+    correspondence::CorrespondenceStore,
     default_board_state::generate_default_board,
+    localization::{tr, tr_args},
     menu::{
-        clickable::{Clickable, Transform},
+        clickable::{Clickable, UiAction},
+        create_clickable_layout::MY_GAMES_TEXTS,
         menu_state::Menu,
     },
+    move_entry::MoveEntry,
+    move_list::MoveList,
     move_struct::MoveType,
     piece::piece::{self, Board, Piece, PieceColor::*, PieceType::*, *},
-    time::Time,
+    piece::promotion::promote,
+    scene::{Scene, SceneStack},
+    settings::Settings,
+    sound::{Sound, SoundEvent},
+    spectator::{delay_label, Broadcast, Perspective, Spectator, SPECTATOR_DELAYS},
+    theme::{Theme, Themes},
+    time::{Bonus, ClockSide, Time},
+    time_presets::{escape_preset, TimePresets},
 };
 
-use crate::{
-    menu::{
-        clickable::ClickableGroup,
-        menu_game_over::{
-            GAME_OVER_MENU_HEIGHT, GAME_OVER_MENU_WIDTH, GAME_OVER_START_X, GAME_OVER_START_Y,
-        },
-    },
-    STATE,
-};
+use crate::{menu::clickable::ClickableGroup, STATE};
 
 use crate::{
     event_handler::BOARD_SIZE,
     move_struct::{Move, MoveType::*},
-    render_utilities::{translate_to_coords, translate_to_index},
+    render_utilities::{translate_to_coords, translate_to_index, BoardGeometry},
 };
 
-use crate::{event_handler::TILE_SIZE, networking::connection::Networking};
+use crate::networking::{connection::Networking, move_sync::PendingMove, peer::DEFAULT_PEER_PORT};
 
-pub(crate) const BACKGROUND_COLOR: (u8, u8, u8) = (57, 43, 20);
+// The colors of the menus, the board is drawn with the colors of the selected theme
 pub(crate) const DARK_COLOR: (u8, u8, u8) = (181, 136, 99);
 pub(crate) const LIGHT_COLOR: (u8, u8, u8) = (240, 217, 181);
 pub(crate) const ERROR_COLOR: (u8, u8, u8) = (176, 0, 32);
 
-pub(crate) struct Sound {
-    pub(crate) movement: Source,
-    pub(crate) capture: Source,
-    pub(crate) game_end: Source,
-}
 // Main struct
 pub(crate) struct Game {
     pub(crate) board: Board,
@@ -52,95 +54,140 @@ pub(crate) struct Game {
     pub(crate) selected_piece: Option<Piece>,
     pub(crate) playing_as_white: bool,
     pub(crate) board_mesh: Mesh,
+    pub(crate) board_geometry: BoardGeometry,
+    pub(crate) fullscreen: bool,
+    pub(crate) themes: Themes,
+    pub(crate) settings: Settings,
+    pub(crate) board_flipped: bool, // The board is turned around from the player's own side
     pub(crate) active_turn: bool,
     pub(crate) connection: Networking,
     pub(crate) menu: Menu,
-    pub(crate) lobby_sync: i32,
+    pub(crate) scenes: SceneStack,
+    pub(crate) lobby_sync: (i32, i32), // The lobby lists shown in the main menu, see current_lobby_sync
     pub(crate) move_history: Vec<Move>,
     pub(crate) promoting_pawn: Option<Move>,
     pub(crate) available_moves: Vec<usize>,
     pub(crate) premove: Option<(Piece, usize)>, // Piece to move and destination index
+    pub(crate) move_entry: MoveEntry,
+    pub(crate) move_list: MoveList,
+    pub(crate) animations: Animations,
+    pub(crate) annotations: Annotations,
     pub(crate) winner: Option<PieceColor>,
     pub(crate) is_admin: bool,
     pub(crate) time: Time,
     pub(crate) game_active: bool,
     pub(crate) sound: Sound,
+    pub(crate) spectating: Option<Spectator>,
+    pub(crate) broadcast: Broadcast,
+    pub(crate) pending_move: Option<PendingMove>,
+    pub(crate) presets: TimePresets,
+    pub(crate) correspondence: CorrespondenceStore,
 }
 
 impl Game {
-    pub(crate) fn new(ctx: &mut Context) -> Game {
+    pub(crate) fn new(
+        ctx: &mut Context,
+        connection: Networking,
+        settings: Settings,
+        audio: bool,
+    ) -> Game {
         let mut menu = Menu::new(ctx);
         // Create button for main menu
         menu.create_clickables();
+        let config_dir = ggez::filesystem::user_config_dir(ctx).to_path_buf();
+        let presets = TimePresets::load(config_dir.clone());
+        let correspondence = CorrespondenceStore::load(&config_dir);
+        menu.create_custom_preset_clickables(&presets.custom);
+        let board_geometry = BoardGeometry::fit(menu.layout.rect("board"));
+        let themes = Themes::load(ctx, &config_dir);
+        let source: Box<dyn TimeSource> = Box::new(SystemClock);
 
         Game {
             board: generate_default_board(), // Load/create resources such as images here.
             grabbed_piece: None,
             selected_piece: None,
             playing_as_white: false,
-            board_mesh: Game::get_board_mesh(ctx),
+            board_mesh: Game::get_board_mesh(ctx, board_geometry.tile_size, themes.current()),
+            board_geometry,
+            fullscreen: false,
+            themes,
+            settings,
+            board_flipped: false,
             active_turn: false,
-            connection: Networking::new(),
+            connection,
             menu,
-            lobby_sync: 0,
+            lobby_sync: (0, 0),
             move_history: Vec::new(),
             promoting_pawn: None,
             available_moves: Vec::new(),
             premove: None,
+            move_entry: MoveEntry::default(),
+            move_list: MoveList::default(),
+            animations: Animations::default(),
+            annotations: Annotations::default(),
             winner: None,
+            scenes: SceneStack::new(Scene::EnterName),
             is_admin: false,
             time: Time {
                 current_time_left: Duration::new(300, 0),
                 opponent_time_left: Duration::new(300, 0),
-                turn_start: Instant::now(),
+                turn_start: source.now(),
                 initial_time: Duration::new(300, 0),
                 increment: Duration::new(0, 0),
-                time_set: false,
+                black_time: None,
+                editing: ClockSide::Both,
+                bonus: Bonus::Increment,
+                stages: Vec::new(),
+                preset: None,
+                low_time_threshold: Duration::from_secs(20),
+                low_time_warned: false,
+                days_per_move: None,
+                last_move_time: source.system_time(),
+                source,
             },
             game_active: false,
-            sound: Sound {
-                movement: ggez::audio::Source::new(ctx, "/move.ogg").unwrap(),
-                capture: ggez::audio::Source::new(ctx, "/capture.ogg").unwrap(),
-                game_end: ggez::audio::Source::new(ctx, "/game_end.ogg").unwrap(),
-            },
+            sound: Sound::load(ctx, audio),
+            spectating: None,
+            broadcast: Broadcast::new(),
+            pending_move: None,
+            presets,
+            correspondence,
         }
     }
 
     // Start a game and start the clocks
-    fn start_game(&mut self) {
+    pub(crate) fn start_game(&mut self) {
         // Cannot start game while in progress
         if self.game_active || self.winner.is_some() {
             return;
         }
-        self.time.current_time_left = self.time.initial_time;
-        self.time.opponent_time_left = self.time.initial_time;
+        self.reset_clocks();
         self.game_active = true;
     }
 
-    fn get_board_mesh(ctx: &mut Context) -> Mesh {
+    pub(crate) fn get_board_mesh(ctx: &mut Context, tile_size: f32, theme: &Theme) -> Mesh {
         let mut mesh_builder = MeshBuilder::new();
 
         let get_rect = |x_index: i32, y_index: i32| {
-            return Rect::new_i32(
-                x_index * TILE_SIZE,
-                y_index * TILE_SIZE,
-                TILE_SIZE,
-                TILE_SIZE,
+            return Rect::new(
+                x_index as f32 * tile_size,
+                y_index as f32 * tile_size,
+                tile_size,
+                tile_size,
             );
         };
         // Calculate sprite batch
         for row in 0..8 {
             for column in 0..8 {
-                let color: (u8, u8, u8);
-                if (column + row) % 2 == 0 {
+                let color = if (column + row) % 2 == 0 {
                     // White
-                    color = LIGHT_COLOR;
+                    theme.light_tile
                 } else {
-                    color = DARK_COLOR;
-                }
+                    theme.dark_tile
+                };
 
                 // Create Rectangle in mesh at position
-                mesh_builder.rectangle(DrawMode::fill(), get_rect(column, row), Color::from(color));
+                mesh_builder.rectangle(DrawMode::fill(), get_rect(column, row), color);
             }
         }
         let mesh = mesh_builder
@@ -149,6 +196,28 @@ impl Game {
         mesh
     }
 
+    /// Places the menus and the board for a new window size
+    pub(crate) fn resize(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.menu.layout.resize(width, height);
+        self.menu.relayout();
+
+        self.board_geometry = BoardGeometry::fit(self.menu.layout.rect("board"));
+        self.board_mesh =
+            Game::get_board_mesh(ctx, self.board_geometry.tile_size, self.themes.current());
+    }
+
+    pub(crate) fn toggle_fullscreen(&mut self, ctx: &mut Context) {
+        let fullscreen_type = if self.fullscreen {
+            FullscreenType::Windowed
+        } else {
+            FullscreenType::Desktop
+        };
+        match graphics::set_fullscreen(ctx, fullscreen_type) {
+            Ok(_) => self.fullscreen = !self.fullscreen,
+            Err(e) => println!("Could not change fullscreen mode: {}", e),
+        }
+    }
+
     pub(crate) fn move_piece_from_board(&mut self, move_: Move) {
         let piece_source_index = move_.piece.index;
         let piece_dest_index = move_.piece_dest_index;
@@ -169,19 +238,19 @@ impl Game {
                 color: piece.color,
                 index: piece_dest_index,
             });
-            self.move_history.push(Move {
+            self.perform_time_increment();
+            self.record_move(Move {
                 piece: piece,
                 piece_dest_index: piece_dest_index,
                 captured_piece,
                 move_type: Promotion(piece_type),
             });
-            self.perform_time_increment();
             // Your turn is over once you've made a move
             self.active_turn = !self.active_turn;
             if !self.game_active {
                 self.start_game();
             }
-            self.time.turn_start = Instant::now();
+            self.time.start_turn();
             self.update_available_moves();
         } else {
             println!("Moving! active_turn: {}", self.active_turn);
@@ -326,18 +395,22 @@ impl Game {
 
     /// Updates self.available_moves, called at the end of every turn
     pub(crate) fn update_available_moves(&mut self) {
-        let mut available_moves: Vec<usize> = Vec::new();
-        for tile in &self.board {
-            if let Some(piece) = tile {
-                if (piece.color == White && self.playing_as_white)
-                    || (piece.color == Black && !self.playing_as_white)
-                {
-                    available_moves.push(piece.index);
-                    available_moves.append(&mut piece::get_valid_move_indices(self, &piece, false));
-                }
-            }
+        // Spectators watching the full board can see every tile
+        if let Some(Spectator {
+            perspective: Perspective::Full,
+            ..
+        }) = &self.spectating
+        {
+            self.available_moves = (0..BOARD_SIZE * BOARD_SIZE).collect();
+            return;
         }
-        self.available_moves = available_moves;
+
+        let color = if self.playing_as_white { White } else { Black };
+        let previous = std::mem::replace(
+            &mut self.available_moves,
+            piece::visible_tiles(&self.board, &self.move_history, color),
+        );
+        self.vision_sounds(&previous);
     }
 
     fn move_to_end_turn(
@@ -359,21 +432,10 @@ impl Game {
             move_type,
         };
 
-        // Play sound
-        if captured_piece.is_some() {
-            self.sound
-                .capture
-                .play()
-                .expect("Could not play capture sound");
-        } else {
-            self.sound
-                .movement
-                .play()
-                .expect("Could not play movement sound");
-        }
+        self.play_move_sound(&move_);
 
-        if self.active_turn {
-            self.connection.send("opponent", &move_.to_string());
+        if self.active_turn && self.spectating.is_none() {
+            self.send_move(move_);
             self.grabbed_piece = None;
             self.selected_piece = None;
 
@@ -387,8 +449,18 @@ impl Game {
         if !self.game_active {
             self.start_game();
         }
-        self.time.turn_start = Instant::now();
+        self.time.start_turn();
+        self.record_move(move_);
+    }
+
+    /// Adds a move to the move history and queues it for the spectators
+    pub(crate) fn record_move(&mut self, move_: Move) {
+        if self.is_admin {
+            let clocks = self.clocks();
+            self.broadcast.push(move_, clocks);
+        }
         self.move_history.push(move_);
+        self.save_correspondence();
     }
 
     pub(crate) fn game_over(&mut self, winning_color: PieceColor) {
@@ -396,22 +468,18 @@ impl Game {
         if self.winner.is_some() {
             return;
         }
+        self.finish_correspondence();
 
         self.game_active = false;
+        // The game over menu is not shown to players who have already left the room
+        if !self.scenes.contains(Scene::MainMenu) {
+            self.scenes.push(Scene::GameOver);
+        }
         self.grabbed_piece = None;
         self.selected_piece = None;
-        STATE
-            .get()
-            .write()
-            .unwrap()
-            .event_validation
-            .deselect_cursor = true;
-
-        // Play game over sound
-        self.sound
-            .game_end
-            .play()
-            .expect("Could not play game over sound");
+        self.connection.state.event_validation.deselect_cursor = true;
+
+        self.play_sound(SoundEvent::GameEnd);
 
         match winning_color {
             PieceColor::White => {
@@ -424,36 +492,19 @@ impl Game {
             }
         }
 
-        self.menu.clickables.push(Clickable {
-            transform: Transform {
-                x: (GAME_OVER_START_X + 100.0) as i32,
-                y: (GAME_OVER_START_Y + GAME_OVER_MENU_HEIGHT - 100.0) as i32,
-                width: (GAME_OVER_MENU_WIDTH * 0.3) as i32,
-                height: (GAME_OVER_MENU_HEIGHT * 0.1) as i32,
-            },
-            id: String::from("play_again"),
-            text: String::from("Play again"),
-            list_item: false,
-            hovered: false,
-            color: Color::from(LIGHT_COLOR),
-            group: ClickableGroup::GameOverMenu,
-        });
-
-        self.menu.clickables.push(Clickable {
-            transform: Transform {
-                x: (GAME_OVER_START_X + GAME_OVER_MENU_WIDTH - 100.0 - GAME_OVER_MENU_WIDTH * 0.3)
-                    as i32,
-                y: (GAME_OVER_START_Y + GAME_OVER_MENU_HEIGHT - 100.0) as i32,
-                width: (GAME_OVER_MENU_WIDTH * 0.3) as i32,
-                height: (GAME_OVER_MENU_HEIGHT * 0.1) as i32,
-            },
-            id: String::from("goto_main_menu"),
-            text: String::from("Leave"),
-            list_item: false,
-            hovered: false,
-            color: Color::from(LIGHT_COLOR),
-            group: ClickableGroup::GameOverMenu,
-        });
+        self.menu.clickables.push(Clickable::button(
+            UiAction::PlayAgain,
+            self.menu.place(&UiAction::PlayAgain),
+            tr("game_over.play_again"),
+            ClickableGroup::GameOverMenu,
+        ));
+
+        self.menu.clickables.push(Clickable::button(
+            UiAction::GotoMainMenu,
+            self.menu.place(&UiAction::GotoMainMenu),
+            tr("game_over.leave"),
+            ClickableGroup::GameOverMenu,
+        ));
 
         // Prevent the current buttons from being cliked next time
         // a button is clicked
@@ -467,164 +518,300 @@ impl Game {
         self.grabbed_piece = None;
         self.selected_piece = None;
         self.premove = None;
+        self.board_flipped = false;
+        self.move_entry = MoveEntry::default();
+        self.move_list = MoveList::default();
+        self.annotations.clear();
         self.move_history = Vec::new();
-        self.time.turn_start = Instant::now();
+        self.time.start_turn();
         self.promoting_pawn = None;
+        self.scenes.close_overlays();
+        self.broadcast.clear();
+        self.pending_move = None;
     }
 
-    #[allow(unused_assignments)]
-    pub(crate) fn button_parsing(&mut self, allowed_group: Vec<ClickableGroup>) {
+    /// Performs the action of the hovered clickable, if it is in one of the given groups
+    pub(crate) fn button_parsing(&mut self, ctx: &mut Context, allowed_group: Vec<ClickableGroup>) {
+        let action = self
+            .menu
+            .clickables
+            .iter()
+            .find(|clickable| clickable.hovered && allowed_group.contains(&clickable.group))
+            .map(|clickable| clickable.action.clone());
+        if let Some(action) = action {
+            self.perform_action(ctx, action);
+        }
+    }
+
+    pub(crate) fn perform_action(&mut self, ctx: &mut Context, action: UiAction) {
         let read_state = STATE.get().read().unwrap().clone();
 
-        for i in 0..self.menu.clickables.len() {
-            if self.menu.clickables[i].hovered
-                && allowed_group.contains(&self.menu.clickables[i].group)
-            {
-                match &self.menu.clickables[i].id[..] {
-                    "create_room_button" => {
-                        self.connection.send("create_room", "");
-                    }
-                    "play_again" => {
-                        if STATE
-                            .get()
-                            .read()
-                            .unwrap()
-                            .event_validation
-                            .opponent_name
-                            .is_none()
-                        {
-                            return;
-                        }
-
-                        self.reset_game();
-                        self.playing_as_white = !self.playing_as_white;
-                        self.active_turn = self.playing_as_white;
-                        self.time.increment = Duration::from_secs(0);
-                        self.time.current_time_left = self.time.initial_time;
-                        self.time.opponent_time_left = self.time.initial_time;
-                        self.time.turn_start = Instant::now();
-                        self.update_available_moves();
-                        self.connection.send("play_again", "");
-                    }
-                    "goto_main_menu" => {
-                        STATE.get().write().unwrap().room_id = None;
-                        STATE.get().write().unwrap().event_validation.opponent_name = None;
-                        self.menu.visible = true;
-                        self.reset_game();
-                        self.time.time_set = false;
-                        self.connection.send("opponent_leave_lobby", "");
-                        self.connection.send("list_rooms", "");
-                    }
-                    "resign_game_button" => {
-                        let winner = if self.playing_as_white {
-                            PieceColor::Black
-                        } else {
-                            PieceColor::White
-                        };
-                        self.game_over(winner);
-                        self.connection.send("resign", "");
-                    }
-                    "submit_name_button" => {
-                        if read_state.name.len() > 0 {
-                            STATE.get().write().unwrap().entering_name = false;
-                            self.connection.send("set_name", &read_state.name);
-
-                            // Delete the button after it has been used
-                            let index = self
-                                .menu
-                                .clickables
-                                .iter()
-                                .position(|current| {
-                                    current.id == String::from("submit_name_button")
-                                })
-                                .unwrap();
-                            self.menu.clickables.remove(index);
-                        }
-                    }
-                    "minute_plus_1" => {
-                        self.modify_time(Duration::from_secs(60), true, false);
-                    }
-                    "minute_plus_5" => {
-                        self.modify_time(Duration::from_secs(5 * 60), true, false);
-                    }
-                    "minute_plus_10" => {
-                        self.modify_time(Duration::from_secs(10 * 60), true, false);
-                    }
-                    "minute_minus_1" => {
-                        self.modify_time(Duration::from_secs(60), false, false);
-                    }
-                    "minute_minus_5" => {
-                        self.modify_time(Duration::from_secs(5 * 60), false, false);
+        match action {
+            UiAction::CreateRoom => {
+                self.connection.send("create_room", "");
+            }
+            UiAction::OpenDirectConnection => {
+                self.scenes.push(Scene::DirectConnection);
+                STATE.get().write().unwrap().peer_status = String::from("");
+            }
+            UiAction::CloseDirectConnection => {
+                self.scenes.pop();
+            }
+            UiAction::OpenSettings => self.open_settings(),
+            UiAction::CloseSettings => self.close_settings(),
+            UiAction::CycleTheme => self.cycle_theme(ctx),
+            UiAction::CycleAnimationSpeed => self.cycle_animation_speed(),
+            UiAction::CycleVolume => self.cycle_volume(),
+            UiAction::ToggleMute => self.toggle_mute(),
+            UiAction::CycleEditedSound => self.cycle_edited_sound(),
+            UiAction::CycleSoundVolume => self.cycle_sound_volume(),
+            UiAction::ToggleAutoQueen => self.toggle_auto_queen(),
+            UiAction::CycleDefaultTimeControl => self.cycle_default_time_control(),
+            UiAction::CycleLanguage => self.cycle_language(),
+            UiAction::HostPeer => {
+                // The port can be typed on its own or as the end of an address
+                let port = read_state
+                    .peer_address
+                    .rsplit(':')
+                    .next()
+                    .and_then(|port| port.parse::<u16>().ok())
+                    .unwrap_or(DEFAULT_PEER_PORT);
+
+                match self.connection.host_peer(port) {
+                    Ok(_) => {
+                        let state = &mut self.connection.state;
+                        state.room_id = Some(format!("port {}", port));
+                        state.event_validation.create_room = true;
                     }
-                    "minute_minus_10" => {
-                        self.modify_time(Duration::from_secs(10 * 60), false, false);
+                    Err(e) => {
+                        STATE.get().write().unwrap().peer_status =
+                            tr_args("direct.host_failed", &[("error", e.to_string())])
                     }
+                }
+            }
+            UiAction::JoinPeer => {
+                let mut address = read_state.peer_address.clone();
+                if !address.contains(':') {
+                    address.push_str(&format!(":{}", DEFAULT_PEER_PORT));
+                }
+                self.join_peer_game(address);
+            }
+            UiAction::PlayAgain => {
+                // Spectators follow the players into the next game
+                if self.spectating.is_some() {
+                    return;
+                }
+                if self
+                    .connection
+                    .state
+                    .event_validation
+                    .opponent_name
+                    .is_none()
+                {
+                    return;
+                }
 
-                    "second_plus_15" => {
-                        self.modify_time(Duration::from_secs(15), true, false);
-                    }
-                    "second_minus_15" => {
-                        self.modify_time(Duration::from_secs(15), false, false);
+                self.reset_game();
+                self.playing_as_white = !self.playing_as_white;
+                self.active_turn = self.playing_as_white;
+                self.time.increment = Duration::from_secs(0);
+                self.reset_clocks();
+                self.time.start_turn();
+                self.update_available_moves();
+                self.connection.send("play_again", "");
+                if self.is_admin {
+                    self.send_spectate_sync();
+                }
+            }
+            UiAction::GotoMainMenu => {
+                self.connection.state.room_id = None;
+                self.connection.state.event_validation.opponent_name = None;
+                self.scenes.reset(Scene::MainMenu);
+                self.reset_game();
+                if self.spectating.take().is_some() {
+                    self.playing_as_white = false;
+                    self.connection.send("spectator_leave", "");
+                } else {
+                    self.connection.send("opponent_leave_lobby", "");
+                }
+                self.connection.send("list_rooms", "");
+            }
+            UiAction::FlipBoard => self.flip_board(),
+            UiAction::Resign => {
+                let winner = if self.playing_as_white {
+                    PieceColor::Black
+                } else {
+                    PieceColor::White
+                };
+                self.game_over(winner);
+                self.connection.send("resign", "");
+            }
+            UiAction::SubmitName => {
+                if read_state.name.len() > 0 {
+                    self.scenes.reset(Scene::MainMenu);
+                    self.connection.send("set_name", &read_state.name);
+                    // The name is remembered for the next time the game is started
+                    if self.settings.name != read_state.name {
+                        self.settings.name = read_state.name.clone();
+                        self.save_settings();
                     }
+                    // Correspondence games saved on the server, possibly from another computer
+                    self.connection.send("list_correspondence", "");
 
-                    "increment_plus_1" => {
-                        self.modify_time(Duration::from_secs(1), true, true);
-                    }
-                    "increment_plus_5" => {
-                        self.modify_time(Duration::from_secs(5), true, true);
-                    }
-                    "increment_plus_10" => {
-                        self.modify_time(Duration::from_secs(10), true, true);
-                    }
-                    "increment_minus_1" => {
-                        self.modify_time(Duration::from_secs(1), false, true);
-                    }
-                    "increment_minus_5" => {
-                        self.modify_time(Duration::from_secs(5), false, true);
-                    }
-                    "increment_minus_10" => {
-                        self.modify_time(Duration::from_secs(10), false, true);
-                    }
-                    "finish_time_start_game" => {
-                        // Only admin has permission to make changes to the time
-                        if self.is_admin {
-                            self.time.time_set = true;
-                            self.time.current_time_left = self.time.initial_time;
-                            self.time.opponent_time_left = self.time.initial_time;
-
-                            if read_state.opponent_online {
-                                // If the client is already connected we send the data afterwards
-                                self.connection.send(
-                                    "set_clock_time",
-                                    &format!(
-                                        "{}:{}",
-                                        self.time.initial_time.as_secs(),
-                                        self.time.increment.as_secs()
-                                    )[..],
-                                );
-                            }
-                        }
-                    }
-                    id if self.menu.clickables[i].list_item => {
-                        if id.len() != 4 {
-                            println!("Wrong formatted id: {}", id);
-                        } else {
-                            println!("Join room: {}", id);
-                            STATE.get().write().unwrap().room_id = Some(String::from(id));
-                            self.connection.send("join_room", id);
-                        }
-                    }
-                    data => {
-                        println!("Unused button click {}", data);
+                    // Delete the button after it has been used
+                    self.menu
+                        .clickables
+                        .retain(|clickable| clickable.action != UiAction::SubmitName);
+                }
+            }
+            UiAction::ChangeTime { seconds, add } => {
+                self.modify_time(Duration::from_secs(seconds), add, false);
+            }
+            UiAction::ChangeIncrement { seconds, add } => {
+                self.modify_time(Duration::from_secs(seconds), add, true);
+            }
+            UiAction::CycleSpectatorDelay => {
+                if self.is_admin {
+                    // Cycle to the next available delay
+                    let current = SPECTATOR_DELAYS
+                        .iter()
+                        .position(|delay| *delay == self.broadcast.delay.as_secs())
+                        .unwrap_or(0);
+                    let next = SPECTATOR_DELAYS[(current + 1) % SPECTATOR_DELAYS.len()];
+                    self.broadcast.delay = Duration::from_secs(next);
+                    self.menu
+                        .set_text(&action, delay_label(self.broadcast.delay));
+                }
+            }
+            UiAction::SavePreset => {
+                self.save_custom_preset();
+            }
+            UiAction::StandardPreset(index) => {
+                self.select_standard_preset(index);
+            }
+            UiAction::CustomPreset(index) => {
+                self.select_custom_preset(index);
+            }
+            UiAction::CycleLowTimeThreshold => {
+                self.cycle_low_time_threshold();
+            }
+            UiAction::CycleCorrespondenceDays => {
+                self.cycle_correspondence_days();
+            }
+            UiAction::ToggleMyGames => {
+                self.menu.showing_my_games = !self.menu.showing_my_games;
+                let key = MY_GAMES_TEXTS[self.menu.showing_my_games as usize];
+                self.menu.set_text(&action, tr(key));
+                self.menu.list.scroll = 0.0;
+                self.refresh_menu_list();
+            }
+            UiAction::LeaveCorrespondence => {
+                // The game stays saved and can be continued from "My games"
+                self.connection.send("leave_correspondence", "");
+                self.connection.state.room_id = None;
+                self.connection.state.event_validation.opponent_name = None;
+                self.scenes.reset(Scene::MainMenu);
+                self.reset_game();
+                self.time.days_per_move = None;
+                self.connection.send("list_rooms", "");
+                self.refresh_menu_list();
+            }
+            UiAction::CycleEditedSide => {
+                self.cycle_edited_side();
+            }
+            UiAction::CycleBonus => {
+                self.cycle_bonus();
+            }
+            UiAction::CycleTimeStages => {
+                self.cycle_time_stages();
+            }
+            UiAction::SetPerspective(perspective) => {
+                self.set_perspective(perspective);
+            }
+            UiAction::StartGame => {
+                // Only admin has permission to make changes to the time
+                if self.is_admin {
+                    self.scenes.replace(Scene::InGame);
+                    self.reset_clocks();
+
+                    if self.connection.state.opponent_online {
+                        // If the client is already connected we send the data afterwards
+                        self.connection
+                            .send("set_clock_time", &self.time.control_string());
                     }
+                    // Shown next to the room in the lobby list
+                    let preset = self.time.preset.clone().unwrap_or_default();
+                    self.connection
+                        .send("set_room_preset", &escape_preset(&preset));
                 }
-                break;
+            }
+            UiAction::OpenCorrespondenceGame(room_id) => {
+                self.open_correspondence_game(room_id);
+            }
+            UiAction::JoinLanGame(address) => {
+                println!("Join LAN game: {}", address);
+                self.join_peer_game(address);
+            }
+            UiAction::SpectateRoom(room_id) => {
+                // Full rooms can only be watched
+                println!("Spectate room: {}", room_id);
+                self.connection.send("spectate_room", &room_id);
+                self.connection.state.room_id = Some(room_id);
+            }
+            UiAction::JoinRoom(room_id) => {
+                println!("Join room: {}", room_id);
+                self.connection.send("join_room", &room_id);
+                self.connection.state.room_id = Some(room_id);
             }
         }
     }
 
-    // Attempt to move a piece
-    pub(crate) fn attempt_move(&mut self, piece: Piece, piece_dest_index: usize) {
+    /// Fills the main menu list with either the open lobbies or the saved correspondence games
+    pub(crate) fn refresh_menu_list(&mut self) {
+        self.menu.clear_list_items_from_list();
+        if self.menu.showing_my_games {
+            self.menu
+                .generate_list_item_from_games(&self.correspondence.games);
+        } else {
+            let lan_games = STATE.get().read().unwrap().lan_games.clone();
+            self.menu
+                .generate_list_item_from_list(&self.connection.state.lobbies, &lan_games);
+        }
+        self.lobby_sync = self.current_lobby_sync();
+    }
+
+    /// Changes whenever the server lobbies or the games on the local network change
+    pub(crate) fn current_lobby_sync(&self) -> (i32, i32) {
+        (
+            self.connection.state.lobby_sync,
+            STATE.get().read().unwrap().lan_sync,
+        )
+    }
+
+    /// Connects directly to a hosted game, errors are shown on the direct connection screen
+    fn join_peer_game(&mut self, address: String) {
+        match self.connection.join_peer(&address) {
+            Ok(_) => {
+                let state = &mut self.connection.state;
+                state.room_id = Some(address);
+                state.event_validation.join_room = true;
+            }
+            Err(e) => {
+                self.scenes.push(Scene::DirectConnection);
+                STATE.get().write().unwrap().peer_status =
+                    tr_args("direct.join_failed", &[("error", e.to_string())]);
+            }
+        }
+    }
+
+    // Attempt to move a piece. A pawn reaching the last rank is promoted to `promotion` if it is given,
+    // otherwise the piece is chosen in the promotion menu unless auto-queen is on.
+    pub(crate) fn attempt_move(
+        &mut self,
+        piece: Piece,
+        piece_dest_index: usize,
+        promotion: Option<PieceType>,
+    ) {
         let valid_moves = piece::get_valid_move_indices(self, &piece, false);
         println!("Current turn: {}", self.active_turn);
         println!("Valid moves: {:?}", valid_moves);
@@ -645,6 +832,15 @@ impl Game {
                     captured_piece: None, // It is assigned an eventual captured piece when the promotion has been confirmed (mouse button down event)
                     move_type: Promotion(King(true)), // Default invalid value that is later changed when the player has selected which piece to promote into.
                 });
+                let auto_queen = if self.settings.auto_queen {
+                    Some(Queen)
+                } else {
+                    None
+                };
+                match promotion.or(auto_queen) {
+                    Some(piece_type) => promote(self, piece_type),
+                    None => self.scenes.push(Scene::Promotion),
+                }
                 return;
             }
 
@@ -663,11 +859,14 @@ impl Game {
                     piece_dest_index
                 );
                 self.premove = Some((piece, piece_dest_index));
+            } else if piece_dest_index != piece.index {
+                self.play_sound(SoundEvent::IllegalMove);
             }
         } else {
             println!("Move to index {} is NOT valid.", piece_dest_index);
             // // Reset position to source
             self.board[piece.index] = Some(piece);
+            self.play_sound(SoundEvent::IllegalMove);
         }
     }
 }
@@ -0,0 +1,216 @@
+use std::{collections::HashMap, fs, path::Path, sync::RwLock};
+
+use serde_json::Value;
+use state::Storage;
+
+use crate::game::Game;
+
+// All text shown to the player is looked up by key in the string table of the selected language.
+// The built-in languages are read from the resources, and users can add their own as json files in
+// the "languages" folder of the config directory. Keys missing from a language fall back to English,
+// so a language file doesn't have to be complete. Values can contain named arguments, e.g. "{name}".
+
+const DEFAULT_LANGUAGES: [&str; 2] = [
+    include_str!("../resources/languages/en.json"),
+    include_str!("../resources/languages/sv.json"),
+];
+const LANGUAGE_DIR: &str = "languages";
+
+static LANGUAGES: Storage<RwLock<Languages>> = Storage::new();
+
+pub(crate) struct Language {
+    pub(crate) code: String, // Saved in the settings, e.g. "en"
+    pub(crate) name: String, // The name of the language in the language itself
+    strings: HashMap<String, String>,
+}
+
+impl Language {
+    fn parse(data: &str) -> Result<Language, String> {
+        let value: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        let field = |key: &str| {
+            value[key]
+                .as_str()
+                .map(String::from)
+                .ok_or(format!("Language has no {}", key))
+        };
+        let strings = value["strings"]
+            .as_object()
+            .ok_or(String::from("Language has no strings"))?
+            .iter()
+            .map(|(key, text)| {
+                text.as_str()
+                    .map(|text| (key.clone(), text.to_string()))
+                    .ok_or(format!("Invalid text for {}", key))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Language {
+            code: field("code")?,
+            name: field("name")?,
+            strings,
+        })
+    }
+}
+
+/// The languages which can be selected, the first one being English
+pub(crate) struct Languages {
+    languages: Vec<Language>,
+    selected: usize,
+}
+
+impl Languages {
+    /// Loads the built-in languages followed by the user's languages, files which can't be read are skipped
+    fn load(config_dir: &Path) -> Languages {
+        let mut languages: Vec<Language> = DEFAULT_LANGUAGES
+            .iter()
+            .map(|data| Language::parse(data).expect("The default languages are invalid"))
+            .collect();
+
+        if let Ok(entries) = fs::read_dir(config_dir.join(LANGUAGE_DIR)) {
+            let mut paths: Vec<_> = entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "json")
+                })
+                .collect();
+            paths.sort();
+            for path in paths {
+                match fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| Language::parse(&data))
+                {
+                    // A user's language replaces a built-in language with the same code
+                    Ok(language) => match languages.iter().position(|l| l.code == language.code) {
+                        Some(index) => languages[index] = language,
+                        None => languages.push(language),
+                    },
+                    Err(e) => println!("Could not read language {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        Languages {
+            languages,
+            selected: 0,
+        }
+    }
+
+    fn current(&self) -> &Language {
+        &self.languages[self.selected]
+    }
+
+    fn lookup(&self, key: &str) -> Option<&String> {
+        self.current()
+            .strings
+            .get(key)
+            .or_else(|| self.languages[0].strings.get(key))
+    }
+}
+
+/// Reads the languages, called at startup before any text is created
+pub(crate) fn load_languages(config_dir: &Path) {
+    LANGUAGES.set(RwLock::new(Languages::load(config_dir)));
+}
+
+/// Selects the language with the given code, returns false if there is no such language
+pub(crate) fn select_language(code: &str) -> bool {
+    let mut languages = LANGUAGES.get().write().unwrap();
+    match languages.languages.iter().position(|l| l.code == code) {
+        Some(index) => {
+            languages.selected = index;
+            true
+        }
+        None => false,
+    }
+}
+
+/// The code and name of the selected language
+pub(crate) fn current_language() -> (String, String) {
+    let languages = LANGUAGES.get().read().unwrap();
+    let language = languages.current();
+    (language.code.clone(), language.name.clone())
+}
+
+/// The text of a key in the selected language. A key which no language has is shown as it is.
+pub(crate) fn tr(key: &str) -> String {
+    match LANGUAGES.try_get() {
+        Some(languages) => languages
+            .read()
+            .unwrap()
+            .lookup(key)
+            .cloned()
+            .unwrap_or_else(|| key.to_string()),
+        None => key.to_string(),
+    }
+}
+
+/// The text of a key with its named arguments filled in, e.g. ("name", "Isak") replaces "{name}"
+pub(crate) fn tr_args(key: &str, args: &[(&str, String)]) -> String {
+    fill_args(&tr(key), args)
+}
+
+/// Fills in every placeholder in one pass, so braces in an argument are left as they are.
+/// Placeholders without an argument are kept.
+fn fill_args(text: &str, args: &[(&str, String)]) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let value = placeholder.find('}').and_then(|end| {
+            let name = &placeholder[1..end];
+            args.iter()
+                .find(|(arg, _)| *arg == name)
+                .map(|(_, value)| (value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                filled.push_str(value);
+                rest = &placeholder[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+impl Game {
+    /// Switches to the next language and recreates the texts of the menus
+    pub(crate) fn cycle_language(&mut self) {
+        {
+            let mut languages = LANGUAGES.get().write().unwrap();
+            languages.selected = (languages.selected + 1) % languages.languages.len();
+        }
+        self.settings.language = current_language().0;
+        self.retranslate();
+        self.save_settings();
+    }
+
+    /// Creates the buttons again, so they get the texts of the selected language
+    fn retranslate(&mut self) {
+        self.menu.clickables.clear();
+        self.menu.create_clickables();
+        self.menu
+            .create_custom_preset_clickables(&self.presets.custom);
+        self.refresh_menu_list();
+        self.update_time_labels();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_are_filled_in_one_pass() {
+        let args = [("name", "{time}".to_string()), ("time", "3+2".to_string())];
+        assert_eq!(fill_args("{name} has {time}", &args), "{time} has 3+2");
+        assert_eq!(fill_args("{unknown} {time}", &args), "{unknown} 3+2");
+        assert_eq!(fill_args("{time", &args), "{time");
+    }
+}
@@ -7,15 +7,31 @@ use ggez::{
     graphics::{self, Rect},
     ContextBuilder,
 };
-use move_struct::Move;
-use networking::connection::{NetworkEventValidation, Room};
+use menu::clickable::UiAction;
+use networking::{
+    connection::{Networking, SocketIoTransport},
+    discovery::LanGame,
+    transport::Transport,
+};
+use settings::Settings;
 use state::Storage;
 
+mod animation;
+mod annotation;
+mod clock;
+mod correspondence;
 mod default_board_state;
 mod event_handler;
 mod game;
+mod localization;
+mod material;
+mod move_entry;
+mod move_list;
 mod move_struct;
+mod spectator;
+mod theme;
 mod time;
+mod time_presets;
 mod piece {
     pub mod piece;
     pub mod piece_movement;
@@ -24,28 +40,37 @@ mod piece {
 
 pub mod enter_name_screen;
 mod render_utilities;
+mod scene;
+mod settings;
+mod sound;
 mod networking {
     pub mod connection;
+    pub mod discovery;
     pub mod events;
+    pub mod move_sync;
+    pub mod peer;
+    pub mod transport;
 }
 mod menu {
     pub mod clickable;
     pub mod create_clickable_layout;
+    pub mod layout;
+    pub mod menu_direct_connection;
     pub mod menu_game_over;
     pub mod menu_state;
     pub mod menu_utilities;
+    pub mod widgets;
 }
 
+/// State shared with the networking threads. What is received on the game's connection
+/// is kept by the connection itself, see ConnectionState.
 #[derive(Debug, Clone)]
 pub(crate) struct State {
-    pub(crate) entering_name: bool,
     pub(crate) name: String,
-    pub(crate) lobbies: Vec<Room>,
-    pub(crate) lobby_sync: i32,
-    pub(crate) event_validation: NetworkEventValidation,
-    pub(crate) incoming_move: Option<Move>,
-    pub(crate) room_id: Option<String>,
-    pub(crate) opponent_online: bool,
+    pub(crate) peer_address: String,
+    pub(crate) peer_status: String,
+    pub(crate) lan_games: Vec<LanGame>,
+    pub(crate) lan_sync: i32, // Changes whenever lan_games changes
 }
 
 use std::ptr;
@@ -53,8 +78,11 @@ use winapi::um::wincon::GetConsoleWindow;
 use winapi::um::winuser::{ShowWindow, SW_HIDE};
 
 static STATE: Storage<RwLock<State>> = Storage::new();
+// The size the window opens with, the layout follows the window when it is resized
 const SCREEN_WIDTH: f32 = 1500.0;
 const SCREEN_HEIGHT: f32 = 900.0;
+const MIN_SCREEN_WIDTH: f32 = 1280.0;
+const MIN_SCREEN_HEIGHT: f32 = 860.0;
 
 fn main() {
 
@@ -68,25 +96,11 @@ fn main() {
     }
 
     let app_state = State {
-        entering_name: true,
         name: String::from(""),
-        incoming_move: None,
-        lobbies: Vec::new(),
-        lobby_sync: 0,
-        event_validation: NetworkEventValidation {
-            create_room: false,
-            join_room: false,
-            opponent_connect: false,
-            opponent_disconnect: false,
-            play_again: false,
-            set_color: None,
-            resign: false,
-            opponent_name: None,
-            time: None,
-            deselect_cursor: false,
-        },
-        room_id: None,
-        opponent_online: false,
+        peer_address: String::from(""),
+        peer_status: String::from(""),
+        lan_games: Vec::new(),
+        lan_sync: 0,
     };
     STATE.set(RwLock::new(app_state));
 
@@ -99,17 +113,29 @@ fn main() {
             .title("Fog of War Chess")
             .vsync(false);
 
-        let (mut ctx, mut event_loop) = ContextBuilder::new("Fog of war", "Isak & Hampus")
-            .window_mode(
-                conf::WindowMode::default()
-                    .dimensions(SCREEN_WIDTH, SCREEN_HEIGHT)
-                    .maximized(false)
-                    .resizable(false),
-            )
-            .window_setup(conf)
-            .add_resource_path(path)
-            .build()
-            .expect("contextbuilder fail");
+        let context_builder = |audio: bool| {
+            ContextBuilder::new("Fog of war", "Isak & Hampus")
+                .window_mode(
+                    conf::WindowMode::default()
+                        .dimensions(SCREEN_WIDTH, SCREEN_HEIGHT)
+                        .min_dimensions(MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT)
+                        .maximized(false)
+                        .resizable(true),
+                )
+                .window_setup(conf.clone())
+                .modules(conf::ModuleConf::default().audio(audio))
+                .add_resource_path(path.clone())
+        };
+        // Building the context fails without an audio device, the game is then played without sound
+        let (audio, (mut ctx, mut event_loop)) = match context_builder(true).build() {
+            Ok(context) => (true, context),
+            Err(_) => (
+                false,
+                context_builder(false)
+                    .build()
+                    .expect("contextbuilder fail"),
+            ),
+        };
         graphics::set_drawable_size(&mut ctx, SCREEN_WIDTH, SCREEN_HEIGHT)
             .expect("window drawable fail");
         graphics::set_screen_coordinates(
@@ -118,9 +144,22 @@ fn main() {
         )
         .expect("screen coord fail");
 
-        let mut game = Game::new(&mut ctx);
+        let settings = Settings::load(ggez::filesystem::user_config_dir(&ctx));
+        // The texts are created with the game, so the language is chosen first
+        localization::load_languages(ggez::filesystem::user_config_dir(&ctx));
+        localization::select_language(&settings.language);
+        let server = SocketIoTransport::connect(&settings.server_url)
+            .map(|socket| Box::new(socket) as Box<dyn Transport>);
+        let connection = Networking::new(server);
+        connection.start_discovery();
+        let mut game = Game::new(&mut ctx, connection, settings, audio);
+        game.apply_settings(&mut ctx);
 
         game.connection.send("list_rooms", "");
+        // A saved name skips the name screen
+        if !game.settings.name.is_empty() {
+            game.perform_action(&mut ctx, UiAction::SubmitName);
+        }
 
         // Run!
         match event::run(&mut ctx, &mut event_loop, &mut game) {
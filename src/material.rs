@@ -0,0 +1,112 @@
+use std::cmp::Reverse;
+
+use ggez::{
+    graphics::{self, spritebatch::SpriteBatch, DrawParam, Image, Rect},
+    nalgebra::{Point2, Vector2},
+    Context, GameResult,
+};
+
+use crate::{
+    game::{Game, LIGHT_COLOR},
+    move_entry::same_kind,
+    move_struct::MoveType,
+    piece::piece::{get_piece_rect, Piece, PieceColor, PieceType},
+    render_utilities::SPRITE_TILE_SIZE,
+};
+
+/// The usual material values, the king has none since capturing it ends the game
+fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::King(_) => 0,
+        PieceType::Queen => 9,
+        PieceType::Rook(_) => 5,
+        PieceType::Bishop | PieceType::Knight => 3,
+        PieceType::Pawn(_) => 1,
+    }
+}
+
+/// The pieces one side has captured, and how much material it is ahead
+pub(crate) struct Captures {
+    pub(crate) pieces: Vec<Piece>,
+    pub(crate) advantage: i32,
+}
+
+impl Game {
+    /// Rebuilds the captures of a side from the move history, up to the position being shown.
+    /// Every capture is known to both players, since a captured piece was always in its owner's vision.
+    pub(crate) fn captures(&self, color: PieceColor) -> Captures {
+        let moves = self.move_list.preview.unwrap_or(self.move_history.len());
+        let mut pieces = Vec::new();
+        let mut advantage = 0;
+        for move_ in &self.move_history[..moves] {
+            let sign = if move_.piece.color == color { 1 } else { -1 };
+            if let Some(captured) = move_.captured_piece {
+                advantage += sign * piece_value(captured.piece_type);
+                if move_.piece.color == color {
+                    pieces.push(captured);
+                }
+            }
+            // A promotion replaces a pawn with a more valuable piece
+            if let MoveType::Promotion(piece_type) = move_.move_type {
+                advantage += sign * (piece_value(piece_type) - 1);
+            }
+        }
+
+        // The king comes first, then the most valuable pieces
+        pieces.sort_by_key(|piece| {
+            (
+                Reverse(matches!(piece.piece_type, PieceType::King(_))),
+                Reverse(piece_value(piece.piece_type)),
+            )
+        });
+        Captures { pieces, advantage }
+    }
+
+    /// Draws the pieces a side has captured in a row starting at the left of the area,
+    /// followed by its material advantage if it is ahead
+    pub(crate) fn draw_captures(
+        &mut self,
+        ctx: &mut Context,
+        color: PieceColor,
+        area: Rect,
+    ) -> GameResult<()> {
+        let captures = self.captures(color);
+        let size = area.h;
+        let mut batch = SpriteBatch::new(Image::new(ctx, &self.theme().pieces.image)?);
+        let mut x = area.x;
+        let mut previous: Option<PieceType> = None;
+        for piece in &captures.pieces {
+            // Pieces of the same type overlap, with a gap between the types
+            if let Some(previous) = previous {
+                x += if same_kind(previous, piece.piece_type) {
+                    size * 0.5
+                } else {
+                    size
+                };
+            }
+            previous = Some(piece.piece_type);
+            batch.add(
+                DrawParam::default()
+                    .src(get_piece_rect(&self.theme().pieces, piece))
+                    .dest(Point2::new(x, area.y))
+                    .scale(Vector2::new(size, size) / SPRITE_TILE_SIZE),
+            );
+        }
+        graphics::draw(ctx, &batch, (Point2::<f32>::new(0.0, 0.0),))?;
+
+        if captures.advantage > 0 {
+            if previous.is_some() {
+                x += size * 1.2;
+            }
+            self.menu.draw_text(
+                ctx,
+                format!("+{}", captures.advantage),
+                (x, area.y),
+                (area.x + area.w - x, size),
+                graphics::Color::from(LIGHT_COLOR),
+                graphics::Align::Left,
+            );
+        }
+        Ok(())
+    }
+}
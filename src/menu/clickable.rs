@@ -1,6 +1,8 @@
 use ggez::graphics;
 
-#[derive(PartialEq, Debug)]
+use crate::spectator::Perspective;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub(crate) enum ClickableGroup {
     EnterName = 0,
     MainMenu = 1,
@@ -8,6 +10,61 @@ pub(crate) enum ClickableGroup {
     InGame = 3,
     GameOverMenu = 4,
     TimeSelection = 5,
+    Spectating = 6,
+    DirectConnection = 7,
+    Correspondence = 8,
+    Board = 9, // Shown with the board to both players and spectators
+    Settings = 10,
+}
+
+/// What happens when a clickable is clicked, handled in `Game::perform_action`
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum UiAction {
+    // Name and main menu
+    SubmitName,
+    CreateRoom,
+    OpenDirectConnection,
+    CloseDirectConnection,
+    HostPeer,
+    JoinPeer,
+    ToggleMyGames,
+    OpenSettings,
+    // Main menu list entries
+    JoinRoom(String),
+    SpectateRoom(String),
+    JoinLanGame(String),            // Address of the host
+    OpenCorrespondenceGame(String), // Room id of the saved game
+    // In game
+    Resign,
+    LeaveCorrespondence,
+    SetPerspective(Perspective),
+    FlipBoard,
+    PlayAgain,
+    GotoMainMenu,
+    // Clock screen
+    ChangeTime { seconds: u64, add: bool },
+    ChangeIncrement { seconds: u64, add: bool },
+    StandardPreset(usize),
+    CustomPreset(usize),
+    SavePreset,
+    CycleTimeStages,
+    CycleEditedSide,
+    CycleBonus,
+    CycleSpectatorDelay,
+    CycleCorrespondenceDays,
+    CycleLowTimeThreshold,
+    StartGame,
+    // Settings screen
+    CycleTheme,
+    CycleAnimationSpeed,
+    CycleVolume,
+    ToggleMute,
+    CycleEditedSound,
+    CycleSoundVolume,
+    ToggleAutoQueen,
+    CycleDefaultTimeControl,
+    CycleLanguage,
+    CloseSettings,
 }
 
 pub(crate) struct Transform {
@@ -17,7 +74,7 @@ pub(crate) struct Transform {
     pub(crate) height: i32,
 }
 pub(crate) struct Clickable {
-    pub(crate) id: String,
+    pub(crate) action: UiAction,
     pub(crate) transform: Transform,
     pub(crate) color: graphics::Color,
     pub(crate) text: String,
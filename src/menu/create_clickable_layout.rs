@@ -1,364 +1,381 @@
+use std::time::Duration;
+
 use ggez::graphics::Color;
 
 use crate::{
-    event_handler::BOARD_WIDTH,
-    game::{ERROR_COLOR, LIGHT_COLOR},
-    time::{
-        TIME_BACKDROP_Y_OFFSET, TIME_TEXT_HEIGHT, TIME_TEXT_PADDING, TIME_TEXT_WIDTH,
-        TIME_TEXT_Y_POSITION,
-    },
-    SCREEN_HEIGHT, SCREEN_WIDTH,
+    correspondence::days_label,
+    game::ERROR_COLOR,
+    localization::tr,
+    spectator::{delay_label, Perspective},
+    time::{low_time_label, stages_label, Bonus, ClockSide},
+    time_presets::{standard_preset_name, TimePreset, STANDARD_PRESETS},
 };
 
 use super::{
-    clickable::{Clickable, ClickableGroup, Transform},
+    clickable::{Clickable, ClickableGroup, Transform, UiAction},
     menu_state::Menu,
 };
 
+/// The text keys of the button switching the main menu list, when it shows lobbies and saved games
+pub(crate) const MY_GAMES_TEXTS: [&str; 2] = ["menu.my_games", "menu.open_lobbies"];
+
+/// The layout element a button is placed in. List items and presets are placed in cells instead.
+fn element_name(action: &UiAction) -> Option<String> {
+    let name = match action {
+        UiAction::SubmitName => "submit_name",
+        UiAction::CreateRoom => "create_room",
+        UiAction::OpenDirectConnection => "direct_connection",
+        UiAction::ToggleMyGames => "my_games",
+        UiAction::OpenSettings => "open_settings",
+        UiAction::HostPeer => "host_peer",
+        UiAction::JoinPeer => "join_peer",
+        UiAction::CloseDirectConnection => "close_direct_connection",
+        UiAction::Resign => "resign",
+        UiAction::FlipBoard => "flip_board",
+        UiAction::LeaveCorrespondence => "continue_later",
+        UiAction::SetPerspective(Perspective::White) => "white_view",
+        UiAction::SetPerspective(Perspective::Black) => "black_view",
+        UiAction::SetPerspective(Perspective::Full) => "full_board_view",
+        UiAction::PlayAgain => "play_again",
+        UiAction::GotoMainMenu => "leave_game",
+        UiAction::ChangeTime { seconds, add } => {
+            let change = if *add { "add" } else { "remove" };
+            return Some(if seconds % 60 == 0 {
+                format!("{}_minutes_{}", change, seconds / 60)
+            } else {
+                format!("{}_seconds_{}", change, seconds)
+            });
+        }
+        UiAction::ChangeIncrement { seconds, add } => {
+            let change = if *add { "add" } else { "remove" };
+            return Some(format!("{}_increment_{}", change, seconds));
+        }
+        UiAction::CycleTimeStages => "time_stages",
+        UiAction::CycleEditedSide => "edited_side",
+        UiAction::CycleBonus => "bonus",
+        UiAction::CycleSpectatorDelay => "spectator_delay",
+        UiAction::CycleCorrespondenceDays => "correspondence_days",
+        UiAction::CycleLowTimeThreshold => "low_time_threshold",
+        UiAction::StartGame => "start_game",
+        UiAction::CycleTheme => "setting_theme",
+        UiAction::CycleAnimationSpeed => "setting_animation_speed",
+        UiAction::CycleVolume => "setting_volume",
+        UiAction::ToggleMute => "setting_mute",
+        UiAction::CycleEditedSound => "setting_sound",
+        UiAction::CycleSoundVolume => "setting_sound_volume",
+        UiAction::ToggleAutoQueen => "setting_auto_queen",
+        UiAction::CycleDefaultTimeControl => "setting_time_control",
+        UiAction::CycleLanguage => "setting_language",
+        UiAction::CloseSettings => "close_settings",
+        UiAction::JoinRoom(_)
+        | UiAction::SpectateRoom(_)
+        | UiAction::JoinLanGame(_)
+        | UiAction::OpenCorrespondenceGame(_)
+        | UiAction::StandardPreset(_)
+        | UiAction::CustomPreset(_)
+        | UiAction::SavePreset => return None,
+    };
+    Some(name.to_string())
+}
+
+/// The number of standard presets in each category, in order
+fn preset_categories() -> Vec<usize> {
+    let mut categories: Vec<usize> = Vec::new();
+    for (i, (category, _, _)) in STANDARD_PRESETS.iter().enumerate() {
+        match categories.last_mut() {
+            Some(count) if STANDARD_PRESETS[i - 1].0 == *category => *count += 1,
+            _ => categories.push(1),
+        }
+    }
+    categories
+}
+
 impl Menu {
+    /// Where the button performing an action is placed
+    pub(crate) fn place(&self, action: &UiAction) -> Transform {
+        let name = element_name(action)
+            .unwrap_or_else(|| panic!("{:?} is not placed by name in the layout", action));
+        self.layout.transform(&name)
+    }
+
+    /// Moves every clickable to where the layout puts it, after the screen size has changed
+    pub(crate) fn relayout(&mut self) {
+        self.list.transform = self.layout.transform("list");
+
+        let standard_presets = self.layout.cells("standard_presets", &preset_categories());
+        let custom_presets = self.layout.cells(
+            "custom_presets",
+            &[self
+                .clickables
+                .iter()
+                .filter(|clickable| {
+                    matches!(
+                        clickable.action,
+                        UiAction::CustomPreset(_) | UiAction::SavePreset
+                    )
+                })
+                .count()],
+        );
+
+        let list_items = self
+            .clickables
+            .iter()
+            .filter(|clickable| clickable.list_item)
+            .count();
+        let list = self.layout.cells("list", &[list_items]);
+
+        let mut list_index = 0;
+        for clickable in self.clickables.iter_mut() {
+            let rect = match &clickable.action {
+                UiAction::StandardPreset(i) => standard_presets[*i],
+                UiAction::SavePreset => custom_presets[0],
+                UiAction::CustomPreset(i) => custom_presets[i + 1],
+                // List items are placed after each other, in the order they were added
+                _ if clickable.list_item => {
+                    list_index += 1;
+                    list[list_index - 1]
+                }
+                action => match element_name(action) {
+                    Some(name) => self.layout.rect(&name),
+                    None => continue,
+                },
+            };
+            clickable.transform = Transform::from(rect);
+        }
+    }
+
+    /// Creates the buttons of every screen, placed where the layout puts them, with the texts of the selected language
     pub(crate) fn create_clickables(&mut self) {
         // Main menu buttons ###########################################
-        self.clickables.push(Clickable {
-            id: String::from("create_room_button"),
-            transform: Transform {
-                x: SCREEN_WIDTH as i32 / 4 - 500 / 2,
-                y: SCREEN_HEIGHT as i32 / 2 - 200 / 2,
-                width: 500,
-                height: 200,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            text: String::from("Create room"),
-            list_item: false,
-            group: ClickableGroup::MainMenu,
-        });
+        self.push_buttons(
+            &[
+                (UiAction::CreateRoom, "menu.create_room"),
+                (UiAction::OpenDirectConnection, "menu.direct_connection"),
+                (UiAction::OpenSettings, "menu.settings"),
+            ],
+            ClickableGroup::MainMenu,
+        );
 
-        let board_right_edge = SCREEN_WIDTH / 2.0 + (BOARD_WIDTH / 2) as f32;
+        // Switches the list between open lobbies and saved correspondence games
+        self.clickables.push(Clickable::toggle(
+            UiAction::ToggleMyGames,
+            self.place(&UiAction::ToggleMyGames),
+            MY_GAMES_TEXTS.map(tr),
+            self.showing_my_games,
+            ClickableGroup::MainMenu,
+        ));
+
+        // Direct connection buttons ####################################
+        self.push_buttons(
+            &[
+                (UiAction::HostPeer, "direct.host"),
+                (UiAction::JoinPeer, "direct.join"),
+                (UiAction::CloseDirectConnection, "common.back"),
+            ],
+            ClickableGroup::DirectConnection,
+        );
 
         // In game buttons ##############################################
         // Resign button for in game
-        self.clickables.push(Clickable {
-            id: String::from("resign_game_button"),
-            transform: Transform {
-                x: (board_right_edge + (SCREEN_WIDTH - board_right_edge) / 2.0 - 125.0 / 2.0)
-                    as i32,
-                y: (SCREEN_HEIGHT / 2.0 - 25.0) as i32,
-                width: 125,
-                height: 50,
-            },
-            color: Color::from(ERROR_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("Resign"),
-            group: ClickableGroup::InGame,
-        });
+        self.clickables.push(
+            Clickable::button(
+                UiAction::Resign,
+                self.place(&UiAction::Resign),
+                tr("game.resign"),
+                ClickableGroup::InGame,
+            )
+            .with_color(Color::from(ERROR_COLOR)),
+        );
+
+        // Leaves a correspondence game without resigning, it can be continued later
+        self.push_buttons(
+            &[(UiAction::LeaveCorrespondence, "game.continue_later")],
+            ClickableGroup::Correspondence,
+        );
+
+        // Turns the board around, for players and spectators alike
+        self.push_buttons(
+            &[(UiAction::FlipBoard, "game.flip_board")],
+            ClickableGroup::Board,
+        );
+
+        // Spectator buttons ############################################
+        // Lets spectators choose which side's fog of war they are watching
+        self.push_buttons(
+            &[
+                (
+                    UiAction::SetPerspective(Perspective::White),
+                    "game.white_view",
+                ),
+                (
+                    UiAction::SetPerspective(Perspective::Black),
+                    "game.black_view",
+                ),
+                (
+                    UiAction::SetPerspective(Perspective::Full),
+                    "game.full_board",
+                ),
+            ],
+            ClickableGroup::Spectating,
+        );
+
+        // Settings buttons ############################################
+        // The texts show the current values, they are set when the settings are loaded
+        for action in [
+            UiAction::CycleTheme,
+            UiAction::CycleAnimationSpeed,
+            UiAction::CycleVolume,
+            UiAction::ToggleMute,
+            UiAction::CycleEditedSound,
+            UiAction::CycleSoundVolume,
+            UiAction::ToggleAutoQueen,
+            UiAction::CycleDefaultTimeControl,
+            UiAction::CycleLanguage,
+        ] {
+            self.clickables.push(Clickable::button(
+                action.clone(),
+                self.place(&action),
+                String::new(),
+                ClickableGroup::Settings,
+            ));
+        }
+        self.push_buttons(
+            &[(UiAction::CloseSettings, "common.back")],
+            ClickableGroup::Settings,
+        );
 
         // Name screen button ###########################################
-        // Submit name button
-        self.clickables.push(Clickable {
-            id: String::from("submit_name_button"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0 - 150.0) as i32,
-                y: (SCREEN_HEIGHT * 3.0 / 4.0 - 125.0 / 2.0) as i32,
-                width: 300,
-                height: 125,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("Submit name"),
-            group: ClickableGroup::EnterName,
-        });
+        self.push_buttons(
+            &[(UiAction::SubmitName, "name.submit")],
+            ClickableGroup::EnterName,
+        );
 
         // Set time buttons ##############################################
+        let mut time_buttons = Vec::new();
+        for seconds in [60, 5 * 60, 10 * 60, 15].iter() {
+            let text = if seconds % 60 == 0 {
+                seconds / 60
+            } else {
+                *seconds
+            };
+            time_buttons.push((
+                UiAction::ChangeTime {
+                    seconds: *seconds,
+                    add: true,
+                },
+                format!("+{}", text),
+            ));
+            time_buttons.push((
+                UiAction::ChangeTime {
+                    seconds: *seconds,
+                    add: false,
+                },
+                format!("-{}", text),
+            ));
+        }
+        for seconds in [1, 5, 10].iter() {
+            time_buttons.push((
+                UiAction::ChangeIncrement {
+                    seconds: *seconds,
+                    add: true,
+                },
+                format!("+{}", seconds),
+            ));
+            time_buttons.push((
+                UiAction::ChangeIncrement {
+                    seconds: *seconds,
+                    add: false,
+                },
+                format!("-{}", seconds),
+            ));
+        }
 
-        let button_width = 80.0;
-        let button_height = 40.0;
-        let button_padding = 20.0;
+        // Preset grid, one group of buttons per category below the title
+        for (i, rect) in self
+            .layout
+            .cells("standard_presets", &preset_categories())
+            .into_iter()
+            .enumerate()
+        {
+            self.clickables.push(Clickable::button(
+                UiAction::StandardPreset(i),
+                Transform::from(rect),
+                standard_preset_name(i),
+                ClickableGroup::TimeSelection,
+            ));
+        }
 
-        // ### MINUTES ###
-        self.clickables.push(Clickable {
-            id: String::from("minute_plus_1"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    - TIME_TEXT_WIDTH / 2.0
-                    - TIME_TEXT_PADDING
-                    - TIME_TEXT_WIDTH / 2.0
-                    - button_width
-                    - button_padding
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET - button_height) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("+1"),
-            group: ClickableGroup::TimeSelection,
-        });
-        self.clickables.push(Clickable {
-            id: String::from("minute_plus_5"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    - TIME_TEXT_WIDTH / 2.0
-                    - TIME_TEXT_PADDING
-                    - TIME_TEXT_WIDTH / 2.0
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET - button_height) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("+5"),
-            group: ClickableGroup::TimeSelection,
-        });
-        self.clickables.push(Clickable {
-            id: String::from("minute_plus_10"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    - TIME_TEXT_WIDTH / 2.0
-                    - TIME_TEXT_PADDING
-                    - TIME_TEXT_WIDTH / 2.0
-                    + button_width
-                    + button_padding
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET - button_height) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("+10"),
-            group: ClickableGroup::TimeSelection,
-        });
+        // Time control type buttons, below the minutes and increment columns
+        time_buttons.push((UiAction::CycleTimeStages, stages_label(&[])));
+        time_buttons.push((UiAction::CycleEditedSide, ClockSide::Both.label()));
+        time_buttons.push((UiAction::CycleBonus, Bonus::Increment.label()));
+        // Spectator broadcast delay button
+        time_buttons.push((
+            UiAction::CycleSpectatorDelay,
+            delay_label(Duration::from_secs(0)),
+        ));
+        // Correspondence button, switches between a live game and days per move
+        time_buttons.push((UiAction::CycleCorrespondenceDays, days_label(None)));
+        // Low time warning button, personal so it can be changed by both players
+        time_buttons.push((
+            UiAction::CycleLowTimeThreshold,
+            low_time_label(Duration::from_secs(20)),
+        ));
+        // Start game button
+        time_buttons.push((UiAction::StartGame, tr("clock.enter_game")));
 
-        self.clickables.push(Clickable {
-            id: String::from("minute_minus_1"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    - TIME_TEXT_WIDTH / 2.0
-                    - TIME_TEXT_PADDING
-                    - TIME_TEXT_WIDTH / 2.0
-                    - button_width
-                    - button_padding
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION + TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("-1"),
-            group: ClickableGroup::TimeSelection,
-        });
-        self.clickables.push(Clickable {
-            id: String::from("minute_minus_5"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    - TIME_TEXT_WIDTH / 2.0
-                    - TIME_TEXT_PADDING
-                    - TIME_TEXT_WIDTH / 2.0
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION + TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("-5"),
-            group: ClickableGroup::TimeSelection,
-        });
-        self.clickables.push(Clickable {
-            id: String::from("minute_minus_10"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    - TIME_TEXT_WIDTH / 2.0
-                    - TIME_TEXT_PADDING
-                    - TIME_TEXT_WIDTH / 2.0
-                    + button_width
-                    + button_padding
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION + TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("-10"),
-            group: ClickableGroup::TimeSelection,
-        });
+        for (action, text) in time_buttons {
+            self.clickables.push(Clickable::button(
+                action.clone(),
+                self.place(&action),
+                text,
+                ClickableGroup::TimeSelection,
+            ));
+        }
+    }
 
-        // ### Seconds ###
-        self.clickables.push(Clickable {
-            id: String::from("second_plus_15"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0 - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET - button_height) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("+15"),
-            group: ClickableGroup::TimeSelection,
-        });
+    // The buttons are given the keys of their texts
+    fn push_buttons(&mut self, buttons: &[(UiAction, &str)], group: ClickableGroup) {
+        for (action, key) in buttons.iter() {
+            self.clickables.push(Clickable::button(
+                action.clone(),
+                self.place(action),
+                tr(key),
+                group,
+            ));
+        }
+    }
 
-        self.clickables.push(Clickable {
-            id: String::from("second_minus_15"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0 - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION + TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("-15"),
-            group: ClickableGroup::TimeSelection,
+    /// (Re)creates the save button and the buttons of the custom presets on the clock screen
+    pub(crate) fn create_custom_preset_clickables(&mut self, presets: &[TimePreset]) {
+        self.clickables.retain(|clickable| {
+            !matches!(
+                clickable.action,
+                UiAction::CustomPreset(_) | UiAction::SavePreset
+            )
         });
 
-        // ### Increment ###
-        self.clickables.push(Clickable {
-            id: String::from("increment_plus_1"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    + TIME_TEXT_WIDTH / 2.0
-                    + TIME_TEXT_PADDING
-                    + TIME_TEXT_WIDTH / 2.0
-                    - button_width
-                    - button_padding
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET - button_height) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("+1"),
-            group: ClickableGroup::TimeSelection,
-        });
-        self.clickables.push(Clickable {
-            id: String::from("increment_plus_5"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    + TIME_TEXT_WIDTH / 2.0
-                    + TIME_TEXT_PADDING
-                    + TIME_TEXT_WIDTH / 2.0
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET - button_height) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("+5"),
-            group: ClickableGroup::TimeSelection,
-        });
-        self.clickables.push(Clickable {
-            id: String::from("increment_plus_10"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    + TIME_TEXT_WIDTH / 2.0
-                    + TIME_TEXT_PADDING
-                    + TIME_TEXT_WIDTH / 2.0
-                    + button_width
-                    + button_padding
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET - button_height) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("+10"),
-            group: ClickableGroup::TimeSelection,
-        });
+        let mut cells = self
+            .layout
+            .cells("custom_presets", &[presets.len() + 1])
+            .into_iter();
 
-        self.clickables.push(Clickable {
-            id: String::from("increment_minus_1"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    + TIME_TEXT_WIDTH / 2.0
-                    + TIME_TEXT_PADDING
-                    + TIME_TEXT_WIDTH / 2.0
-                    - button_width
-                    - button_padding
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION + TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("-1"),
-            group: ClickableGroup::TimeSelection,
-        });
-        self.clickables.push(Clickable {
-            id: String::from("increment_minus_5"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    + TIME_TEXT_WIDTH / 2.0
-                    + TIME_TEXT_PADDING
-                    + TIME_TEXT_WIDTH / 2.0
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION + TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("-5"),
-            group: ClickableGroup::TimeSelection,
-        });
-        self.clickables.push(Clickable {
-            id: String::from("increment_minus_10"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0
-                    + TIME_TEXT_WIDTH / 2.0
-                    + TIME_TEXT_PADDING
-                    + TIME_TEXT_WIDTH / 2.0
-                    + button_width
-                    + button_padding
-                    - button_width / 2.0) as i32,
-                y: (TIME_TEXT_Y_POSITION + TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET) as i32,
-                width: button_width as i32,
-                height: button_height as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("-10"),
-            group: ClickableGroup::TimeSelection,
-        });
+        if let Some(rect) = cells.next() {
+            self.clickables.push(Clickable::button(
+                UiAction::SavePreset,
+                Transform::from(rect),
+                tr("clock.save_preset"),
+                ClickableGroup::TimeSelection,
+            ));
+        }
 
-        // Start game button
-        self.clickables.push(Clickable {
-            id: String::from("finish_time_start_game"),
-            transform: Transform {
-                x: (SCREEN_WIDTH / 2.0 - 300.0 / 2.0) as i32,
-                y: (SCREEN_HEIGHT * 0.8) as i32,
-                width: 300.0 as i32,
-                height: 120.0 as i32,
-            },
-            color: Color::from(LIGHT_COLOR),
-            hovered: false,
-            list_item: false,
-            text: String::from("Enter game"),
-            group: ClickableGroup::TimeSelection,
-        });
+        for (i, (preset, rect)) in presets.iter().zip(cells).enumerate() {
+            self.clickables.push(Clickable::button(
+                UiAction::CustomPreset(i),
+                Transform::from(rect),
+                preset.name.clone(),
+                ClickableGroup::TimeSelection,
+            ));
+        }
     }
 }
@@ -0,0 +1,449 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use ggez::graphics::Rect;
+use serde_json::Value;
+
+use super::clickable::Transform;
+
+// The positions of the menu elements are described by a tree of nodes, read from a data file.
+// Rows and columns place their children after each other, stacks place them on top of each other.
+// Every node can be given a name, which the menus use to look up where to draw things.
+
+const DEFAULT_LAYOUT: &str = include_str!("../../resources/layout.json");
+const LAYOUT_FILE: &str = "layout.json";
+
+/// A width, height or offset, either in pixels or relative to the parent
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Length {
+    Pixels(f32),
+    Percent(f32),
+    Fill, // What is left of the parent
+}
+
+impl Length {
+    fn parse(value: &Value) -> Result<Length, String> {
+        match value {
+            Value::Number(number) => number
+                .as_f64()
+                .map(|pixels| Length::Pixels(pixels as f32))
+                .ok_or(format!("Invalid length {}", value)),
+            Value::String(string) if string == "fill" => Ok(Length::Fill),
+            Value::String(string) if string.ends_with('%') => string[..string.len() - 1]
+                .trim()
+                .parse()
+                .map(Length::Percent)
+                .map_err(|_| format!("Invalid length {}", string)),
+            _ => Err(format!("Invalid length {}", value)),
+        }
+    }
+
+    fn resolve(&self, parent: f32, fill: f32) -> f32 {
+        match self {
+            Length::Pixels(pixels) => *pixels,
+            Length::Percent(percent) => parent * percent / 100.0,
+            Length::Fill => fill,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Direction {
+    Stack,
+    Row,
+    Column,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Align {
+    Start,
+    Center,
+    End,
+}
+
+impl Align {
+    /// Where something of the given size starts in the available space
+    fn offset(&self, space: f32, size: f32) -> f32 {
+        match self {
+            Align::Start => 0.0,
+            Align::Center => (space - size) / 2.0,
+            Align::End => space - size,
+        }
+    }
+}
+
+/// Where a node is placed in the space its parent gives it, horizontally and vertically
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Anchor {
+    x: Align,
+    y: Align,
+}
+
+impl Anchor {
+    fn parse(string: &str) -> Result<Anchor, String> {
+        let (y, x) = match string {
+            "top_left" => (Align::Start, Align::Start),
+            "top" => (Align::Start, Align::Center),
+            "top_right" => (Align::Start, Align::End),
+            "left" => (Align::Center, Align::Start),
+            "center" => (Align::Center, Align::Center),
+            "right" => (Align::Center, Align::End),
+            "bottom_left" => (Align::End, Align::Start),
+            "bottom" => (Align::End, Align::Center),
+            "bottom_right" => (Align::End, Align::End),
+            _ => return Err(format!("Invalid anchor {}", string)),
+        };
+        Ok(Anchor { x, y })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct LayoutNode {
+    name: Option<String>,
+    direction: Direction,
+    width: Length,
+    height: Length,
+    padding: f32,
+    spacing: f32,       // Between the children of a row or column
+    group_spacing: f32, // Between groups of cells, see Layout::cells
+    align: Align,       // Where the children of a row or column go when they don't fill it
+    anchor: Anchor,
+    offset: (Length, Length),
+    cell: Option<Box<LayoutNode>>, // Template for elements created by the game, e.g. list items
+    children: Vec<LayoutNode>,
+}
+
+impl LayoutNode {
+    fn parse(value: &Value) -> Result<LayoutNode, String> {
+        let object = value
+            .as_object()
+            .ok_or(format!("Expected a layout node, found {}", value))?;
+
+        let length = |key: &str| match object.get(key) {
+            Some(value) => Length::parse(value),
+            None => Ok(Length::Fill),
+        };
+        let number = |key: &str, default: f32| match object.get(key) {
+            Some(value) => value
+                .as_f64()
+                .map(|number| number as f32)
+                .ok_or(format!("Invalid {} {}", key, value)),
+            None => Ok(default),
+        };
+        let string = |key: &str, default: &'static str| match object.get(key) {
+            Some(value) => value
+                .as_str()
+                .map(|string| string.to_string())
+                .ok_or(format!("Invalid {} {}", key, value)),
+            None => Ok(default.to_string()),
+        };
+
+        let direction = match &string("layout", "stack")?[..] {
+            "stack" => Direction::Stack,
+            "row" => Direction::Row,
+            "column" => Direction::Column,
+            other => return Err(format!("Invalid layout {}", other)),
+        };
+        let align = match &string("align", "center")?[..] {
+            "start" => Align::Start,
+            "center" => Align::Center,
+            "end" => Align::End,
+            other => return Err(format!("Invalid align {}", other)),
+        };
+        let offset = match object.get("offset") {
+            Some(Value::Array(offset)) if offset.len() == 2 => {
+                (Length::parse(&offset[0])?, Length::parse(&offset[1])?)
+            }
+            Some(other) => return Err(format!("Invalid offset {}", other)),
+            None => (Length::Pixels(0.0), Length::Pixels(0.0)),
+        };
+        let spacing = number("spacing", 0.0)?;
+
+        Ok(LayoutNode {
+            name: match object.get("name") {
+                Some(name) => Some(
+                    name.as_str()
+                        .ok_or(format!("Invalid name {}", name))?
+                        .to_string(),
+                ),
+                None => None,
+            },
+            direction,
+            width: length("width")?,
+            height: length("height")?,
+            padding: number("padding", 0.0)?,
+            spacing,
+            group_spacing: number("group_spacing", spacing)?,
+            align,
+            anchor: Anchor::parse(&string("anchor", "center")?)?,
+            offset,
+            cell: match object.get("cell") {
+                Some(cell) => Some(Box::new(LayoutNode::parse(cell)?)),
+                None => None,
+            },
+            children: match object.get("children") {
+                Some(Value::Array(children)) => children
+                    .iter()
+                    .map(LayoutNode::parse)
+                    .collect::<Result<_, _>>()?,
+                Some(other) => return Err(format!("Invalid children {}", other)),
+                None => Vec::new(),
+            },
+        })
+    }
+
+    /// An empty node taking up space between other nodes
+    fn spacer(direction: Direction, size: f32) -> LayoutNode {
+        let mut spacer = LayoutNode::parse(&Value::Object(Default::default()))
+            .expect("An empty node is always valid");
+        match direction {
+            Direction::Column => spacer.height = Length::Pixels(size),
+            _ => spacer.width = Length::Pixels(size),
+        }
+        spacer
+    }
+
+    /// Places the node in the slot given by its parent, along with all its children
+    fn resolve(&self, slot: Rect, parent: (f32, f32), rects: &mut HashMap<String, Rect>) {
+        let width = self.width.resolve(parent.0, slot.w);
+        let height = self.height.resolve(parent.1, slot.h);
+        let rect = Rect::new(
+            slot.x + self.anchor.x.offset(slot.w, width) + self.offset.0.resolve(parent.0, 0.0),
+            slot.y + self.anchor.y.offset(slot.h, height) + self.offset.1.resolve(parent.1, 0.0),
+            width,
+            height,
+        );
+        if let Some(name) = &self.name {
+            rects.insert(name.clone(), rect);
+        }
+
+        self.resolve_children(&self.children, rect, rects);
+    }
+
+    fn inner(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.x + self.padding,
+            rect.y + self.padding,
+            rect.w - self.padding * 2.0,
+            rect.h - self.padding * 2.0,
+        )
+    }
+
+    fn resolve_children(
+        &self,
+        children: &[LayoutNode],
+        rect: Rect,
+        rects: &mut HashMap<String, Rect>,
+    ) {
+        let inner = self.inner(rect);
+        let size = (inner.w, inner.h);
+
+        if self.direction == Direction::Stack {
+            for child in children {
+                child.resolve(inner, size, rects);
+            }
+            return;
+        }
+
+        // Rows and columns share the space left by the fixed size children between the filling ones
+        let row = self.direction == Direction::Row;
+        let (space, main_length): (f32, fn(&LayoutNode) -> Length) = if row {
+            (inner.w, |node| node.width)
+        } else {
+            (inner.h, |node| node.height)
+        };
+        let fixed = children
+            .iter()
+            .filter(|child| main_length(child) != Length::Fill)
+            .map(|child| main_length(child).resolve(space, 0.0))
+            .sum::<f32>()
+            + self.spacing * children.len().saturating_sub(1) as f32;
+        let fills = children
+            .iter()
+            .filter(|child| main_length(child) == Length::Fill)
+            .count();
+        let fill = if fills > 0 {
+            f32::max(space - fixed, 0.0) / fills as f32
+        } else {
+            0.0
+        };
+
+        let mut position = self.align.offset(space, fixed + fill * fills as f32);
+        for child in children {
+            let length = main_length(child).resolve(space, fill);
+            let slot = if row {
+                Rect::new(inner.x + position, inner.y, length, inner.h)
+            } else {
+                Rect::new(inner.x, inner.y + position, inner.w, length)
+            };
+            child.resolve(slot, size, rects);
+            position += length + self.spacing;
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<&LayoutNode> {
+        if self.name.as_deref() == Some(name) {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(name))
+    }
+
+    fn names(&self, names: &mut Vec<String>) {
+        if let Some(name) = &self.name {
+            names.push(name.clone());
+        }
+        for child in &self.children {
+            child.names(names);
+        }
+    }
+}
+
+impl From<Rect> for Transform {
+    fn from(rect: Rect) -> Transform {
+        Transform {
+            x: rect.x as i32,
+            y: rect.y as i32,
+            width: rect.w as i32,
+            height: rect.h as i32,
+        }
+    }
+}
+
+/// The menu layout, resolved for the current screen size
+pub(crate) struct Layout {
+    root: LayoutNode,
+    screen: Rect,
+    rects: HashMap<String, Rect>,
+}
+
+impl Layout {
+    /// Loads the layout from the config directory if the user has one, otherwise the default layout is used
+    pub(crate) fn load(config_dir: &Path, width: f32, height: f32) -> Layout {
+        let default = Layout::parse(DEFAULT_LAYOUT).expect("The default layout is invalid");
+
+        let layout = match fs::read_to_string(config_dir.join(LAYOUT_FILE)) {
+            Ok(data) => match Layout::parse(&data) {
+                Ok(layout) => match layout.missing_element(&default) {
+                    Some(missing) => {
+                        println!("Layout file {}", missing);
+                        default
+                    }
+                    None => layout,
+                },
+                Err(e) => {
+                    println!("Could not read layout file: {}", e);
+                    default
+                }
+            },
+            Err(_) => default,
+        };
+
+        let mut layout = layout;
+        layout.resize(width, height);
+        layout
+    }
+
+    pub(crate) fn parse(data: &str) -> Result<Layout, String> {
+        let value: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        Ok(Layout {
+            root: LayoutNode::parse(&value)?,
+            screen: Rect::default(),
+            rects: HashMap::new(),
+        })
+    }
+
+    /// Places every element again for a new screen size
+    pub(crate) fn resize(&mut self, width: f32, height: f32) {
+        self.screen = Rect::new(0.0, 0.0, width, height);
+        self.rects.clear();
+        self.root
+            .resolve(self.screen, (width, height), &mut self.rects);
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.root.names(&mut names);
+        names
+    }
+
+    /// The menus need every element of the default layout, and a cell wherever the default has one
+    fn missing_element(&self, default: &Layout) -> Option<String> {
+        default.names().into_iter().find_map(|name| {
+            let needs_cell = default.root.find(&name)?.cell.is_some();
+            match self.root.find(&name) {
+                None => Some(format!("has no element named {}", name)),
+                Some(node) if needs_cell && node.cell.is_none() => {
+                    Some(format!("element {} has no cell", name))
+                }
+                Some(_) => None,
+            }
+        })
+    }
+
+    pub(crate) fn screen(&self) -> Rect {
+        self.screen
+    }
+
+    pub(crate) fn rect(&self, name: &str) -> Rect {
+        *self
+            .rects
+            .get(name)
+            .unwrap_or_else(|| panic!("No layout element named {}", name))
+    }
+
+    pub(crate) fn transform(&self, name: &str) -> Transform {
+        Transform::from(self.rect(name))
+    }
+
+    /// Places cells shaped like the cell of the named element after each other, in groups
+    /// of the given sizes. Groups are separated by the group spacing of the element.
+    pub(crate) fn cells(&self, name: &str, groups: &[usize]) -> Vec<Rect> {
+        let node = self
+            .root
+            .find(name)
+            .unwrap_or_else(|| panic!("No layout element named {}", name));
+        let cell = node
+            .cell
+            .as_ref()
+            .unwrap_or_else(|| panic!("Layout element {} has no cell", name));
+
+        let mut children = Vec::new();
+        let mut index = 0;
+        for (group, size) in groups.iter().enumerate() {
+            if group > 0 {
+                children.push(LayoutNode::spacer(
+                    node.direction,
+                    node.group_spacing - node.spacing * 2.0,
+                ));
+            }
+            for _ in 0..*size {
+                let mut child = (**cell).clone();
+                child.name = Some(index.to_string());
+                children.push(child);
+                index += 1;
+            }
+        }
+
+        let mut rects = HashMap::new();
+        node.resolve_children(&children, self.rect(name), &mut rects);
+        (0..index).map(|index| rects[&index.to_string()]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_without_a_required_cell_is_rejected() {
+        let default = Layout::parse(DEFAULT_LAYOUT).unwrap();
+        assert_eq!(default.missing_element(&default), None);
+
+        let mut layout = Layout::parse(DEFAULT_LAYOUT).unwrap();
+        fn remove_cells(node: &mut LayoutNode) {
+            node.cell = None;
+            node.children.iter_mut().for_each(remove_cells);
+        }
+        remove_cells(&mut layout.root);
+        assert!(layout.missing_element(&default).is_some());
+    }
+}
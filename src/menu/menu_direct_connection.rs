@@ -0,0 +1,53 @@
+use ggez::graphics;
+use ggez::Context;
+
+use crate::{
+    game::{ERROR_COLOR, LIGHT_COLOR},
+    localization::{tr, tr_args},
+    menu::{clickable::ClickableGroup, menu_state::Menu, widgets::ADDRESS_FIELD},
+    networking::peer::DEFAULT_PEER_PORT,
+    STATE,
+};
+
+impl Menu {
+    pub(crate) fn render_direct_connection(&mut self, ctx: &mut Context) {
+        // Draw screen title
+        let title = self.layout.rect("direct_title");
+        self.draw_text(
+            ctx,
+            tr("direct.title"),
+            (title.x, title.y),
+            (title.w, title.h),
+            graphics::Color::from(LIGHT_COLOR),
+            graphics::Align::Center,
+        );
+
+        let read_state = STATE.get().read().unwrap().clone();
+        let field = self.layout.rect("address_field");
+        ADDRESS_FIELD.draw(self, ctx, &read_state.peer_address, field);
+
+        // Draw instructions, or the reason the last attempt failed
+        let (status, color) = if read_state.peer_status.is_empty() {
+            (
+                tr_args(
+                    "direct.instructions",
+                    &[("port", DEFAULT_PEER_PORT.to_string())],
+                ),
+                LIGHT_COLOR,
+            )
+        } else {
+            (read_state.peer_status.clone(), ERROR_COLOR)
+        };
+        let status_area = self.layout.rect("peer_status");
+        self.draw_text(
+            ctx,
+            status,
+            (status_area.x, status_area.y),
+            (status_area.w, status_area.h),
+            graphics::Color::from(color),
+            graphics::Align::Center,
+        );
+
+        self.draw_clickables(ctx, vec![ClickableGroup::DirectConnection]);
+    }
+}
@@ -4,32 +4,23 @@ use ggez::{
     Context,
 };
 
-use crate::{
-    game::{BACKGROUND_COLOR, LIGHT_COLOR},
-    piece::piece::PieceColor,
-    SCREEN_HEIGHT, SCREEN_WIDTH,
-};
+use crate::{game::LIGHT_COLOR, localization::tr, piece::piece::PieceColor};
 
 use super::{clickable::ClickableGroup, menu_state::Menu};
 
-pub(crate) const GAME_OVER_START_X: f32 = SCREEN_WIDTH / 2.0 - SCREEN_WIDTH * 0.4 / 2.0;
-pub(crate) const GAME_OVER_START_Y: f32 = SCREEN_HEIGHT / 2.0 - SCREEN_HEIGHT * 0.7 / 2.0;
-pub(crate) const GAME_OVER_MENU_WIDTH: f32 = SCREEN_WIDTH * 0.4;
-pub(crate) const GAME_OVER_MENU_HEIGHT: f32 = SCREEN_HEIGHT * 0.7;
-
 impl Menu {
-    pub(crate) fn render_game_over(&mut self, ctx: &mut Context, winner: Option<PieceColor>) {
+    pub(crate) fn render_game_over(
+        &mut self,
+        ctx: &mut Context,
+        winner: Option<PieceColor>,
+        background: graphics::Color,
+    ) {
         // Draw list
         if let Ok(drawable) = graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::fill(),
-            graphics::Rect::new(
-                GAME_OVER_START_X,
-                GAME_OVER_START_Y,
-                GAME_OVER_MENU_WIDTH,
-                GAME_OVER_MENU_HEIGHT,
-            ),
-            graphics::Color::from(BACKGROUND_COLOR),
+            self.layout.rect("game_over"),
+            background,
         ) {
             graphics::draw(ctx, &drawable, graphics::DrawParam::default())
                 .expect("Could not draw list");
@@ -38,29 +29,23 @@ impl Menu {
         let mut text = Text::new("");
         match winner {
             Some(PieceColor::White) => {
-                text = Text::new("White won");
+                text = Text::new(tr("game_over.white_won"));
             }
             Some(PieceColor::Black) => {
-                text = Text::new("Black won");
+                text = Text::new(tr("game_over.black_won"));
             }
             None => {}
         }
 
-        let scale = 50.0;
-        text.set_font(self.font, graphics::Scale::uniform(scale));
-        text.set_bounds(
-            Point2::new(SCREEN_WIDTH * 0.4, 50.0),
-            graphics::Align::Center,
-        );
+        let title = self.layout.rect("game_over_title");
+        text.set_font(self.font, graphics::Scale::uniform(title.h));
+        text.set_bounds(Point2::new(title.w, title.h), graphics::Align::Center);
 
         graphics::draw(
             ctx,
             &text,
             graphics::DrawParam::default()
-                .dest(Point2::<f32>::new(
-                    SCREEN_WIDTH / 2.0 - GAME_OVER_MENU_WIDTH / 2.0,
-                    SCREEN_HEIGHT / 2.0 - GAME_OVER_MENU_HEIGHT / 2.0 + 50.0,
-                ))
+                .dest(Point2::<f32>::new(title.x, title.y))
                 .color(graphics::Color::from(LIGHT_COLOR)),
         )
         .expect("Error drawing clickable text");
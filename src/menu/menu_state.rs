@@ -1,12 +1,10 @@
 use super::{
     clickable::{Clickable, Transform},
+    layout::Layout,
     menu_utilities::is_within_boundary,
 };
 use crate::menu::clickable::ClickableGroup;
-use crate::{
-    game::{BACKGROUND_COLOR, LIGHT_COLOR},
-    SCREEN_HEIGHT, SCREEN_WIDTH,
-};
+use crate::{game::LIGHT_COLOR, localization::tr, SCREEN_HEIGHT, SCREEN_WIDTH};
 use ggez::{graphics::Drawable, nalgebra::Vector2};
 use ggez::{
     graphics::{self, Font, Text},
@@ -14,14 +12,6 @@ use ggez::{
     Context,
 };
 
-pub(crate) const LIST_WIDTH: f32 = SCREEN_WIDTH / 2.0 * 0.8;
-pub(crate) const LIST_HEIGHT: f32 = SCREEN_HEIGHT as f32 * 0.8;
-pub(crate) const LIST_START_X: f32 =
-    (((3.0 * SCREEN_WIDTH as f32 / 4.0) - (LIST_WIDTH / 2.0)) as i32) as f32;
-pub(crate) const LIST_START_Y: f32 = (SCREEN_HEIGHT as f32 / 2.0) - (LIST_HEIGHT / 2.0);
-pub(crate) const LIST_ITEM_WIDTH: f32 = LIST_WIDTH * 0.8;
-pub(crate) const LIST_ITEM_HEIGHT: f32 = 100.0;
-pub(crate) const LIST_ITEM_MARGIN: f32 = 20.0;
 pub(crate) const LIST_CHIN_HEIGHT: f32 = 50.0;
 
 pub(crate) struct List {
@@ -30,30 +20,32 @@ pub(crate) struct List {
     pub(crate) hovered: bool,
 }
 pub(crate) struct Menu {
-    pub(crate) visible: bool,
     pub(crate) clickables: Vec<Clickable>,
     pub(crate) list: List,
     last_iteration_hover: bool,
     pub(crate) font: Font,
+    pub(crate) showing_my_games: bool, // The list shows saved correspondence games instead of lobbies
+    pub(crate) layout: Layout,
 }
 
 impl Menu {
     pub(crate) fn new(ctx: &mut Context) -> Menu {
+        let layout = Layout::load(
+            ggez::filesystem::user_config_dir(ctx),
+            SCREEN_WIDTH,
+            SCREEN_HEIGHT,
+        );
         Menu {
-            visible: true,
             clickables: Vec::new(),
             last_iteration_hover: false,
             list: List {
-                transform: Transform {
-                    x: LIST_START_X as i32,
-                    y: LIST_START_Y as i32,
-                    width: LIST_WIDTH as i32,
-                    height: LIST_HEIGHT as i32,
-                },
+                transform: layout.transform("list"),
                 scroll: 0.0,
                 hovered: false,
             },
             font: Font::new(ctx, "/fonts/Roboto-Regular.ttf").expect("Error loading font"),
+            showing_my_games: false,
+            layout,
         }
     }
 
@@ -123,8 +115,8 @@ impl Menu {
         if y < 0.0 {
             match last_list_clickable {
                 Some(_) => {
-                    if self.list.scroll > Menu::max_scroll(self.list_elements(), LIST_ITEM_MARGIN) {
-                        self.list.scroll = Menu::max_scroll(self.list_elements(), LIST_ITEM_MARGIN);
+                    if self.list.scroll > self.max_scroll() {
+                        self.list.scroll = self.max_scroll();
                     }
                 }
                 None => {}
@@ -137,14 +129,17 @@ impl Menu {
         }
     }
 
-    pub(crate) fn render(&mut self, ctx: &mut Context) {
+    pub(crate) fn render(&mut self, ctx: &mut Context, background: graphics::Color) {
         if let Ok(sprite) = graphics::Image::new(ctx, "/logo.png") {
+            // The logo is scaled to the width of its layout element
+            let logo = self.layout.rect("logo");
+            let scale = logo.w / sprite.width() as f32;
             sprite
                 .draw(
                     ctx,
                     graphics::DrawParam::default()
-                        .dest(Point2::new(SCREEN_WIDTH / 4.0 - 1000.0 * 0.15 / 2.0, 100.0))
-                        .scale(Vector2::new(0.15, 0.15)),
+                        .dest(Point2::new(logo.x, logo.y))
+                        .scale(Vector2::new(scale, scale)),
                 )
                 .expect("COULD NOT DRAW IMAGE");
         } else {
@@ -173,20 +168,17 @@ impl Menu {
             vec![ClickableGroup::MainMenu, ClickableGroup::MainMenuList],
         );
 
+        let list = self.layout.rect("list");
+        let screen = self.layout.screen();
+
         // Draw scroll chin
         if self.list_elements() > 0.0 {
             match graphics::Mesh::new_rectangle(
                 ctx,
                 graphics::DrawMode::fill(),
                 graphics::Rect::new(
-                    LIST_START_X + LIST_WIDTH - 10.0,
-                    LIST_START_Y
-                        + LIST_HEIGHT
-                            * (self.list.scroll
-                                / Menu::max_scroll_adjusted(
-                                    self.list_elements(),
-                                    LIST_ITEM_MARGIN,
-                                )),
+                    list.x + list.w - 10.0,
+                    list.y + list.h * (self.list.scroll / self.max_scroll_adjusted()),
                     10.0,
                     LIST_CHIN_HEIGHT,
                 ),
@@ -204,13 +196,8 @@ impl Menu {
         let high_overlapper = graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::fill(),
-            graphics::Rect::new(
-                LIST_START_X,
-                0.0,
-                LIST_WIDTH,
-                (SCREEN_HEIGHT - LIST_HEIGHT) / 2.0,
-            ),
-            graphics::Color::from(BACKGROUND_COLOR),
+            graphics::Rect::new(list.x, screen.y, list.w, list.y - screen.y),
+            background,
         );
         match high_overlapper {
             Ok(overlapper) => {
@@ -223,13 +210,8 @@ impl Menu {
         match graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::fill(),
-            graphics::Rect::new(
-                LIST_START_X,
-                LIST_START_Y + LIST_HEIGHT,
-                LIST_WIDTH,
-                (SCREEN_HEIGHT - LIST_HEIGHT) / 2.0,
-            ),
-            graphics::Color::from(BACKGROUND_COLOR),
+            graphics::Rect::new(list.x, list.y + list.h, list.w, screen.h - list.y - list.h),
+            background,
         ) {
             Ok(overlapper) => {
                 graphics::draw(ctx, &overlapper, graphics::DrawParam::default())
@@ -238,24 +220,29 @@ impl Menu {
             Err(_) => {}
         }
 
+        let title = self.layout.rect("list_title");
         self.draw_text(
             ctx,
-            String::from("Open lobbies"),
-            (LIST_START_X, SCREEN_HEIGHT * 0.01),
-            (LIST_WIDTH, SCREEN_HEIGHT * 0.08),
+            if self.showing_my_games {
+                tr("menu.my_games")
+            } else {
+                tr("menu.open_lobbies")
+            },
+            (title.x, title.y),
+            (title.w, title.h),
             graphics::Color::from(LIGHT_COLOR),
             graphics::Align::Center,
         );
 
-        let mut text = Text::new("A game created by Isak Einberg & Hampus Hallkvist");
-        let scale = 20.0;
-        text.set_font(self.font, graphics::Scale::uniform(scale));
+        let credits = self.layout.rect("credits");
+        let mut text = Text::new(tr("menu.credits"));
+        text.set_font(self.font, graphics::Scale::uniform(credits.h));
 
         graphics::draw(
             ctx,
             &text,
             graphics::DrawParam::default()
-                .dest(Point2::<f32>::new(25.0, SCREEN_HEIGHT - 45.0))
+                .dest(Point2::<f32>::new(credits.x, credits.y))
                 .color(graphics::Color::from(LIGHT_COLOR)),
         )
         .expect("Error drawing clickable text");
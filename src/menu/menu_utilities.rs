@@ -5,17 +5,16 @@ use ggez::{
 };
 
 use crate::{
+    correspondence::CorrespondenceGame,
     game::{DARK_COLOR, LIGHT_COLOR},
-    networking::connection::Room,
-    SCREEN_HEIGHT, SCREEN_WIDTH,
+    localization::tr,
+    networking::{connection::Room, discovery::LanGame},
+    time::format_time_control,
 };
 
 use super::{
-    clickable::{Clickable, ClickableGroup, Transform},
-    menu_state::{
-        Menu, LIST_CHIN_HEIGHT, LIST_HEIGHT, LIST_ITEM_HEIGHT, LIST_ITEM_MARGIN, LIST_ITEM_WIDTH,
-        LIST_WIDTH,
-    },
+    clickable::{Clickable, ClickableGroup, Transform, UiAction},
+    menu_state::{Menu, LIST_CHIN_HEIGHT},
 };
 
 pub(crate) fn is_within_boundary(
@@ -48,24 +47,7 @@ pub(crate) fn reverse_scroll(scroll: f32) -> f32 {
     return scroll / 25.0;
 }
 
-/**
-    This function converts relative coordinates
-    (from the list point of view) to the actual
-    screen coorindates.
-*/
 impl Menu {
-    pub(crate) fn list_from_rel_to_real(x: f32, y: f32) -> (f32, f32) {
-        let real_x = x - LIST_WIDTH / 2.0 + 3.0 / 4.0 * SCREEN_WIDTH;
-        let real_y = y - LIST_HEIGHT / 2.0 + SCREEN_HEIGHT / 2.0;
-        return (real_x, real_y);
-    }
-
-    /* pub(crate) fn list_from_real_to_rel(x: f32, y: f32) -> (f32, f32) {
-        let rel_x = x + LIST_WIDTH / 2.0 - 3.0/4.0 * SCREEN_WIDTH;
-        let rel_y = y + LIST_HEIGHT / 2.0 - SCREEN_HEIGHT / 2.0;
-        return (rel_x, rel_y)
-    } */
-
     pub(crate) fn clear_clickable_hovers(&mut self) {
         for clickable in &mut self.clickables {
             clickable.hovered = false;
@@ -82,37 +64,78 @@ impl Menu {
         }
     }
 
-    pub(crate) fn generate_list_item_from_list(&mut self, elements: &Vec<Room>) {
-        let (x_pos, y_pos) =
-            Menu::list_from_rel_to_real(LIST_WIDTH / 2.0 - LIST_ITEM_WIDTH / 2.0, 0.0);
-
-        for i in 0..elements.len() {
-            self.clickables.push(Clickable {
-                id: elements[i].id.clone(),
-                transform: Transform {
-                    x: x_pos as i32,
-                    y: (y_pos + i as f32 * (LIST_ITEM_HEIGHT + LIST_ITEM_MARGIN)) as i32,
-                    height: LIST_ITEM_HEIGHT as i32,
-                    width: LIST_ITEM_WIDTH as i32,
-                },
-                hovered: false,
-                list_item: true,
-                color: graphics::Color::from(DARK_COLOR),
-                text: elements[i].id.clone(),
-                group: ClickableGroup::MainMenuList,
-            })
+    pub(crate) fn generate_list_item_from_list(
+        &mut self,
+        elements: &Vec<Room>,
+        lan_games: &[LanGame],
+    ) {
+        let cells = self
+            .layout
+            .cells("list", &[elements.len() + lan_games.len()]);
+        for (i, room) in elements.iter().enumerate() {
+            // Full rooms can be joined as a spectator
+            let full = room.members >= 2;
+            let text = match (&room.preset, full) {
+                (Some(preset), true) => format!("{} {} {}", room.id, preset, tr("list.spectate")),
+                (Some(preset), false) => format!("{} {}", room.id, preset),
+                (None, true) => format!("{} {}", room.id, tr("list.spectate")),
+                (None, false) => room.id.clone(),
+            };
+            let action = if full {
+                UiAction::SpectateRoom(room.id.clone())
+            } else {
+                UiAction::JoinRoom(room.id.clone())
+            };
+            self.clickables.push(Clickable::list_item(
+                action,
+                Transform::from(cells[i]),
+                text,
+            ));
+        }
+
+        // Games found on the local network are listed after the server lobbies
+        for (i, game) in lan_games.iter().enumerate() {
+            let text = format!(
+                "{}: {} {}",
+                tr("list.lan"),
+                game.host_name,
+                game.preset
+                    .clone()
+                    .unwrap_or_else(|| format_time_control(game.initial_time, game.increment))
+            );
+            self.clickables.push(Clickable::list_item(
+                UiAction::JoinLanGame(game.address.to_string()),
+                Transform::from(cells[i + elements.len()]),
+                text,
+            ));
         }
     }
 
-    pub(crate) fn max_scroll(element_count: f32, bottom_margin: f32) -> f32 {
-        let virtual_size = (LIST_ITEM_HEIGHT + bottom_margin) * element_count;
-        return reverse_scroll(virtual_size - LIST_HEIGHT);
+    pub(crate) fn generate_list_item_from_games(&mut self, games: &[CorrespondenceGame]) {
+        let cells = self.layout.cells("list", &[games.len()]);
+        for (i, game) in games.iter().enumerate() {
+            self.clickables.push(Clickable::list_item(
+                UiAction::OpenCorrespondenceGame(game.room_id.clone()),
+                Transform::from(cells[i]),
+                game.list_text(),
+            ));
+        }
     }
 
-    pub(crate) fn max_scroll_adjusted(element_count: f32, bottom_margin: f32) -> f32 {
-        let virtual_size =
-            (LIST_ITEM_HEIGHT + bottom_margin) * element_count + LIST_CHIN_HEIGHT / 2.0;
-        return reverse_scroll(virtual_size - LIST_HEIGHT);
+    /// The distance from the top of one list item to the next, including the margin between them
+    fn list_item_pitch(&self) -> f32 {
+        let cells = self.layout.cells("list", &[2]);
+        cells[1].y - cells[0].y
+    }
+
+    pub(crate) fn max_scroll(&self) -> f32 {
+        let virtual_size = self.list_item_pitch() * self.list_elements();
+        return reverse_scroll(virtual_size - self.layout.rect("list").h);
+    }
+
+    pub(crate) fn max_scroll_adjusted(&self) -> f32 {
+        let virtual_size = self.list_item_pitch() * self.list_elements() + LIST_CHIN_HEIGHT / 2.0;
+        return reverse_scroll(virtual_size - self.layout.rect("list").h);
     }
 
     pub(crate) fn list_elements(&self) -> f32 {
@@ -125,13 +148,30 @@ impl Menu {
         return elements;
     }
 
+    /// The height a text is drawn at so it fits the area, texts too wide at the full height are made smaller.
+    /// Translations differ in length, so the width is measured instead of estimated from the number of characters.
+    pub(crate) fn fitted_scale(
+        &self,
+        ctx: &mut Context,
+        text: &str,
+        width: f32,
+        height: f32,
+    ) -> f32 {
+        let text_width = self.text_width(ctx, text, height);
+        if text_width > width {
+            height * width / text_width
+        } else {
+            height
+        }
+    }
+
     pub(crate) fn draw_clickables(
         &mut self,
         ctx: &mut Context,
         selected_groups: Vec<ClickableGroup>,
     ) {
         // Go through all clickables and draw them
-        for clickable in &mut self.clickables {
+        for clickable in &self.clickables {
             // We don't render clickables that
             // arent in the selected group
             if !selected_groups.contains(&clickable.group) {
@@ -169,11 +209,9 @@ impl Menu {
                 Err(_) => {}
             }
 
+            // The text keeps a margin to the sides of the button
             let mut text = Text::new(clickable.text.clone());
-            let scale = f32::min(
-                clickable.transform.width as f32 * 2.0 / clickable.text.len() as f32,
-                clickable.transform.height as f32 * 0.8,
-            );
+            let scale = self.fitted_scale(ctx, &clickable.text, rect.w * 0.9, rect.h * 0.8);
             text.set_font(self.font, graphics::Scale::uniform(scale));
 
             text.set_bounds(Point2::new(rect.w, rect.h), graphics::Align::Center);
@@ -207,9 +245,9 @@ impl Menu {
         color: graphics::Color,
         alignment: graphics::Align,
     ) {
-        // Draw player name
+        // A text too wide for the area is made smaller, and kept vertically centered
+        let scale = self.fitted_scale(ctx, &text, size.0, size.1);
         let mut text = Text::new(text);
-        let scale = size.1;
         text.set_font(self.font, graphics::Scale::uniform(scale));
 
         text.set_bounds(Point2::new(size.0, size.1), alignment);
@@ -218,9 +256,19 @@ impl Menu {
             ctx,
             &text,
             graphics::DrawParam::default()
-                .dest(Point2::<f32>::new(position.0, position.1))
+                .dest(Point2::<f32>::new(
+                    position.0,
+                    position.1 + (size.1 - scale) / 2.0,
+                ))
                 .color(color),
         )
         .expect("Error drawing clickable text");
     }
+
+    /// The width of a text drawn by draw_text with the given height
+    pub(crate) fn text_width(&self, ctx: &mut Context, text: &str, height: f32) -> f32 {
+        let mut text = Text::new(text);
+        text.set_font(self.font, graphics::Scale::uniform(height));
+        text.width(ctx) as f32
+    }
 }
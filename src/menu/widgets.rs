@@ -0,0 +1,165 @@
+use ggez::{graphics, Context};
+
+use crate::{
+    game::{DARK_COLOR, LIGHT_COLOR},
+    localization::tr,
+};
+
+use super::{
+    clickable::{Clickable, ClickableGroup, Transform, UiAction},
+    menu_state::Menu,
+};
+
+// The widgets the menus are built from. Buttons, toggles and list items are clickables
+// which carry the action they perform, text fields are drawn and edited by the scene they belong to.
+
+impl Clickable {
+    pub(crate) fn button(
+        action: UiAction,
+        transform: Transform,
+        text: String,
+        group: ClickableGroup,
+    ) -> Clickable {
+        Clickable {
+            action,
+            transform,
+            color: graphics::Color::from(LIGHT_COLOR),
+            text,
+            hovered: false,
+            list_item: false,
+            group,
+        }
+    }
+
+    /// A button which shows the first text when switched off and the second when switched on
+    pub(crate) fn toggle(
+        action: UiAction,
+        transform: Transform,
+        texts: [String; 2],
+        on: bool,
+        group: ClickableGroup,
+    ) -> Clickable {
+        Clickable::button(action, transform, texts[on as usize].clone(), group)
+    }
+
+    /// An entry of the main menu list, which scrolls with the list
+    pub(crate) fn list_item(action: UiAction, transform: Transform, text: String) -> Clickable {
+        Clickable {
+            action,
+            transform,
+            color: graphics::Color::from(DARK_COLOR),
+            text,
+            hovered: false,
+            list_item: true,
+            group: ClickableGroup::MainMenuList,
+        }
+    }
+
+    pub(crate) fn with_color(mut self, color: graphics::Color) -> Clickable {
+        self.color = color;
+        self
+    }
+}
+
+/// A line of text typed by the user, drawn as "label: text" above an underline
+pub(crate) struct TextField {
+    pub(crate) label: &'static str, // The key of the label's text
+    pub(crate) max_length: usize,
+    pub(crate) accepts: fn(char) -> bool,
+}
+
+fn is_name_character(character: char) -> bool {
+    !character.is_whitespace() && !character.is_control()
+}
+
+// Host and port, e.g. "192.168.0.2:7878", "localhost:7878" or "[::1]:7878"
+fn is_address_character(character: char) -> bool {
+    character.is_ascii_alphanumeric() || ".-:[]".contains(character)
+}
+
+fn is_url_character(character: char) -> bool {
+    character.is_ascii_graphic()
+}
+
+fn is_move_character(character: char) -> bool {
+    character.is_ascii_alphanumeric() || "-=+#:".contains(character)
+}
+
+pub(crate) const NAME_FIELD: TextField = TextField {
+    label: "field.name",
+    max_length: 20,
+    accepts: is_name_character,
+};
+
+pub(crate) const ADDRESS_FIELD: TextField = TextField {
+    label: "field.address",
+    max_length: 40,
+    accepts: is_address_character,
+};
+
+pub(crate) const SERVER_FIELD: TextField = TextField {
+    label: "field.server",
+    max_length: 60,
+    accepts: is_url_character,
+};
+
+pub(crate) const MOVE_FIELD: TextField = TextField {
+    label: "field.move",
+    max_length: 10,
+    accepts: is_move_character,
+};
+
+impl TextField {
+    /// Adds a typed character to the text
+    pub(crate) fn edit(&self, text: &mut String, character: char) {
+        // 8u8 is the ASCII code for backspace
+        if character == (8u8 as char) {
+            text.pop();
+        } else if (self.accepts)(character) && text.len() < self.max_length {
+            text.push(character);
+        }
+    }
+
+    /// Draws the field in the given area, with the underline just below it
+    pub(crate) fn draw(
+        &self,
+        menu: &mut Menu,
+        ctx: &mut Context,
+        text: &str,
+        area: graphics::Rect,
+    ) {
+        menu.draw_text(
+            ctx,
+            format!("{}: {}", tr(self.label), text),
+            (area.x, area.y),
+            (area.w, area.h),
+            graphics::Color::from(LIGHT_COLOR),
+            graphics::Align::Left,
+        );
+
+        // Draw text underline
+        match graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(area.x, area.y + area.h + 10.0, area.w, 2.0),
+            graphics::Color::from(LIGHT_COLOR),
+        ) {
+            Ok(rect) => {
+                graphics::draw(ctx, &rect, graphics::DrawParam::default())
+                    .expect("Could not draw underline");
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+impl Menu {
+    /// Changes the text of the clickables with the given action
+    pub(crate) fn set_text(&mut self, action: &UiAction, text: String) {
+        for clickable in self.clickables.iter_mut() {
+            if clickable.action == *action {
+                clickable.text = text.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::mem::discriminant;
+
+use crate::{
+    game::Game,
+    localization::{tr, tr_args},
+    piece::piece::{get_valid_move_indices, Piece, PieceColor, PieceType, PieceType::*},
+    render_utilities::{translate_to_coords, translate_to_index},
+    sound::SoundEvent,
+};
+
+/// The text typed into the move box and the response to the last submitted move
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MoveEntry {
+    pub(crate) text: String,
+    pub(crate) feedback: String,
+    pub(crate) is_error: bool,
+}
+
+/// A move typed by the player, either in algebraic notation ("e4", "Nf3", "exd5", "e8=Q", "O-O")
+/// or as the source and destination tiles ("e2e4")
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MoveInput {
+    Tiles {
+        from: usize,
+        to: usize,
+        promotion: Option<PieceType>,
+    },
+    Algebraic {
+        piece_type: PieceType,
+        file: Option<usize>, // Board x coordinate of the moving piece, if given
+        rank: Option<usize>, // Board y coordinate of the moving piece, if given
+        to: usize,
+        promotion: Option<PieceType>,
+    },
+    Castle {
+        kingside: bool,
+    },
+}
+
+// The a-file is at the highest x coordinate of the board
+fn file_to_x(character: char) -> Option<usize> {
+    match character {
+        'a'..='h' => Some((b'h' - character as u8) as usize),
+        _ => None,
+    }
+}
+
+fn rank_to_y(character: char) -> Option<usize> {
+    match character {
+        '1'..='8' => Some((character as u8 - b'1') as usize),
+        _ => None,
+    }
+}
+
+/// The name of a tile in algebraic notation, e.g. "e4"
+pub(crate) fn tile_name(index: usize) -> String {
+    let (x, y) = translate_to_coords(index);
+    format!("{}{}", (b'h' - x as u8) as char, y + 1)
+}
+
+fn piece_from_letter(letter: char) -> Option<PieceType> {
+    match letter {
+        'K' => Some(King(false)),
+        'Q' => Some(Queen),
+        'R' => Some(Rook(false)),
+        'B' => Some(Bishop),
+        'N' => Some(Knight),
+        _ => None,
+    }
+}
+
+fn promotion_from_letter(letter: char) -> Option<PieceType> {
+    match letter.to_ascii_uppercase() {
+        'Q' => Some(Queen),
+        'R' => Some(Rook(true)),
+        'B' => Some(Bishop),
+        'N' => Some(Knight),
+        _ => None,
+    }
+}
+
+/// Compares piece types without their inner values
+pub(crate) fn same_kind(a: PieceType, b: PieceType) -> bool {
+    discriminant(&a) == discriminant(&b)
+}
+
+impl MoveInput {
+    fn parse(text: &str) -> Option<MoveInput> {
+        // Check and mate markers are allowed but not needed
+        let text = text.trim().trim_end_matches(['+', '#']);
+        match text {
+            "O-O" | "0-0" => return Some(MoveInput::Castle { kingside: true }),
+            "O-O-O" | "0-0-0" => return Some(MoveInput::Castle { kingside: false }),
+            _ => {}
+        }
+
+        // Captures don't need to be marked since the opponent's pieces may be hidden by the fog
+        let mut characters: Vec<char> = text
+            .chars()
+            .filter(|c| !matches!(c, 'x' | '-' | '=' | ':'))
+            .collect();
+
+        let mut promotion = None;
+        if characters.len() > 2 {
+            let last = characters[characters.len() - 1];
+            let before_last = characters[characters.len() - 2];
+            if rank_to_y(before_last).is_some() && promotion_from_letter(last).is_some() {
+                promotion = promotion_from_letter(last);
+                characters.pop();
+            }
+        }
+
+        // The destination tile is always last
+        let rank = rank_to_y(characters.pop()?)?;
+        let file = file_to_x(characters.pop()?)?;
+        let to = translate_to_index(file, rank);
+
+        let piece_type = match characters.first().and_then(|c| piece_from_letter(*c)) {
+            Some(piece_type) => {
+                characters.remove(0);
+                piece_type
+            }
+            None => Pawn(false),
+        };
+
+        // What remains is the tile, file or rank the piece moves from
+        let (file, rank) = match characters.as_slice() {
+            [] => (None, None),
+            [file, rank] => (Some(file_to_x(*file)?), Some(rank_to_y(*rank)?)),
+            [character] => match (file_to_x(*character), rank_to_y(*character)) {
+                (Some(file), _) => (Some(file), None),
+                (_, Some(rank)) => (None, Some(rank)),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        // A pawn named by its destination alone moves straight forward
+        let file = match (piece_type, file) {
+            (Pawn(_), None) => Some(translate_to_coords(to).0),
+            _ => file,
+        };
+
+        match (piece_type, file, rank) {
+            (Pawn(_), Some(file), Some(rank)) => Some(MoveInput::Tiles {
+                from: translate_to_index(file, rank),
+                to,
+                promotion,
+            }),
+            _ => Some(MoveInput::Algebraic {
+                piece_type,
+                file,
+                rank,
+                to,
+                promotion,
+            }),
+        }
+    }
+
+    /// Whether the piece is the one described by the input, and if so where it moves to
+    fn destination(&self, piece: &Piece) -> Option<usize> {
+        let (x, y) = piece.get_pos();
+        match *self {
+            MoveInput::Tiles { from, to, .. } if piece.index == from => Some(to),
+            MoveInput::Algebraic {
+                piece_type,
+                file,
+                rank,
+                to,
+                ..
+            } if same_kind(piece.piece_type, piece_type)
+                && file.unwrap_or(x) == x
+                && rank.unwrap_or(y) == y =>
+            {
+                Some(to)
+            }
+            // Castling moves the king two steps towards the rook, kingside being towards the lower x coordinates
+            MoveInput::Castle { kingside } if piece.piece_type == King(false) => {
+                if kingside {
+                    piece.index.checked_sub(2)
+                } else {
+                    Some(piece.index + 2)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn promotion(&self) -> Option<PieceType> {
+        match *self {
+            MoveInput::Tiles { promotion, .. } | MoveInput::Algebraic { promotion, .. } => {
+                promotion
+            }
+            MoveInput::Castle { .. } => None,
+        }
+    }
+}
+
+impl Game {
+    /// Makes the move typed into the move box, the text is kept if the move could not be made
+    pub(crate) fn submit_move_entry(&mut self) {
+        let text = self.move_entry.text.trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+
+        match self.enter_move(&text) {
+            Ok(feedback) => {
+                self.move_entry.text.clear();
+                self.move_entry.feedback = feedback;
+                self.move_entry.is_error = false;
+            }
+            Err(feedback) => {
+                self.move_entry.feedback = feedback;
+                self.move_entry.is_error = true;
+            }
+        }
+    }
+
+    /// Attempts a typed move with the same rules as moving by mouse, returning the feedback to show
+    fn enter_move(&mut self, text: &str) -> Result<String, String> {
+        let opponent_online = self.connection.state.opponent_online;
+        if self.spectating.is_some() {
+            return Err(tr("entry.spectating"));
+        }
+        if !opponent_online && !self.is_correspondence() {
+            return Err(tr("entry.waiting"));
+        }
+
+        let input = match MoveInput::parse(text) {
+            Some(input) => input,
+            None => return Err(tr_args("entry.unreadable", &[("move", text.to_string())])),
+        };
+
+        // A typed premove replaces the queued one
+        let is_premove = !self.active_turn;
+        if is_premove {
+            self.premove = None;
+        }
+
+        let color = if self.playing_as_white {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        let candidates: Vec<(Piece, usize)> = self
+            .board
+            .iter()
+            .flatten()
+            .filter(|piece| piece.color == color)
+            .filter_map(|piece| {
+                let destination = input.destination(piece)?;
+                let valid_moves = get_valid_move_indices(self, piece, is_premove);
+                if destination != piece.index && valid_moves.contains(&destination) {
+                    Some((*piece, destination))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let (piece, destination) = match candidates.as_slice() {
+            [candidate] => *candidate,
+            [] => {
+                self.play_sound(SoundEvent::IllegalMove);
+                return Err(tr_args("entry.illegal", &[("move", text.to_string())]));
+            }
+            _ => {
+                let sources: Vec<String> = candidates
+                    .iter()
+                    .map(|(piece, _)| tile_name(piece.index))
+                    .collect();
+                return Err(tr_args(
+                    "entry.ambiguous",
+                    &[
+                        ("move", text.to_string()),
+                        ("tiles", sources.join(&format!(" {} ", tr("entry.or")))),
+                    ],
+                ));
+            }
+        };
+
+        self.selected_piece = None;
+        if let Some(piece) = self.board[piece.index].take() {
+            // A typed promotion piece is used right away, otherwise it is chosen in the promotion menu
+            self.attempt_move(piece, destination, input.promotion());
+        }
+
+        if is_premove {
+            Ok(tr_args("entry.premove", &[("move", text.to_string())]))
+        } else {
+            Ok(tr_args("entry.played", &[("move", text.to_string())]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(name: &str) -> usize {
+        let mut characters = name.chars();
+        let x = file_to_x(characters.next().unwrap()).unwrap();
+        let y = rank_to_y(characters.next().unwrap()).unwrap();
+        translate_to_index(x, y)
+    }
+
+    fn file(name: char) -> Option<usize> {
+        file_to_x(name)
+    }
+
+    fn algebraic(
+        piece_type: PieceType,
+        file: Option<usize>,
+        rank: Option<usize>,
+        to: &str,
+        promotion: Option<PieceType>,
+    ) -> Option<MoveInput> {
+        Some(MoveInput::Algebraic {
+            piece_type,
+            file,
+            rank,
+            to: tile(to),
+            promotion,
+        })
+    }
+
+    fn tiles(from: &str, to: &str, promotion: Option<PieceType>) -> Option<MoveInput> {
+        Some(MoveInput::Tiles {
+            from: tile(from),
+            to: tile(to),
+            promotion,
+        })
+    }
+
+    #[test]
+    fn parses_typed_moves() {
+        let cases = [
+            ("e4", algebraic(Pawn(false), file('e'), None, "e4", None)),
+            (" e4 ", algebraic(Pawn(false), file('e'), None, "e4", None)),
+            ("e2e4", tiles("e2", "e4", None)),
+            ("e2-e4", tiles("e2", "e4", None)),
+            ("Nf3", algebraic(Knight, None, None, "f3", None)),
+            ("Nbd7", algebraic(Knight, file('b'), None, "d7", None)),
+            (
+                "R1a3",
+                algebraic(Rook(false), None, rank_to_y('1'), "a3", None),
+            ),
+            (
+                "Qh4xe1",
+                algebraic(Queen, file('h'), rank_to_y('4'), "e1", None),
+            ),
+            ("Qh4#", algebraic(Queen, None, None, "h4", None)),
+            ("Kxe2+", algebraic(King(false), None, None, "e2", None)),
+            ("exd5", algebraic(Pawn(false), file('e'), None, "d5", None)),
+            (
+                "e8=Q",
+                algebraic(Pawn(false), file('e'), None, "e8", Some(Queen)),
+            ),
+            (
+                "bxc8=B",
+                algebraic(Pawn(false), file('b'), None, "c8", Some(Bishop)),
+            ),
+            ("e7e8n", tiles("e7", "e8", Some(Knight))),
+            ("O-O", Some(MoveInput::Castle { kingside: true })),
+            ("0-0+", Some(MoveInput::Castle { kingside: true })),
+            ("O-O-O", Some(MoveInput::Castle { kingside: false })),
+            ("0-0-0", Some(MoveInput::Castle { kingside: false })),
+            // Rejected inputs
+            ("", None),
+            ("i9", None),
+            ("e9", None),
+            ("Nf", None),
+            ("N", None),
+            ("Zf3", None),
+            ("Nb1c2d3", None),
+            ("e8=K", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(MoveInput::parse(text), expected, "parsing {:?}", text);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use ggez::{
+    event::KeyCode,
+    graphics::{self, Rect},
+    Context, GameResult,
+};
+
+use crate::{
+    default_board_state::generate_default_board,
+    event_handler::BOARD_SIZE,
+    game::{Game, DARK_COLOR, LIGHT_COLOR},
+    localization::{tr, tr_args},
+    move_entry::tile_name,
+    move_struct::{apply_move, Move, MoveType},
+    piece::piece::{visible_tiles, Board, PieceColor, PieceType},
+    spectator::{Perspective, Spectator},
+};
+
+const ROW_HEIGHT: f32 = 30.0;
+
+/// The state of the move list beside the board
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MoveList {
+    pub(crate) preview: Option<usize>, // Number of moves made in the previewed position, None when showing the live game
+    scroll: usize,                     // The first row shown
+    rows: usize, // Rows when the list was last drawn, used to follow new moves
+    texts: Vec<String>,
+    texts_of: Option<(Vec<Move>, bool, PieceColor)>, // The history, whether all is revealed and the player's color the texts were made for
+}
+
+/// A position of the game and the tiles which are visible in it
+pub(crate) struct BoardView {
+    pub(crate) board: Board,
+    pub(crate) visible: Vec<usize>,
+    pub(crate) last_move: Option<Move>,
+    pub(crate) live: bool,
+}
+
+fn piece_letter(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::King(_) => "K",
+        PieceType::Queen => "Q",
+        PieceType::Rook(_) => "R",
+        PieceType::Bishop => "B",
+        PieceType::Knight => "N",
+        PieceType::Pawn(_) => "",
+    }
+}
+
+/// A move in long algebraic notation, e.g. "Ng1-f3". Tiles the player didn't see are shown as "?"
+fn notation(move_: &Move, seen_source: bool, seen_dest: bool) -> String {
+    if !seen_source && !seen_dest {
+        return String::from("?");
+    }
+    if let (MoveType::Castle, true, true) = (move_.move_type, seen_source, seen_dest) {
+        // Kingside is towards the lower indices
+        return if move_.piece_dest_index < move_.piece.index {
+            String::from("O-O")
+        } else {
+            String::from("O-O-O")
+        };
+    }
+
+    let tile = |seen: bool, index: usize| {
+        if seen {
+            tile_name(index)
+        } else {
+            String::from("?")
+        }
+    };
+    let mut text = format!(
+        "{}{}{}{}",
+        piece_letter(move_.piece.piece_type),
+        tile(seen_source, move_.piece.index),
+        if move_.captured_piece.is_some() && seen_dest {
+            "x"
+        } else {
+            "-"
+        },
+        tile(seen_dest, move_.piece_dest_index)
+    );
+    if let (MoveType::Promotion(piece_type), true) = (move_.move_type, seen_dest) {
+        text.push('=');
+        text.push_str(piece_letter(piece_type));
+    }
+    text
+}
+
+/// The title above the list, which tells which position is previewed out of how many moves
+fn list_title(preview: Option<usize>, total: usize) -> String {
+    match preview {
+        Some(moves) => tr_args(
+            "moves.preview",
+            &[("move", moves.to_string()), ("total", total.to_string())],
+        ),
+        None => tr("moves.title"),
+    }
+}
+
+impl Game {
+    pub(crate) fn own_color(&self) -> PieceColor {
+        if self.playing_as_white {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        }
+    }
+
+    /// The whole game is shown once it is over, and to spectators watching the full board
+    fn reveals_all(&self) -> bool {
+        self.winner.is_some()
+            || matches!(
+                self.spectating,
+                Some(Spectator {
+                    perspective: Perspective::Full,
+                    ..
+                })
+            )
+    }
+
+    /// The notation of every move as far as the player has seen it.
+    /// The opponent's moves are only shown on the tiles which were visible when they were made.
+    fn move_texts(&self) -> Vec<String> {
+        let mut board = generate_default_board();
+        let mut texts = Vec::new();
+        for (moves, move_) in self.move_history.iter().enumerate() {
+            let (seen_source, seen_dest) = if self.reveals_all()
+                || move_.piece.color == self.own_color()
+            {
+                (true, true)
+            } else {
+                let visible = visible_tiles(&board, &self.move_history[..moves], self.own_color());
+                (
+                    visible.contains(&move_.piece.index),
+                    visible.contains(&move_.piece_dest_index),
+                )
+            };
+            texts.push(notation(move_, seen_source, seen_dest));
+            apply_move(&mut board, move_);
+        }
+        texts
+    }
+
+    /// Makes the texts of the move list again if the game has changed since they were made
+    fn update_move_texts(&mut self) {
+        let (reveals_all, own_color) = (self.reveals_all(), self.own_color());
+        let current = matches!(
+            &self.move_list.texts_of,
+            Some((history, reveals, color))
+                if *history == self.move_history && *reveals == reveals_all && *color == own_color
+        );
+        if !current {
+            self.move_list.texts = self.move_texts();
+            self.move_list.texts_of = Some((self.move_history.clone(), reveals_all, own_color));
+        }
+    }
+
+    /// The position to draw, which is either the live game or the previewed position
+    pub(crate) fn board_view(&self) -> BoardView {
+        match self.move_list.preview {
+            None => BoardView {
+                board: self.board.clone(),
+                visible: self.available_moves.clone(),
+                last_move: self.move_history.last().copied(),
+                live: true,
+            },
+            Some(moves) => {
+                let history = &self.move_history[..moves];
+                let mut board = generate_default_board();
+                for move_ in history {
+                    apply_move(&mut board, move_);
+                }
+                let visible = if self.reveals_all() {
+                    (0..BOARD_SIZE * BOARD_SIZE).collect()
+                } else {
+                    visible_tiles(&board, history, self.own_color())
+                };
+                BoardView {
+                    board,
+                    visible,
+                    last_move: history.last().copied(),
+                    live: false,
+                }
+            }
+        }
+    }
+
+    /// Shows the position after the given number of moves, the live game is shown when all moves are made
+    pub(crate) fn preview_position(&mut self, moves: usize) {
+        self.move_list.preview = if moves >= self.move_history.len() {
+            None
+        } else {
+            Some(moves)
+        };
+        self.grabbed_piece = None;
+        self.selected_piece = None;
+    }
+
+    /// Steps through the earlier positions with the arrow keys, Home and End jump to the start and the live game
+    pub(crate) fn move_list_key_down(&mut self, keycode: KeyCode) {
+        let current = self.move_list.preview.unwrap_or(self.move_history.len());
+        match keycode {
+            KeyCode::Left | KeyCode::Up => self.preview_position(current.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Down => self.preview_position(current + 1),
+            KeyCode::Home => self.preview_position(0),
+            KeyCode::End => self.preview_position(self.move_history.len()),
+            _ => return,
+        }
+
+        // Keep the shown move in view
+        let visible_rows = self.visible_rows();
+        if let Some(moves) = self.move_list.preview {
+            let row = moves.saturating_sub(1) / 2;
+            if row < self.move_list.scroll {
+                self.move_list.scroll = row;
+            } else if row >= self.move_list.scroll + visible_rows {
+                self.move_list.scroll = row + 1 - visible_rows;
+            }
+        } else {
+            self.move_list.scroll = self.move_list.rows.saturating_sub(visible_rows);
+        }
+    }
+
+    // The first row of the list is its title
+    fn visible_rows(&self) -> usize {
+        let area = self.menu.layout.rect("move_list");
+        ((area.h / ROW_HEIGHT) as usize).saturating_sub(1)
+    }
+
+    /// Where a move is drawn in the list, if it is scrolled into view
+    fn move_rect(&self, index: usize) -> Option<Rect> {
+        let row = index / 2;
+        if row < self.move_list.scroll || row >= self.move_list.scroll + self.visible_rows() {
+            return None;
+        }
+        let area = self.menu.layout.rect("move_list");
+        Some(Rect::new(
+            area.x + area.w * (0.2 + 0.4 * (index % 2) as f32),
+            area.y + ROW_HEIGHT * (row - self.move_list.scroll + 1) as f32,
+            area.w * 0.4,
+            ROW_HEIGHT,
+        ))
+    }
+
+    /// Clicking a move previews the position after it, returns whether a move was clicked
+    pub(crate) fn move_list_mouse_down(&mut self, x: f32, y: f32) -> bool {
+        let clicked = (0..self.move_history.len()).find(|index| {
+            self.move_rect(*index)
+                .is_some_and(|rect| rect.contains([x, y]))
+        });
+        if let Some(index) = clicked {
+            self.preview_position(index + 1);
+        }
+        clicked.is_some()
+    }
+
+    pub(crate) fn move_list_scroll(&mut self, x: f32, y: f32, amount: f32) {
+        let area = self.menu.layout.rect("move_list");
+        if !area.contains([x, y]) {
+            return;
+        }
+        let max_scroll = self.move_list.rows.saturating_sub(self.visible_rows());
+        if amount > 0.0 {
+            self.move_list.scroll = self.move_list.scroll.saturating_sub(1);
+        } else if amount < 0.0 {
+            self.move_list.scroll = (self.move_list.scroll + 1).min(max_scroll);
+        }
+    }
+
+    pub(crate) fn draw_move_list(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let area = self.menu.layout.rect("move_list");
+        self.update_move_texts();
+        let moves = self.move_list.texts.len();
+        let rows = moves.div_ceil(2);
+        let visible_rows = self.visible_rows();
+
+        // Follow new moves unless an earlier position is being previewed
+        if rows != self.move_list.rows && self.move_list.preview.is_none() {
+            self.move_list.scroll = rows.saturating_sub(visible_rows);
+        }
+        self.move_list.rows = rows;
+        self.move_list.scroll = self.move_list.scroll.min(rows.saturating_sub(visible_rows));
+
+        self.menu.draw_text(
+            ctx,
+            list_title(self.move_list.preview, moves),
+            (area.x, area.y),
+            (area.w, ROW_HEIGHT * 0.8),
+            graphics::Color::from(LIGHT_COLOR),
+            graphics::Align::Center,
+        );
+
+        for index in 0..moves {
+            let rect = match self.move_rect(index) {
+                Some(rect) => rect,
+                None => continue,
+            };
+
+            // Highlight the move of the previewed position
+            if self.move_list.preview == Some(index + 1) {
+                let highlight = graphics::Mesh::new_rectangle(
+                    ctx,
+                    graphics::DrawMode::fill(),
+                    rect,
+                    graphics::Color::from(DARK_COLOR),
+                )?;
+                graphics::draw(ctx, &highlight, graphics::DrawParam::default())?;
+            }
+
+            if index % 2 == 0 {
+                self.menu.draw_text(
+                    ctx,
+                    format!("{}.", index / 2 + 1),
+                    (area.x, rect.y + ROW_HEIGHT * 0.1),
+                    (area.w * 0.2, ROW_HEIGHT * 0.8),
+                    graphics::Color::from(LIGHT_COLOR),
+                    graphics::Align::Left,
+                );
+            }
+            self.menu.draw_text(
+                ctx,
+                self.move_list.texts[index].clone(),
+                (rect.x, rect.y + ROW_HEIGHT * 0.1),
+                (rect.w, ROW_HEIGHT * 0.8),
+                graphics::Color::from(LIGHT_COLOR),
+                graphics::Align::Center,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::localization::load_languages;
+
+    #[test]
+    fn preview_title_counts_all_moves() {
+        load_languages(Path::new("no config dir"));
+        assert_eq!(list_title(Some(3), 10), "Move 3 of 10");
+        assert_eq!(list_title(None, 10), tr("moves.title"));
+    }
+}
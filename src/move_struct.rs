@@ -1,12 +1,14 @@
-use std::iter::FromIterator;
-
 use self::MoveType::*;
-use crate::piece::piece::{
-    self, Piece,
-    PieceType::{self, *},
+use crate::{
+    event_handler::BOARD_SIZE,
+    piece::piece::{
+        self, Board, Piece,
+        PieceColor::*,
+        PieceType::{self, *},
+    },
 };
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub(crate) struct Move {
     pub(crate) piece: Piece,
     pub(crate) piece_dest_index: usize,
@@ -41,37 +43,39 @@ impl ToString for Move {
 }
 
 impl Move {
-    pub(crate) fn from_str(string: String) -> Self {
-        // Removes prefix and suffix
-        let mut chars = string.chars();
-        chars.next();
-        chars.next_back();
-
-        let s: String = String::from_iter(chars);
-        let mut s = s.split(":");
+    /// Parses a move that has already been stripped of its surrounding quotes.
+    /// Moves come from other clients, so a malformed move returns None instead of panicking.
+    pub(crate) fn parse(string: &str) -> Option<Self> {
+        let mut s = string.split(":");
+        let parse_index = |index: Option<&str>| {
+            index?
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index < BOARD_SIZE * BOARD_SIZE)
+        };
 
-        let piece_type = PieceType::from_str(s.next().unwrap());
-        let color = piece::PieceColor::from_str(s.next().unwrap());
-        let index = s.next().unwrap().parse::<usize>().unwrap();
+        let piece_type = PieceType::from_str(s.next()?)?;
+        let color = piece::PieceColor::from_str(s.next()?)?;
+        let index = parse_index(s.next())?;
         let piece = Piece {
             piece_type,
             color,
             index,
         };
 
-        let piece_dest_index = s.next().unwrap().parse::<usize>().unwrap();
+        let piece_dest_index = parse_index(s.next())?;
 
         let captured_piece = {
-            let captured_type = s.next().unwrap();
+            let captured_type = s.next()?;
             if captured_type == "-" {
                 // Skips the captured color and index since there is no captured piece
                 s.next();
                 s.next();
                 None
             } else {
-                let captured_type = PieceType::from_str(captured_type);
-                let captured_color = piece::PieceColor::from_str(s.next().unwrap());
-                let captured_index = s.next().unwrap().parse::<usize>().unwrap();
+                let captured_type = PieceType::from_str(captured_type)?;
+                let captured_color = piece::PieceColor::from_str(s.next()?)?;
+                let captured_index = parse_index(s.next())?;
                 let captured_piece = Piece {
                     piece_type: captured_type,
                     color: captured_color,
@@ -80,17 +84,72 @@ impl Move {
                 Some(captured_piece)
             }
         };
-        let move_type = MoveType::from_str(s.next().unwrap());
-        Move {
+        let move_type = MoveType::from_str(s.next()?)?;
+        if s.next().is_some() {
+            return None;
+        }
+        Some(Move {
             piece,
             piece_dest_index,
             captured_piece,
             move_type,
+        })
+    }
+}
+
+/// Whether it is White's turn after the given number of moves
+pub(crate) fn white_to_move(moves: usize) -> bool {
+    moves.is_multiple_of(2)
+}
+
+/// Performs a move directly on the board without any side effects (no sound, networking or clock changes).
+/// Used when a position has to be rebuilt from a list of moves.
+pub(crate) fn apply_move(board: &mut Board, move_: &Move) {
+    let source_index = move_.piece.index;
+    let dest_index = move_.piece_dest_index;
+    if board[source_index].is_none() {
+        return;
+    }
+    board[source_index] = None;
+
+    let mut piece = move_.piece;
+    piece.index = dest_index;
+
+    match move_.move_type {
+        Regular => {}
+        EnPassant => {
+            // The captured pawn is located one square behind the destination
+            let one_square_back = if let White = piece.color {
+                dest_index - BOARD_SIZE
+            } else {
+                dest_index + BOARD_SIZE
+            };
+            board[one_square_back] = None;
+        }
+        Promotion(piece_type) => piece.piece_type = piece_type,
+        Castle => {
+            // Kingside rooks are located at lower indices than the king
+            let kingside = dest_index < source_index;
+            let (rook_index, king_index, rook_dest_index) = if kingside {
+                (source_index - 3, source_index - 2, source_index - 1)
+            } else {
+                (source_index + 4, source_index + 2, source_index + 1)
+            };
+            if let Some(mut rook) = board[rook_index].take() {
+                rook.piece_type = Rook(true);
+                rook.index = rook_dest_index;
+                board[rook_dest_index] = Some(rook);
+            }
+            piece.piece_type = King(true);
+            piece.index = king_index;
+            board[king_index] = Some(piece);
+            return;
         }
     }
+    board[dest_index] = Some(piece);
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub(crate) enum MoveType {
     Regular,
     EnPassant,
@@ -114,24 +173,25 @@ impl MoveType {
         }
     }
 
-    pub(crate) fn from_str(string: &str) -> Self {
+    pub(crate) fn from_str(string: &str) -> Option<Self> {
         let mut chars = string.chars();
 
         // If promoting
-        if chars.next().unwrap() == 'p' {
-            Promotion(match chars.next().unwrap() {
+        if chars.next()? == 'p' {
+            let piece_type = match chars.next()? {
                 'q' => Queen,
                 'r' => Rook(true),
                 'b' => Bishop,
                 'n' => Knight,
-                _ => panic!("Invalid promotion type"),
-            })
+                _ => return None,
+            };
+            chars.next().is_none().then_some(Promotion(piece_type))
         } else {
             match string {
-                "r" => Regular,
-                "ep" => EnPassant,
-                "c" => Castle,
-                _ => panic!("Invalid input when attempting to convert &str to MoveType"),
+                "r" => Some(Regular),
+                "ep" => Some(EnPassant),
+                "c" => Some(Castle),
+                _ => None,
             }
         }
     }
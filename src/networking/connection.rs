@@ -1,11 +1,72 @@
-use crate::{networking::events, piece::piece::PieceColor};
-use rust_socketio::{Socket, SocketBuilder};
+use std::{
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    move_struct::Move,
+    networking::{
+        discovery::{self, Announcer},
+        events,
+        peer::PeerConnection,
+        transport::{new_event_queue, EventQueue, Transport},
+    },
+    piece::piece::PieceColor,
+    spectator::Clocks,
+};
+use rust_socketio::{Payload, Socket, SocketBuilder};
 use serde_json::json;
+
+pub(crate) const SERVER_URL: &str = "http://chess.datasektionen.link";
+
+// Every event the lobby server can send to a client
+const SERVER_EVENTS: [&str; 22] = [
+    "join_room_res",
+    "create_room_res",
+    "opponent",
+    "move_ack",
+    "request_resync",
+    "resync",
+    "opponent_connect",
+    "opponent_disconnect",
+    "opponent_leave_lobby",
+    "list_rooms",
+    "play_again",
+    "set_opponent_color",
+    "resign",
+    "get_opponent_name",
+    "set_clock_time",
+    "spectate_room_res",
+    "spectator_connect",
+    "spectate_sync",
+    "spectate_move",
+    "correspondence_sync",
+    "correspondence_games",
+    "correspondence_timeout",
+];
+
 pub(crate) struct Networking {
-    socket: Socket,
+    pub(crate) state: ConnectionState,
+    server: Option<Box<dyn Transport>>,
+    peer: Option<Box<dyn Transport>>,
+    announcer: Option<Announcer>,
+    // Identifies this client in LAN announcements, so that it doesn't list its own game
+    session: u64,
 }
 
-#[derive(Debug, Clone)]
+/// What has been received from the server and the peer, waiting to be handled by the game
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConnectionState {
+    pub(crate) lobbies: Vec<Room>,
+    pub(crate) lobby_sync: i32, // Changes whenever lobbies changes
+    pub(crate) event_validation: NetworkEventValidation,
+    pub(crate) incoming_moves: Vec<(usize, u64, Move)>, // Ply, position hash and move
+    pub(crate) incoming_spectator_moves: Vec<(Move, Clocks)>,
+    pub(crate) room_id: Option<String>,
+    pub(crate) opponent_online: bool,
+}
+
+#[derive(Debug, Clone, Default)]
 pub(crate) struct NetworkEventValidation {
     pub(crate) create_room: bool,
     pub(crate) join_room: bool,
@@ -15,70 +76,313 @@ pub(crate) struct NetworkEventValidation {
     pub(crate) set_color: Option<PieceColor>,
     pub(crate) resign: bool,
     pub(crate) opponent_name: Option<String>,
-    pub(crate) time: Option<(u64, u64)>,
+    pub(crate) time: Option<String>, // The time control, see Time::control_string
     pub(crate) deselect_cursor: bool,
+    pub(crate) spectate_room: bool,
+    pub(crate) spectator_connect: bool,
+    pub(crate) spectate_sync: Option<String>,
+    pub(crate) move_ack: Option<usize>,
+    pub(crate) request_resync: bool,
+    pub(crate) resync: Option<String>,
+    pub(crate) correspondence_sync: Option<String>,
+    pub(crate) correspondence_games: Option<String>,
+    pub(crate) correspondence_timeout: Option<PieceColor>, // The winner, decided by the room admin
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct Room {
     pub(crate) id: String,
     pub(crate) members: i32,
+    pub(crate) preset: Option<String>, // Name of the room's time control preset
 }
 
-impl Networking {
-    pub(crate) fn new() -> Networking {
-        let socket = SocketBuilder::new("http://chess.datasektionen.link")
+/// The connection to the lobby server
+pub(crate) struct SocketIoTransport {
+    socket: Socket,
+    received: EventQueue,
+}
+
+impl SocketIoTransport {
+    pub(crate) fn connect(url: &str) -> Option<SocketIoTransport> {
+        let received = new_event_queue();
+
+        let mut builder = SocketBuilder::new(url)
             .set_namespace("/")
             .expect("illegal namespace")
-            .on("join_room_res", |payload, socket| {
-                events::on_join_room(payload, socket)
-            })
-            .on("create_room_res", |payload, socket| {
-                events::on_create_room(payload, socket)
-            })
-            .on("opponent", |payload, socket| {
-                events::on_opponent(payload, socket)
-            })
-            .on("opponent_connect", |payload, socket| {
-                events::on_opponent_connect(payload, socket)
-            })
-            .on("opponent_disconnect", |payload, socket| {
-                events::on_opponent_disconnect(payload, socket)
-            })
-            .on("opponent_leave_lobby", |payload, socket| {
-                events::on_opponent_disconnect(payload, socket)
-            })
-            .on("list_rooms", |payload, socket| {
-                events::on_list_room(payload, socket)
-            })
-            .on("play_again", |payload, socket| {
-                events::on_play_again(payload, socket)
-            })
-            .on("set_opponent_color", |payload, socket| {
-                events::on_set_opponent_color(payload, socket)
-            })
-            .on("resign", |payload, socket| {
-                events::on_resign(payload, socket)
-            })
-            .on("get_opponent_name", |payload, socket| {
-                events::on_get_opponent_name(payload, socket)
-            })
-            .on("set_clock_time", |payload, socket| {
-                events::on_set_clock_time(payload, socket)
-            })
-            .on("error", |err, _| eprintln!("Error: {:#?}", err))
-            .connect()
-            .expect("Connection failed");
-
-        return Networking { socket };
+            .on("error", |err, _| eprintln!("Error: {:#?}", err));
+        for event in SERVER_EVENTS.iter() {
+            let event = *event;
+            let received = received.clone();
+            builder = builder.on(event, move |payload, _| {
+                if let Payload::String(data) = payload {
+                    received
+                        .lock()
+                        .unwrap()
+                        .push_back((event.to_string(), data));
+                }
+            });
+        }
+
+        match builder.connect() {
+            Ok(socket) => Some(SocketIoTransport { socket, received }),
+            Err(e) => {
+                eprintln!("Could not connect to the lobby server: {}", e);
+                None
+            }
+        }
     }
+}
 
-    pub(crate) fn send(&mut self, event: &str, data: &str) {
-        println!("SENDING '{}' '{}'", event.trim(), data.trim());
+impl Transport for SocketIoTransport {
+    fn send(&mut self, event: &str, data: &str) {
         self.socket
-            .emit(event.trim(), json!(data.trim()))
+            .emit(event, json!(data))
             .expect("Could not emit payload via sockets");
     }
+
+    fn receive(&mut self) -> Option<(String, String)> {
+        self.received.lock().unwrap().pop_front()
+    }
+}
+
+impl Networking {
+    /// Creates the networking of the game. Without a server the game can still be played
+    /// over a direct connection.
+    pub(crate) fn new(server: Option<Box<dyn Transport>>) -> Networking {
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0)
+            ^ ((std::process::id() as u64) << 32);
+
+        Networking {
+            state: ConnectionState::default(),
+            server,
+            peer: None,
+            announcer: None,
+            session,
+        }
+    }
+
+    /// Starts listing the games announced on the local network
+    pub(crate) fn start_discovery(&self) {
+        discovery::start_listener(self.session);
+    }
+
+    /// Plays the game through the given transport instead of the lobby server
+    pub(crate) fn connect_peer(&mut self, transport: Box<dyn Transport>) {
+        self.peer = Some(transport);
+    }
+
+    /// Hosts a game that another client can connect to directly, and announces it on the local network
+    pub(crate) fn host_peer(&mut self, port: u16) -> io::Result<()> {
+        self.connect_peer(Box::new(PeerConnection::host(port)?));
+        self.announcer = Announcer::new(self.session, port);
+        Ok(())
+    }
+
+    /// Updates the time control of the game announced on the local network
+    pub(crate) fn announce_time(&mut self, initial_time: u64, increment: u64, preset: &str) {
+        if let Some(announcer) = &mut self.announcer {
+            announcer.set_time(initial_time, increment, preset);
+        }
+    }
+
+    pub(crate) fn stop_announcing(&mut self) {
+        self.announcer = None;
+    }
+
+    /// Connects directly to a client hosting a game
+    pub(crate) fn join_peer(&mut self, address: &str) -> io::Result<()> {
+        self.connect_peer(Box::new(PeerConnection::join(address)?));
+        Ok(())
+    }
+
+    pub(crate) fn send(&mut self, event: &str, data: &str) {
+        println!("SENDING '{}' '{}'", event.trim(), data.trim());
+
+        // Lobby events always go to the server, game events go to the peer if there is one
+        let lobby_event = matches!(
+            event.trim(),
+            "list_rooms"
+                | "create_room"
+                | "join_room"
+                | "set_name"
+                | "set_room_preset"
+                | "spectate_room"
+                | "spectator_leave"
+                | "list_correspondence"
+                | "save_correspondence"
+                | "finish_correspondence"
+                | "resume_correspondence"
+                | "leave_correspondence"
+        );
+        if let (Some(peer), false) = (&mut self.peer, lobby_event) {
+            peer.send(event.trim(), data.trim());
+            if event.trim() == "opponent_leave_lobby" {
+                self.peer = None;
+                self.announcer = None;
+            }
+            return;
+        }
+
+        if let Some(server) = &mut self.server {
+            server.send(event.trim(), data.trim());
+        }
+    }
+
+    /// Handles every event received since the last call, from both the server and the peer
+    pub(crate) fn poll(&mut self) {
+        for (event, data) in self.receive() {
+            events::dispatch(&mut self.state, &event, data);
+        }
+    }
+
+    /// Takes every event received since the last call, from both the server and the peer
+    fn receive(&mut self) -> Vec<(String, String)> {
+        let mut received = Vec::new();
+        for transport in [&mut self.server, &mut self.peer]
+            .iter_mut()
+            .flat_map(|t| t.as_mut())
+        {
+            while let Some(event) = transport.receive() {
+                received.push(event);
+            }
+        }
+        received
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networking::{
+        move_sync::{acknowledge, answer_move, send_tagged_move, PendingMove, ReceivedMove},
+        transport::LoopbackTransport,
+    };
+
+    /// One side of the game, sending and receiving through the same functions as Game::send_move,
+    /// Game::receive_move and Game::acknowledge_move. Received moves are added to the history
+    /// where the game would move the piece on its board.
+    struct Client {
+        connection: Networking,
+        move_history: Vec<Move>,
+        pending_move: Option<PendingMove>,
+    }
+
+    impl Client {
+        fn new(transport: LoopbackTransport) -> Client {
+            let mut connection = Networking::new(None);
+            connection.connect_peer(Box::new(transport));
+            Client {
+                connection,
+                move_history: Vec::new(),
+                pending_move: None,
+            }
+        }
+
+        fn send_move(&mut self, move_: &str) {
+            let move_ = Move::parse(move_).unwrap();
+            self.pending_move = Some(send_tagged_move(
+                &mut self.connection,
+                &self.move_history,
+                move_,
+            ));
+            self.move_history.push(move_);
+        }
+
+        fn receive_moves(&mut self) -> Vec<ReceivedMove> {
+            self.connection.poll();
+            let moves: Vec<_> = self.connection.state.incoming_moves.drain(..).collect();
+            let mut received = Vec::new();
+            for (ply, hash, move_) in moves {
+                let result =
+                    answer_move(&mut self.connection, &self.move_history, ply, hash, &move_);
+                if result == ReceivedMove::Next {
+                    self.move_history.push(move_);
+                }
+                received.push(result);
+            }
+            received
+        }
+
+        fn receive_acknowledgement(&mut self) {
+            self.connection.poll();
+            if let Some(ply) = self.connection.state.event_validation.move_ack.take() {
+                acknowledge(&mut self.pending_move, ply);
+            }
+        }
+    }
+
+    fn play(sender: &mut Client, receiver: &mut Client, move_: &str) {
+        sender.send_move(move_);
+        assert!(sender.pending_move.is_some());
+        assert_eq!(receiver.receive_moves(), vec![ReceivedMove::Next]);
+        sender.receive_acknowledgement();
+        assert!(sender.pending_move.is_none());
+    }
+
+    #[test]
+    fn plays_moves_both_ways_over_a_loopback() {
+        let (white, black) = LoopbackTransport::pair();
+        let (mut white, mut black) = (Client::new(white), Client::new(black));
+
+        // 1. e4 e5 2. Nf3
+        play(&mut white, &mut black, "Pf:w:52:36:-::-1:r");
+        play(&mut black, &mut white, "Pf:b:12:28:-::-1:r");
+        play(&mut white, &mut black, "N:w:62:45:-::-1:r");
+
+        assert_eq!(white.move_history, black.move_history);
+        assert_eq!(white.move_history.len(), 3);
+    }
+
+    #[test]
+    fn resent_moves_are_acknowledged_but_not_applied_twice() {
+        let (white, black) = LoopbackTransport::pair();
+        let (mut white, mut black) = (Client::new(white), Client::new(black));
+
+        white.send_move("Pf:w:52:36:-::-1:r");
+        assert_eq!(black.receive_moves(), vec![ReceivedMove::Next]);
+        // The acknowledgement was lost, so white sends the move again
+        white.connection.poll();
+        white.connection.state.event_validation.move_ack = None;
+        white
+            .pending_move
+            .as_mut()
+            .unwrap()
+            .resend(&mut white.connection);
+
+        assert_eq!(black.receive_moves(), vec![ReceivedMove::Duplicate]);
+        white.receive_acknowledgement();
+        assert!(white.pending_move.is_none());
+        assert_eq!(black.move_history.len(), 1);
+    }
+
+    #[test]
+    fn moves_for_another_position_are_not_acknowledged() {
+        let (white, black) = LoopbackTransport::pair();
+        let (mut white, mut black) = (Client::new(white), Client::new(black));
+
+        // Black is missing white's first move, so white's second move doesn't fit its position
+        white
+            .move_history
+            .push(Move::parse("Pf:w:52:36:-::-1:r").unwrap());
+        black
+            .move_history
+            .push(Move::parse("Pf:w:51:35:-::-1:r").unwrap());
+        black
+            .move_history
+            .push(Move::parse("Pf:b:12:28:-::-1:r").unwrap());
+        white
+            .move_history
+            .push(Move::parse("Pf:b:12:28:-::-1:r").unwrap());
+        white.send_move("N:w:62:45:-::-1:r");
+
+        assert_eq!(black.receive_moves(), vec![ReceivedMove::Desync]);
+        white.receive_acknowledgement();
+        assert!(white.pending_move.is_some());
+        assert_eq!(black.move_history.len(), 2);
+    }
 }
 
 /* pub(crate) fn establish_connection() {
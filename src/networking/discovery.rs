@@ -0,0 +1,187 @@
+use std::{
+    io,
+    net::{IpAddr, SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::{
+    time_presets::{escape_preset, unescape_preset},
+    STATE,
+};
+
+pub(crate) const DISCOVERY_PORT: u16 = 7879;
+const ANNOUNCEMENT_PREFIX: &str = "fog_of_war";
+const ANNOUNCEMENT_INTERVAL: Duration = Duration::from_secs(1);
+// A game which hasn't been announced for this long is removed from the list
+const LAN_GAME_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// An open game hosted by a client on the local network
+#[derive(Debug, Clone)]
+pub(crate) struct LanGame {
+    pub(crate) address: SocketAddr,
+    pub(crate) host_name: String,
+    pub(crate) initial_time: u64,
+    pub(crate) increment: u64,
+    pub(crate) preset: Option<String>,
+    session: u64,
+    last_seen: Instant,
+}
+
+/// Broadcasts an open game on the local network until it is dropped
+pub(crate) struct Announcer {
+    message: Arc<Mutex<String>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Announcer {
+    pub(crate) fn new(session: u64, port: u16) -> Option<Announcer> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+        socket.set_broadcast(true).ok()?;
+
+        let announcer = Announcer {
+            message: Arc::new(Mutex::new(String::from(""))),
+            closed: Arc::new(AtomicBool::new(false)),
+        };
+
+        let message = announcer.message.clone();
+        let closed = announcer.closed.clone();
+        thread::spawn(move || {
+            while !closed.load(Ordering::Relaxed) {
+                let details = message.lock().unwrap().clone();
+                if !details.is_empty() {
+                    let name = STATE.get().read().unwrap().name.replace(";", "");
+                    let announcement = format!(
+                        "{};{};{};{};{}",
+                        ANNOUNCEMENT_PREFIX, session, name, port, details
+                    );
+                    socket
+                        .send_to(announcement.as_bytes(), ("255.255.255.255", DISCOVERY_PORT))
+                        .ok();
+                }
+                thread::sleep(ANNOUNCEMENT_INTERVAL);
+            }
+        });
+
+        Some(announcer)
+    }
+
+    /// Sets the time control that is announced, in seconds, and the name of its preset
+    pub(crate) fn set_time(&mut self, initial_time: u64, increment: u64, preset: &str) {
+        *self.message.lock().unwrap() =
+            format!("{};{};{}", initial_time, increment, escape_preset(preset));
+    }
+}
+
+impl Drop for Announcer {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Binds the discovery port so that several clients on the same machine can all listen to it
+fn bind_shared(port: u16) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from(([0, 0, 0, 0], port)).into())?;
+    Ok(socket.into())
+}
+
+/// Listens for games announced on the local network and keeps STATE.lan_games up to date
+pub(crate) fn start_listener(own_session: u64) {
+    let socket = match bind_shared(DISCOVERY_PORT) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("LAN discovery disabled: {}", e);
+            return;
+        }
+    };
+    socket
+        .set_read_timeout(Some(ANNOUNCEMENT_INTERVAL))
+        .expect("Could not set discovery timeout");
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; 512];
+        loop {
+            let mut changed = false;
+
+            if let Ok((size, source)) = socket.recv_from(&mut buffer) {
+                let announcement = String::from_utf8_lossy(&buffer[..size]).to_string();
+                if let Some(game) = parse_announcement(&announcement, source.ip()) {
+                    if game.session != own_session {
+                        changed = update_lan_game(game);
+                    }
+                }
+            }
+
+            // Remove games which are no longer announced
+            let mut state = STATE.get().write().unwrap();
+            let count = state.lan_games.len();
+            state
+                .lan_games
+                .retain(|game| game.last_seen.elapsed() < LAN_GAME_TIMEOUT);
+            if changed || count != state.lan_games.len() {
+                state.lan_sync += 1;
+            }
+        }
+    });
+}
+
+/// Adds or refreshes a game, returns true if the list has to be redrawn
+fn update_lan_game(game: LanGame) -> bool {
+    let mut state = STATE.get().write().unwrap();
+    match state
+        .lan_games
+        .iter_mut()
+        .find(|existing| existing.session == game.session)
+    {
+        Some(existing) => {
+            let changed = existing.host_name != game.host_name
+                || existing.initial_time != game.initial_time
+                || existing.increment != game.increment
+                || existing.preset != game.preset
+                || existing.address != game.address;
+            *existing = game;
+            changed
+        }
+        None => {
+            state.lan_games.push(game);
+            true
+        }
+    }
+}
+
+fn parse_announcement(announcement: &str, ip: IpAddr) -> Option<LanGame> {
+    let mut split = announcement.split(';');
+    if split.next()? != ANNOUNCEMENT_PREFIX {
+        return None;
+    }
+    let session = split.next()?.parse::<u64>().ok()?;
+    let host_name = split.next()?.to_string();
+    let port = split.next()?.parse::<u16>().ok()?;
+    let initial_time = split.next()?.parse::<u64>().ok()?;
+    let increment = split.next()?.parse::<u64>().ok()?;
+    // Announcements from older clients have no preset
+    let preset = split
+        .next()
+        .filter(|preset| !preset.is_empty())
+        .map(unescape_preset);
+
+    Some(LanGame {
+        address: SocketAddr::new(ip, port),
+        host_name,
+        initial_time,
+        increment,
+        preset,
+        session,
+        last_seen: Instant::now(),
+    })
+}
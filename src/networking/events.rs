@@ -1,179 +1,230 @@
-use rust_socketio::{Payload, Socket};
+use crate::{
+    networking::{
+        connection::{ConnectionState, Room},
+        move_sync::parse_tagged_move,
+    },
+    piece::piece::PieceColor,
+    spectator::parse_spectate_move,
+    time_presets::unescape_preset,
+};
+
+// Every handler receives the payload as the JSON text it was sent as,
+// which means that string payloads still have their surrounding quotes.
+
+pub(crate) fn on_opponent(state: &mut ConnectionState, payload: String) {
+    // println!("Incoming move: {}", payload);
+    match parse_tagged_move(&payload.replace("\"", "")) {
+        Some(tagged_move) => state.incoming_moves.push(tagged_move),
+        None => eprintln!("Malformed move: {}", payload),
+    }
+}
 
-use crate::{move_struct::Move, piece::piece::PieceColor, Room, STATE};
+pub(crate) fn on_move_ack(state: &mut ConnectionState, payload: String) {
+    if let Ok(ply) = payload.replace("\"", "").parse::<usize>() {
+        state.event_validation.move_ack = Some(ply);
+    }
+}
 
-pub(crate) fn on_opponent(payload: Payload, _: Socket) {
-    let app_state = STATE.get();
+pub(crate) fn on_request_resync(state: &mut ConnectionState, _payload: String) {
+    state.event_validation.request_resync = true;
+}
 
-    match payload {
-        Payload::String(string) => {
-            // println!("Incoming move: {}", string);
-            app_state.write().unwrap().incoming_move = Some(Move::from_str(string));
-        }
-        Payload::Binary(_) => {}
-    }
+pub(crate) fn on_resync(state: &mut ConnectionState, payload: String) {
+    state.event_validation.resync = Some(payload.replace("\"", ""));
 }
 
-pub(crate) fn on_opponent_connect(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(str) => {
-            println!("opponent connect: {}", str);
-            STATE
-                .get()
-                .write()
-                .unwrap()
-                .event_validation
-                .opponent_connect = true;
+pub(crate) fn on_correspondence_sync(state: &mut ConnectionState, payload: String) {
+    state.event_validation.correspondence_sync = Some(payload.replace("\"", ""));
+}
 
-            STATE.get().write().unwrap().opponent_online = true;
-        }
-        Payload::Binary(_) => {}
-    }
+pub(crate) fn on_correspondence_games(state: &mut ConnectionState, payload: String) {
+    state.event_validation.correspondence_games = Some(payload.replace("\"", ""));
+}
+
+pub(crate) fn on_correspondence_timeout(state: &mut ConnectionState, payload: String) {
+    state.event_validation.correspondence_timeout =
+        PieceColor::from_str(&payload.replace("\"", ""));
 }
 
-pub(crate) fn on_opponent_disconnect(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(_) => {
-            STATE
-                .get()
-                .write()
-                .unwrap()
-                .event_validation
-                .opponent_disconnect = true;
+pub(crate) fn on_opponent_connect(state: &mut ConnectionState, payload: String) {
+    println!("opponent connect: {}", payload);
+    state.event_validation.opponent_connect = true;
+    state.opponent_online = true;
+}
 
-            STATE.get().write().unwrap().opponent_online = false;
-        }
-        Payload::Binary(_) => {}
-    }
+pub(crate) fn on_opponent_disconnect(state: &mut ConnectionState, _payload: String) {
+    state.event_validation.opponent_disconnect = true;
+    state.opponent_online = false;
 }
 
-pub(crate) fn on_join_room(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(str) => {
-            println!("join room: {}", str);
-            if str == "true" {
-                STATE.get().write().unwrap().event_validation.join_room = true;
-            } else {
-                // If we failed to join the lobby we remove the room id again
-                STATE.get().write().unwrap().room_id = None;
-            }
-        }
-        Payload::Binary(_) => {}
+pub(crate) fn on_join_room(state: &mut ConnectionState, payload: String) {
+    println!("join room: {}", payload);
+    if payload == "true" {
+        state.event_validation.join_room = true;
+    } else {
+        // If we failed to join the lobby we remove the room id again
+        state.room_id = None;
     }
 }
 
-pub(crate) fn on_create_room(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(str) => {
-            println!("create room: {}", str);
-            if str != "false" {
-                STATE.get().write().unwrap().event_validation.create_room = true;
-                STATE.get().write().unwrap().room_id = Some(str);
-            }
-        }
-        Payload::Binary(_) => {}
+pub(crate) fn on_create_room(state: &mut ConnectionState, payload: String) {
+    println!("create room: {}", payload);
+    if payload != "false" {
+        state.event_validation.create_room = true;
+        state.room_id = Some(payload);
     }
 }
 
-pub(crate) fn on_list_room(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(str) => {
-            println!("Got new list rooms");
-            let incoming: Vec<char> = str.chars().collect();
-            let mut rooms: Vec<Room> = Vec::new();
-
-            let mut current_id = String::from("");
-            let mut current_members = String::from("");
-            let mut id_active = true;
-
-            for i in 0..str.len() {
-                match &incoming[i].to_string()[..] {
-                    "\"" => {
-                        continue;
-                    }
-                    ":" => {
-                        id_active = false;
-                    }
-                    ";" => {
-                        rooms.push(Room {
-                            id: current_id.to_string(),
-                            members: current_members.parse::<i32>().unwrap(),
-                        });
-                        current_id = String::from("");
-                        current_members = String::from("");
-                        id_active = true;
-                    }
-                    letter => {
-                        let temp_array: Vec<char> = letter.chars().collect();
-                        if id_active {
-                            current_id.push(temp_array[0]);
+pub(crate) fn on_list_room(state: &mut ConnectionState, payload: String) {
+    println!("Got new list rooms");
+    let mut rooms: Vec<Room> = Vec::new();
+
+    let mut current_id = String::from("");
+    let mut current_members = String::from("");
+    let mut current_preset = String::from("");
+    // Each room is sent as "id:members;" or "id:members:preset;", where the preset is escaped
+    let mut field = 0;
+
+    for character in payload.chars() {
+        match character {
+            '"' => {
+                continue;
+            }
+            ':' => {
+                field += 1;
+            }
+            ';' => {
+                match current_members.parse::<i32>() {
+                    Ok(members) => rooms.push(Room {
+                        id: current_id.to_string(),
+                        members,
+                        preset: if current_preset.is_empty() {
+                            None
                         } else {
-                            current_members.push(temp_array[0]);
-                        }
-                    }
+                            Some(unescape_preset(&current_preset))
+                        },
+                    }),
+                    Err(_) => eprintln!("Malformed room: {}", current_id),
                 }
+                current_id = String::from("");
+                current_members = String::from("");
+                current_preset = String::from("");
+                field = 0;
             }
-
-            STATE.get().write().unwrap().lobbies = rooms;
-            STATE.get().write().unwrap().lobby_sync += 1;
+            letter => match field {
+                0 => current_id.push(letter),
+                1 => current_members.push(letter),
+                _ => current_preset.push(letter),
+            },
         }
-        Payload::Binary(_) => {}
     }
+
+    state.lobbies = rooms;
+    state.lobby_sync += 1;
 }
 
-pub(crate) fn on_play_again(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(_) => {
-            println!("Received play again, changing STATE!");
-            STATE.get().write().unwrap().event_validation.play_again = true;
-        }
-        Payload::Binary(_) => {}
-    }
+pub(crate) fn on_play_again(state: &mut ConnectionState, _payload: String) {
+    println!("Received play again, changing STATE!");
+    state.event_validation.play_again = true;
 }
 
-pub(crate) fn on_set_opponent_color(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(mut str) => {
-            str = str.replace("\"", "");
-            if str == "white" {
-                STATE.get().write().unwrap().event_validation.set_color = Some(PieceColor::White);
-            } else {
-                STATE.get().write().unwrap().event_validation.set_color = Some(PieceColor::Black);
-            }
-        }
-        Payload::Binary(_) => {}
+pub(crate) fn on_set_opponent_color(state: &mut ConnectionState, payload: String) {
+    if payload.replace("\"", "") == "white" {
+        state.event_validation.set_color = Some(PieceColor::White);
+    } else {
+        state.event_validation.set_color = Some(PieceColor::Black);
     }
 }
 
-pub(crate) fn on_resign(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(_) => {
-            STATE.get().write().unwrap().event_validation.resign = true;
-        }
-        Payload::Binary(_) => {}
+pub(crate) fn on_resign(state: &mut ConnectionState, _payload: String) {
+    state.event_validation.resign = true;
+}
+
+pub(crate) fn on_get_opponent_name(state: &mut ConnectionState, payload: String) {
+    state.event_validation.opponent_name = Some(payload.replace("\"", ""));
+}
+
+pub(crate) fn on_set_clock_time(state: &mut ConnectionState, payload: String) {
+    state.event_validation.time = Some(payload.replace("\"", ""));
+}
+
+pub(crate) fn on_spectate_room(state: &mut ConnectionState, payload: String) {
+    println!("spectate room: {}", payload);
+    if payload == "true" {
+        state.event_validation.spectate_room = true;
+    } else {
+        // If we failed to spectate the room we remove the room id again
+        state.room_id = None;
     }
 }
 
-pub(crate) fn on_get_opponent_name(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(opponent_name) => {
-            STATE.get().write().unwrap().event_validation.opponent_name =
-                Some(opponent_name.replace("\"", ""));
-        }
-        Payload::Binary(_) => {}
+pub(crate) fn on_spectator_connect(state: &mut ConnectionState, _payload: String) {
+    state.event_validation.spectator_connect = true;
+}
+
+pub(crate) fn on_spectate_sync(state: &mut ConnectionState, payload: String) {
+    state.event_validation.spectate_sync = Some(payload.replace("\"", ""));
+}
+
+pub(crate) fn on_spectate_move(state: &mut ConnectionState, payload: String) {
+    match parse_spectate_move(&payload.replace("\"", "")) {
+        Some(spectate_move) => state.incoming_spectator_moves.push(spectate_move),
+        None => eprintln!("Malformed move: {}", payload),
     }
 }
 
-pub(crate) fn on_set_clock_time(payload: Payload, _: Socket) {
-    match payload {
-        Payload::String(package) => {
-            let data = package.replace("\"", "");
-            let mut split = data.split(":");
-            let total_time = split.next().unwrap().parse::<u64>().unwrap();
-            let increment = split.next().unwrap().parse::<u64>().unwrap();
+/// Routes a received event to its handler
+pub(crate) fn dispatch(state: &mut ConnectionState, event: &str, payload: String) {
+    match event {
+        "join_room_res" => on_join_room(state, payload),
+        "create_room_res" => on_create_room(state, payload),
+        "opponent" => on_opponent(state, payload),
+        "move_ack" => on_move_ack(state, payload),
+        "request_resync" => on_request_resync(state, payload),
+        "resync" => on_resync(state, payload),
+        "opponent_connect" => on_opponent_connect(state, payload),
+        "opponent_disconnect" | "opponent_leave_lobby" => on_opponent_disconnect(state, payload),
+        "list_rooms" => on_list_room(state, payload),
+        "play_again" => on_play_again(state, payload),
+        "set_opponent_color" => on_set_opponent_color(state, payload),
+        "resign" => on_resign(state, payload),
+        "get_opponent_name" => on_get_opponent_name(state, payload),
+        "set_clock_time" => on_set_clock_time(state, payload),
+        "spectate_room_res" => on_spectate_room(state, payload),
+        "spectator_connect" => on_spectator_connect(state, payload),
+        "spectate_sync" => on_spectate_sync(state, payload),
+        "spectate_move" => on_spectate_move(state, payload),
+        "correspondence_sync" => on_correspondence_sync(state, payload),
+        "correspondence_games" => on_correspondence_games(state, payload),
+        "correspondence_timeout" => on_correspondence_timeout(state, payload),
+        _ => println!("Unhandled event: {}", event),
+    }
+}
 
-            STATE.get().write().unwrap().event_validation.time = Some((total_time, increment));
-        }
-        Payload::Binary(_) => {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn room_list_keeps_non_ascii_presets_and_skips_malformed_rooms() {
+        let mut state = ConnectionState::default();
+        on_list_room(
+            &mut state,
+            String::from("\"abc:1:Eget 5%3A00+0 fördröjning;def:x;ghi:2;\""),
+        );
+
+        let rooms: Vec<(&str, i32, Option<&str>)> = state
+            .lobbies
+            .iter()
+            .map(|room| (room.id.as_str(), room.members, room.preset.as_deref()))
+            .collect();
+        assert_eq!(
+            rooms,
+            vec![
+                ("abc", 1, Some("Eget 5:00+0 fördröjning")),
+                ("ghi", 2, None)
+            ]
+        );
+        assert_eq!(state.lobby_sync, 1);
     }
 }
@@ -0,0 +1,380 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    default_board_state::generate_default_board,
+    game::Game,
+    move_struct::{apply_move, white_to_move, Move},
+    networking::connection::Networking,
+    piece::piece::Board,
+    scene::Scene,
+};
+
+// A move which hasn't been acknowledged within this time is sent again
+const MOVE_RESEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A move made on this client which the opponent hasn't acknowledged yet
+pub(crate) struct PendingMove {
+    ply: usize,
+    move_: Move,
+    payload: String,
+    sent: Instant,
+}
+
+impl PendingMove {
+    /// Sends the move again with the same ply and hash
+    pub(crate) fn resend(&mut self, connection: &mut Networking) {
+        self.sent = Instant::now();
+        connection.send("opponent", &self.payload);
+    }
+}
+
+/// Hashes the position with FNV-1a, which gives the same result on every platform and build
+pub(crate) fn position_hash(board: &Board) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (index, square) in board.iter().enumerate() {
+        let description = match square {
+            Some(piece) => format!(
+                "{}{}{};",
+                index,
+                piece.piece_type.to_str(),
+                piece.color.to_str()
+            ),
+            None => continue,
+        };
+        for byte in description.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Parses an "opponent" payload, stripped of its quotes.
+/// Format: "ply|hash|move", where the hash is of the position before the move
+pub(crate) fn parse_tagged_move(data: &str) -> Option<(usize, u64, Move)> {
+    let mut split = data.splitn(3, '|');
+    let ply = split.next()?.parse::<usize>().ok()?;
+    let hash = split.next()?.parse::<u64>().ok()?;
+    let move_ = split.next()?;
+    Some((ply, hash, Move::parse(move_)?))
+}
+
+/// What to do with a move received from the opponent
+#[derive(PartialEq, Debug)]
+pub(crate) enum ReceivedMove {
+    Next,      // The move continues the game
+    Duplicate, // The move was already applied
+    Desync,    // The move is for another ply or position than ours
+}
+
+/// The position before the given ply, rebuilt from the move history
+fn position_at(move_history: &[Move], ply: usize) -> Board {
+    let mut board = generate_default_board();
+    for move_ in &move_history[..ply] {
+        apply_move(&mut board, move_);
+    }
+    board
+}
+
+/// The payload sending a move made after the given history, see parse_tagged_move
+pub(crate) fn tag_move(move_history: &[Move], move_: &Move) -> String {
+    let ply = move_history.len();
+    format!(
+        "{}|{}|{}",
+        ply,
+        position_hash(&position_at(move_history, ply)),
+        move_.to_string()
+    )
+}
+
+/// Compares a received move with the move history
+pub(crate) fn classify_move(
+    move_history: &[Move],
+    ply: usize,
+    hash: u64,
+    move_: &Move,
+) -> ReceivedMove {
+    let expected = move_history.len();
+    if ply < expected && move_history[ply].to_string() == move_.to_string() {
+        ReceivedMove::Duplicate
+    } else if ply != expected || hash != position_hash(&position_at(move_history, ply)) {
+        ReceivedMove::Desync
+    } else {
+        ReceivedMove::Next
+    }
+}
+
+/// Sends a move made after the given history, which is kept until the opponent acknowledges it
+pub(crate) fn send_tagged_move(
+    connection: &mut Networking,
+    move_history: &[Move],
+    move_: Move,
+) -> PendingMove {
+    let payload = tag_move(move_history, &move_);
+    connection.send("opponent", &payload);
+    PendingMove {
+        ply: move_history.len(),
+        move_,
+        payload,
+        sent: Instant::now(),
+    }
+}
+
+/// Acknowledges a move from the opponent unless the clients disagree on the position.
+/// A duplicate was already applied, so our acknowledgement was most likely lost.
+/// Returns what the caller has to do with the move.
+pub(crate) fn answer_move(
+    connection: &mut Networking,
+    move_history: &[Move],
+    ply: usize,
+    hash: u64,
+    move_: &Move,
+) -> ReceivedMove {
+    let received = classify_move(move_history, ply, hash, move_);
+    if received != ReceivedMove::Desync {
+        connection.send("move_ack", &ply.to_string());
+    }
+    received
+}
+
+/// Forgets the pending move once the opponent has acknowledged its ply
+pub(crate) fn acknowledge(pending_move: &mut Option<PendingMove>, ply: usize) {
+    if pending_move
+        .as_ref()
+        .is_some_and(|pending| pending.ply == ply)
+    {
+        *pending_move = None;
+    }
+}
+
+impl Game {
+    /// Sends a move made on this client. Has to be called before the move is added to the history.
+    pub(crate) fn send_move(&mut self, move_: Move) {
+        self.pending_move = Some(send_tagged_move(
+            &mut self.connection,
+            &self.move_history,
+            move_,
+        ));
+    }
+
+    /// Applies a move from the opponent if it is the next one and both clients agree on the position
+    pub(crate) fn receive_move(&mut self, ply: usize, hash: u64, move_: Move) {
+        match answer_move(&mut self.connection, &self.move_history, ply, hash, &move_) {
+            ReceivedMove::Duplicate => {}
+            ReceivedMove::Desync => {
+                println!(
+                    "Desync detected at ply {} (expected ply {}), requesting resync",
+                    ply,
+                    self.move_history.len()
+                );
+                self.request_resync();
+            }
+            ReceivedMove::Next => self.move_piece_from_board(move_),
+        }
+    }
+
+    pub(crate) fn acknowledge_move(&mut self, ply: usize) {
+        acknowledge(&mut self.pending_move, ply);
+    }
+
+    pub(crate) fn resend_unacknowledged_move(&mut self) {
+        if self.winner.is_some() {
+            return;
+        }
+        if let Some(pending) = &mut self.pending_move {
+            if pending.sent.elapsed() >= MOVE_RESEND_TIMEOUT {
+                println!(
+                    "Move {} was not acknowledged, sending it again",
+                    pending.ply
+                );
+                pending.resend(&mut self.connection);
+            }
+        }
+    }
+
+    /// The room admin's move history is the one both clients agree on after a desync,
+    /// so the admin sends it right away while the other client asks for it.
+    pub(crate) fn request_resync(&mut self) {
+        if self.is_admin {
+            self.send_resync();
+        } else {
+            self.connection.send("request_resync", "");
+        }
+    }
+
+    /// Sends the whole move history. Format: "move;move;..."
+    pub(crate) fn send_resync(&mut self) {
+        let data = self
+            .move_history
+            .iter()
+            .map(|move_| move_.to_string())
+            .collect::<Vec<String>>()
+            .join(";");
+        self.connection.send("resync", &data);
+    }
+
+    /// Rebuilds the game from the admin's move history
+    pub(crate) fn apply_resync(&mut self, data: &str) {
+        if self.is_admin {
+            return;
+        }
+        let mut moves: Vec<Move> = match data
+            .split(';')
+            .filter(|part| !part.is_empty())
+            .map(Move::parse)
+            .collect()
+        {
+            Some(moves) => moves,
+            None => {
+                eprintln!("Malformed resync: {}", data);
+                return;
+            }
+        };
+
+        // A move we sent which hasn't reached the admin yet will still be applied there when it arrives
+        if let Some(pending) = &self.pending_move {
+            if pending.ply == moves.len() {
+                moves.push(pending.move_);
+            } else {
+                self.pending_move = None;
+            }
+        }
+
+        self.board = generate_default_board();
+        for move_ in &moves {
+            apply_move(&mut self.board, move_);
+        }
+        self.move_history = moves;
+        self.grabbed_piece = None;
+        self.selected_piece = None;
+        self.premove = None;
+        self.promoting_pawn = None;
+        self.scenes.close(Scene::Promotion);
+        self.active_turn = white_to_move(self.move_history.len()) == self.playing_as_white;
+        if !self.move_history.is_empty() {
+            self.start_game();
+        }
+        self.update_available_moves();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1. e4 e5 as they are sent over the network
+    const E4: &str = "Pf:w:52:36:-::-1:r";
+    const E5: &str = "Pf:b:12:28:-::-1:r";
+
+    fn tagged(ply: usize, history: &[Move], move_: &str) -> String {
+        format!(
+            "{}|{}|{}",
+            ply,
+            position_hash(&position_at(history, ply)),
+            move_
+        )
+    }
+
+    fn history() -> Vec<Move> {
+        vec![Move::parse(E4).unwrap()]
+    }
+
+    #[test]
+    fn position_hash_depends_on_the_position() {
+        let start = generate_default_board();
+        assert_eq!(
+            position_hash(&start),
+            position_hash(&generate_default_board())
+        );
+
+        let mut after_e4 = generate_default_board();
+        apply_move(&mut after_e4, &Move::parse(E4).unwrap());
+        assert_ne!(position_hash(&start), position_hash(&after_e4));
+        assert_eq!(
+            position_hash(&after_e4),
+            position_hash(&position_at(&history(), 1))
+        );
+    }
+
+    #[test]
+    fn parses_tagged_moves() {
+        let (ply, hash, move_) = parse_tagged_move(&tagged(1, &history(), E5)).unwrap();
+        assert_eq!(ply, 1);
+        assert_eq!(hash, position_hash(&position_at(&history(), 1)));
+        assert_eq!(move_.to_string(), E5);
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        let malformed = [
+            "",
+            "1",
+            "1|2",
+            "x|2|Pf:b:12:28:-::-1:r",
+            "1|x|Pf:b:12:28:-::-1:r",
+            "1|2|",
+            "1|2|Pf:b:12:28",
+            "1|2|X:b:12:28:-::-1:r",
+            "1|2|Pf:g:12:28:-::-1:r",
+            "1|2|Pf:b:12:64:-::-1:r",
+            "1|2|Pf:b:-1:28:-::-1:r",
+            "1|2|Pf:b:12:28:-::-1:x",
+            "1|2|Pt:b:52:60:-::-1:pk",
+            "1|2|Pt:b:52:60:-::-1:pqq",
+            "1|2|Pt:b:44:53:Q:w:x:r",
+            "1|2|Pf:b:12:28:-::-1:r:r",
+        ];
+        for payload in malformed.iter() {
+            assert!(
+                parse_tagged_move(payload).is_none(),
+                "{} was accepted",
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn receives_the_next_move() {
+        let (ply, hash, move_) = parse_tagged_move(&tagged(1, &history(), E5)).unwrap();
+        assert_eq!(
+            classify_move(&history(), ply, hash, &move_),
+            ReceivedMove::Next
+        );
+    }
+
+    #[test]
+    fn ignores_duplicate_plies() {
+        let (ply, hash, move_) = parse_tagged_move(&tagged(0, &history(), E4)).unwrap();
+        assert_eq!(
+            classify_move(&history(), ply, hash, &move_),
+            ReceivedMove::Duplicate
+        );
+    }
+
+    #[test]
+    fn detects_ply_gaps() {
+        // A move for ply 2 while we are still waiting for ply 1
+        let move_ = Move::parse(E5).unwrap();
+        let hash = position_hash(&position_at(&history(), 1));
+        assert_eq!(
+            classify_move(&history(), 2, hash, &move_),
+            ReceivedMove::Desync
+        );
+
+        // A different move for a ply we already have
+        assert_eq!(
+            classify_move(&history(), 0, hash, &move_),
+            ReceivedMove::Desync
+        );
+    }
+
+    #[test]
+    fn detects_hash_mismatches() {
+        let move_ = Move::parse(E5).unwrap();
+        let start = position_hash(&generate_default_board());
+        assert_eq!(
+            classify_move(&history(), 1, start, &move_),
+            ReceivedMove::Desync
+        );
+    }
+}
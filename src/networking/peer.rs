@@ -0,0 +1,183 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde_json::json;
+
+use crate::{
+    networking::transport::{new_event_queue, EventQueue, Transport},
+    STATE,
+};
+
+pub(crate) const DEFAULT_PEER_PORT: u16 = 7878;
+// Joining is done on the UI thread, so an unreachable host must not freeze the game for long
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A direct connection to another client, used instead of the lobby server.
+/// Every message is sent as one line: the event name, a tab and the JSON encoded data,
+/// which makes the payloads look exactly like the ones received through socket.io.
+pub(crate) struct PeerConnection {
+    stream: Arc<Mutex<Option<TcpStream>>>,
+    closed: Arc<AtomicBool>,
+    received: EventQueue,
+}
+
+impl PeerConnection {
+    /// Listens for one incoming connection on the given port
+    pub(crate) fn host(port: u16) -> io::Result<PeerConnection> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+
+        let connection = PeerConnection {
+            stream: Arc::new(Mutex::new(None)),
+            closed: Arc::new(AtomicBool::new(false)),
+            received: new_event_queue(),
+        };
+
+        let stream = connection.stream.clone();
+        let closed = connection.closed.clone();
+        let received = connection.received.clone();
+        thread::spawn(move || {
+            while !closed.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((incoming, address)) => {
+                        println!("Peer connected from {}", address);
+                        if incoming.set_nonblocking(false).is_err() {
+                            continue;
+                        }
+                        *stream.lock().unwrap() = Some(incoming);
+                        PeerConnection::spawn_reader(stream.clone(), closed, received.clone());
+
+                        received.lock().unwrap().push_back((
+                            String::from("opponent_connect"),
+                            json!("peer").to_string(),
+                        ));
+                        return;
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(e) => {
+                        eprintln!("Error accepting peer: {}", e);
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(connection)
+    }
+
+    /// Connects to a client hosting a game, the address is given as "ip:port"
+    pub(crate) fn join(address: &str) -> io::Result<PeerConnection> {
+        let stream = PeerConnection::connect(address)?;
+        let connection = PeerConnection {
+            stream: Arc::new(Mutex::new(Some(stream))),
+            closed: Arc::new(AtomicBool::new(false)),
+            received: new_event_queue(),
+        };
+        PeerConnection::spawn_reader(
+            connection.stream.clone(),
+            connection.closed.clone(),
+            connection.received.clone(),
+        );
+        Ok(connection)
+    }
+
+    /// Tries every address the host name resolves to, giving up on each after CONNECT_TIMEOUT
+    fn connect(address: &str) -> io::Result<TcpStream> {
+        let mut last_error = io::Error::new(io::ErrorKind::InvalidInput, "No address found");
+        for address in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn spawn_reader(
+        stream: Arc<Mutex<Option<TcpStream>>>,
+        closed: Arc<AtomicBool>,
+        received: EventQueue,
+    ) {
+        let reader = match stream.lock().unwrap().as_ref().map(|s| s.try_clone()) {
+            Some(Ok(reader)) => reader,
+            _ => return,
+        };
+
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                let mut split = line.splitn(2, '\t');
+                let event = split.next().unwrap_or("").to_string();
+                let data = split.next().unwrap_or("\"\"").to_string();
+
+                match &event[..] {
+                    // The peer wants to know our name
+                    "request_name" => {
+                        let name = STATE.get().read().unwrap().name.clone();
+                        PeerConnection::write(&stream, "get_opponent_name", &name);
+                    }
+                    _ => received.lock().unwrap().push_back((event, data)),
+                }
+            }
+
+            // The connection was lost, unless we closed it ourselves the opponent is gone
+            if !closed.load(Ordering::Relaxed) {
+                received.lock().unwrap().push_back((
+                    String::from("opponent_disconnect"),
+                    json!("peer").to_string(),
+                ));
+            }
+        });
+    }
+
+    fn write(stream: &Arc<Mutex<Option<TcpStream>>>, event: &str, data: &str) {
+        if let Some(stream) = stream.lock().unwrap().as_mut() {
+            let line = format!("{}\t{}\n", event, json!(data));
+            if let Err(e) = stream.write_all(line.as_bytes()) {
+                eprintln!("Could not send '{}' to peer: {}", event, e);
+            }
+        }
+    }
+}
+
+impl Transport for PeerConnection {
+    fn send(&mut self, event: &str, data: &str) {
+        match event {
+            // The lobby server forwards our name to the opponent, here we do it ourselves
+            "send_name" => {
+                let name = STATE.get().read().unwrap().name.clone();
+                PeerConnection::write(&self.stream, "get_opponent_name", &name);
+            }
+            "get_opponent_name" => PeerConnection::write(&self.stream, "request_name", ""),
+            // There are no spectators in direct games
+            "spectate_sync" | "spectate_move" => {}
+            _ => PeerConnection::write(&self.stream, event, data),
+        }
+    }
+
+    fn receive(&mut self) -> Option<(String, String)> {
+        self.received.lock().unwrap().pop_front()
+    }
+}
+
+impl Drop for PeerConnection {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        if let Some(stream) = self.stream.lock().unwrap().take() {
+            stream.shutdown(Shutdown::Both).ok();
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(test)]
+use serde_json::json;
+
+/// Received events waiting to be handled by the game loop, as (event, JSON encoded data)
+pub(crate) type EventQueue = Arc<Mutex<VecDeque<(String, String)>>>;
+
+pub(crate) fn new_event_queue() -> EventQueue {
+    Arc::new(Mutex::new(VecDeque::new()))
+}
+
+/// A connection that game events can be sent through and received from.
+/// Received data is JSON encoded, just like the payloads of the socket.io client.
+pub(crate) trait Transport {
+    fn send(&mut self, event: &str, data: &str);
+
+    /// Returns the oldest received event that hasn't been handled yet
+    fn receive(&mut self) -> Option<(String, String)>;
+}
+
+/// An in-process connection, where everything sent on one end is received by the other.
+/// Lets two clients run against each other in tests without any sockets.
+#[cfg(test)]
+pub(crate) struct LoopbackTransport {
+    incoming: EventQueue,
+    outgoing: EventQueue,
+}
+
+#[cfg(test)]
+impl LoopbackTransport {
+    pub(crate) fn pair() -> (LoopbackTransport, LoopbackTransport) {
+        let (first, second) = (new_event_queue(), new_event_queue());
+        (
+            LoopbackTransport {
+                incoming: first.clone(),
+                outgoing: second.clone(),
+            },
+            LoopbackTransport {
+                incoming: second,
+                outgoing: first,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+impl Transport for LoopbackTransport {
+    fn send(&mut self, event: &str, data: &str) {
+        self.outgoing
+            .lock()
+            .unwrap()
+            .push_back((event.to_string(), json!(data).to_string()));
+    }
+
+    fn receive(&mut self) -> Option<(String, String)> {
+        self.incoming.lock().unwrap().pop_front()
+    }
+}
@@ -2,6 +2,8 @@ use ggez::graphics::Rect;
 
 use crate::{
     game::Game,
+    move_entry::same_kind,
+    move_struct::Move,
     piece::{
         piece::PieceType::*,
         piece_movement::{
@@ -10,6 +12,7 @@ use crate::{
         },
     },
     render_utilities::translate_to_coords,
+    theme::PieceSheet,
 };
 
 pub(crate) type Board = Vec<Option<Piece>>;
@@ -27,11 +30,11 @@ impl PieceColor {
             PieceColor::Black => "b",
         }
     }
-    pub(crate) fn from_str(string: &str) -> Self {
+    pub(crate) fn from_str(string: &str) -> Option<Self> {
         match string {
-            "w" => PieceColor::White,
-            "b" => PieceColor::Black,
-            _ => panic!("Error converting &str to Color"),
+            "w" => Some(PieceColor::White),
+            "b" => Some(PieceColor::Black),
+            _ => None,
         }
     }
 }
@@ -63,9 +66,9 @@ impl PieceType {
         }
     }
     /// Returns corresponding PieceType
-    pub(crate) fn from_str(string: &str) -> Self {
+    pub(crate) fn from_str(string: &str) -> Option<Self> {
         // The 't' means that its inner value is true, which is the case for all moved pieces.
-        match string {
+        Some(match string {
             "Kt" => King(true),
             "Kf" => King(false),
             "Q" => Queen,
@@ -75,8 +78,8 @@ impl PieceType {
             "N" => Knight,
             "Pt" => Pawn(true),
             "Pf" => Pawn(false),
-            _ => panic!("Recieved invalid string when converting to PieceType"),
-        }
+            _ => return None,
+        })
     }
 }
 
@@ -96,28 +99,39 @@ impl Piece {
     }
 }
 
-pub(crate) fn get_piece_rect(piece: &Piece) -> Rect {
-    let src_image_y = match piece.color {
-        PieceColor::White => 0.0,
-        PieceColor::Black => 0.5,
-    };
-    let src_image_x = match piece.piece_type {
-        PieceType::King(_) => 0.0,
-        PieceType::Queen => 1.0 / 6.0,
-        PieceType::Bishop => 2.0 / 6.0,
-        PieceType::Knight => 3.0 / 6.0,
-        PieceType::Rook(_) => 4.0 / 6.0,
-        PieceType::Pawn(_) => 5.0 / 6.0,
-    };
-
-    Rect::new(src_image_x, src_image_y, 1.0 / 6.0, 0.5)
+/// The part of the theme's sprite sheet which shows the piece
+pub(crate) fn get_piece_rect(sheet: &PieceSheet, piece: &Piece) -> Rect {
+    let column = sheet
+        .order
+        .iter()
+        .position(|piece_type| same_kind(*piece_type, piece.piece_type))
+        .unwrap_or(0);
+    let row = sheet
+        .rows
+        .iter()
+        .position(|color| *color == piece.color)
+        .unwrap_or(0);
+    let (width, height) = (
+        1.0 / sheet.order.len() as f32,
+        1.0 / sheet.rows.len() as f32,
+    );
+
+    Rect::new(column as f32 * width, row as f32 * height, width, height)
 }
 
 /// Returns the indices of the valid moves that the piece can make.
 /// If the move is a premove, it will return all indices in range with no regard to if there are pieces blocking or if certain conditions are met.
 pub(crate) fn get_valid_move_indices(game: &Game, piece: &Piece, is_premove: bool) -> Vec<usize> {
-    let board = &game.board;
+    valid_move_indices(&game.board, &game.move_history, piece, is_premove)
+}
 
+/// The valid moves of a piece on any board, e.g. an earlier position of the game
+pub(crate) fn valid_move_indices(
+    board: &Board,
+    move_history: &[Move],
+    piece: &Piece,
+    is_premove: bool,
+) -> Vec<usize> {
     // Returns a list of the valid moves
     match piece.piece_type {
         // King moves one square in any direction
@@ -140,6 +154,18 @@ pub(crate) fn get_valid_move_indices(game: &Game, piece: &Piece, is_premove: boo
         PieceType::Knight => knight_valid_moves(board, piece, is_premove),
 
         // Pawn move one square forwards, and captures one square diagonally forwards. It can move two squares forward on its first move.
-        PieceType::Pawn(_) => pawn_valid_moves(board, piece, &game.move_history, is_premove),
+        PieceType::Pawn(_) => pawn_valid_moves(board, piece, move_history, is_premove),
+    }
+}
+
+/// The tiles a player can see through the fog of war: the tiles of their pieces and the tiles those pieces can move to
+pub(crate) fn visible_tiles(board: &Board, move_history: &[Move], color: PieceColor) -> Vec<usize> {
+    let mut visible = Vec::new();
+    for piece in board.iter().flatten() {
+        if piece.color == color {
+            visible.push(piece.index);
+            visible.append(&mut valid_move_indices(board, move_history, piece, false));
+        }
     }
+    visible
 }
@@ -281,7 +281,7 @@ pub(crate) fn king_valid_moves(board: &Board, piece: &Piece, is_premove: bool) -
 pub(crate) fn pawn_valid_moves(
     board: &Board,
     piece: &Piece,
-    move_history: &Vec<Move>,
+    move_history: &[Move],
     is_premove: bool,
 ) -> Vec<usize> {
     let mut indices: Vec<usize> = Vec::new();
@@ -1,22 +1,16 @@
-use std::time::Instant;
-
-use crate::piece::piece::Piece;
-use crate::render_utilities::flip_index;
+use crate::piece::piece::{get_piece_rect, Piece};
 use crate::Game;
 
 use ggez::{
-    graphics::{
-        self, spritebatch::SpriteBatch, DrawMode, DrawParam, Image, Mesh, MeshBuilder, Rect,
-    },
+    graphics::{self, spritebatch::SpriteBatch, DrawMode, DrawParam, Image, Mesh, MeshBuilder},
     input::mouse,
     nalgebra::{Point2, Vector2},
     Context, GameResult,
 };
 
 use crate::{
-    event_handler::{BOARD_ORIGO_X, BOARD_ORIGO_Y, BOARD_WIDTH, TILE_SIZE},
     move_struct::{Move, MoveType},
-    piece::piece::PieceColor,
+    piece::piece::{PieceColor, PieceType},
     render_utilities::translate_to_coords,
 };
 
@@ -28,14 +22,9 @@ pub(crate) fn check_promotion(game: &mut Game, x_tile: usize, y_tile: usize) {
         piece_dest_index,
         captured_piece: _,
         move_type: Promotion(_),
-    }) = game.promoting_pawn.take()
+    }) = game.promoting_pawn
     {
-        let piece_dest_index = piece_dest_index.to_owned();
-        let (promotion_x, _promotion_y) = if game.playing_as_white {
-            translate_to_coords(flip_index(piece_dest_index))
-        } else {
-            translate_to_coords(piece_dest_index)
-        };
+        let (promotion_x, _promotion_y) = translate_to_coords(game.screen_index(piece_dest_index));
 
         // If clicking a tile within the promotion interface: promote to the chosen piece
         if x_tile == promotion_x && y_tile <= 3 {
@@ -46,54 +35,69 @@ pub(crate) fn check_promotion(game: &mut Game, x_tile: usize, y_tile: usize) {
                 3 => Rook(true),
                 _ => panic!("Promotion out of bounds error. This shouldn't happen."),
             };
-
-            let captured_piece = game.board[piece_dest_index].take();
-
-            // The game is over when a king is captured
-            if let Some(Piece {
-                piece_type: King(_),
-                ..
-            }) = &captured_piece
-            {
-                game.game_over(if game.playing_as_white {
-                    PieceColor::White
-                } else {
-                    PieceColor::Black
-                });
-                return;
-            }
-            game.board[piece_dest_index] = Some(Piece {
-                piece_type,
-                color: piece.color,
-                index: piece_dest_index,
-            });
-            let move_ = Move {
-                piece,
-                piece_dest_index,
-                captured_piece,
-                move_type: Promotion(piece_type),
-            };
-            game.move_history.push(move_);
-            game.connection.send("opponent", &move_.to_string());
-            game.grabbed_piece = None;
-            game.selected_piece = None;
-            println!(
-                "Grabbed: {:?}, {:?}",
-                game.grabbed_piece, game.selected_piece
-            );
-            // Your turn is over once you've made a move
-            game.active_turn = !game.active_turn;
-            game.time.turn_start = Instant::now();
-            game.update_available_moves();
+            promote(game, piece_type);
         }
         // If clicking outside the promotion interface: return the pawn to its source position.
         else {
+            game.promoting_pawn = None;
             let index = piece.get_index();
             game.board[index] = Some(piece);
         }
     }
 }
 
+/// Finishes the promotion of the promoting pawn into the given piece type
+pub(crate) fn promote(game: &mut Game, piece_type: PieceType) {
+    if let Some(Move {
+        piece,
+        piece_dest_index,
+        captured_piece: _,
+        move_type: Promotion(_),
+    }) = game.promoting_pawn.take()
+    {
+        let captured_piece = game.board[piece_dest_index].take();
+
+        // The game is over when a king is captured
+        if let Some(Piece {
+            piece_type: King(_),
+            ..
+        }) = &captured_piece
+        {
+            game.game_over(if game.playing_as_white {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            });
+            return;
+        }
+        game.board[piece_dest_index] = Some(Piece {
+            piece_type,
+            color: piece.color,
+            index: piece_dest_index,
+        });
+        let move_ = Move {
+            piece,
+            piece_dest_index,
+            captured_piece,
+            move_type: Promotion(piece_type),
+        };
+        game.play_move_sound(&move_);
+        game.send_move(move_);
+        game.perform_time_increment();
+        game.record_move(move_);
+        game.grabbed_piece = None;
+        game.selected_piece = None;
+        println!(
+            "Grabbed: {:?}, {:?}",
+            game.grabbed_piece, game.selected_piece
+        );
+        // Your turn is over once you've made a move
+        game.active_turn = !game.active_turn;
+        game.time.start_turn();
+        game.update_available_moves();
+    }
+}
+
 pub(crate) fn render_promotion_interface(game: &Game, ctx: &mut Context) -> GameResult<()> {
     // Pawn promotion menu
     if let Some(Move {
@@ -103,13 +107,9 @@ pub(crate) fn render_promotion_interface(game: &Game, ctx: &mut Context) -> Game
         move_type: MoveType::Promotion(_),
     }) = game.promoting_pawn.as_ref()
     {
-        let piece_image = Image::new(ctx, "/pieces.png")?;
-        let bounds = Rect::new_i32(
-            BOARD_ORIGO_X as i32,
-            BOARD_ORIGO_Y as i32,
-            BOARD_WIDTH,
-            BOARD_WIDTH,
-        );
+        let piece_image = Image::new(ctx, &game.theme().pieces.image)?;
+        let board = game.board_geometry;
+        let bounds = board.rect();
         let overlay = Mesh::new_rectangle(
             ctx,
             DrawMode::fill(),
@@ -117,29 +117,20 @@ pub(crate) fn render_promotion_interface(game: &Game, ctx: &mut Context) -> Game
             graphics::Color::from_rgba(240, 240, 240, 40),
         )
         .unwrap();
-        let flipped_x_index = if game.playing_as_white {
-            translate_to_coords(flip_index(*piece_dest_index)).0
-        } else {
-            translate_to_coords(*piece_dest_index).0
-        };
+        let flipped_x_index = translate_to_coords(game.screen_index(*piece_dest_index)).0;
 
         let mut promotion_prompt = MeshBuilder::new();
 
-        let image_y = if let PieceColor::White = piece.color {
-            0.0
-        } else {
-            0.5
-        };
         let mut promotion_piece_batch = SpriteBatch::new(piece_image);
-        for n in 1..=4 {
-            let src_rect = Rect::new(n as f32 / 6.0, image_y, 1.0 / 6.0, 0.5);
-            let (x, y) = ((flipped_x_index as i32) * TILE_SIZE, (n - 1) * TILE_SIZE);
-            let mut dest_rect = Rect::new_i32(
-                x + BOARD_ORIGO_X as i32,
-                y + BOARD_ORIGO_Y as i32,
-                TILE_SIZE,
-                TILE_SIZE,
+        for (n, piece_type) in (1..=4).zip([Queen, Bishop, Knight, Rook(true)]) {
+            let src_rect = get_piece_rect(
+                &game.theme().pieces,
+                &Piece {
+                    piece_type,
+                    ..*piece
+                },
             );
+            let mut dest_rect = board.tile_rect(flipped_x_index, n - 1);
             let center = Point2::new(
                 dest_rect.x + dest_rect.w / 2.0,
                 dest_rect.y + dest_rect.w / 2.0,
@@ -150,20 +141,21 @@ pub(crate) fn render_promotion_interface(game: &Game, ctx: &mut Context) -> Game
                     dest_rect,
                     graphics::Color::from_rgb(191, 43, 33),
                 );
-                Vector2::new(1.0, 1.0)
+                board.sprite_scale()
             } else {
                 let mut center_rect = dest_rect.clone();
                 center_rect.move_to(center);
                 promotion_prompt.circle(
                     DrawMode::fill(),
                     center_rect.point(),
-                    (TILE_SIZE / 2) as f32,
+                    board.tile_size / 2.0,
                     1.0,
                     graphics::Color::from_rgb(214, 214, 214),
                 );
 
-                dest_rect.translate(Vector2::new(15.0, 15.0));
-                Vector2::new(0.7, 0.7)
+                // Shrunk pieces are drawn in the middle of the circle
+                dest_rect.translate(Vector2::new(board.tile_size * 0.15, board.tile_size * 0.15));
+                board.sprite_scale() * 0.7
             };
             promotion_piece_batch.add(
                 DrawParam::default()
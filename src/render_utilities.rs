@@ -1,15 +1,113 @@
 use ggez::{
-    graphics::{self, spritebatch::SpriteBatch, DrawMode, DrawParam, Image, MeshBuilder, Rect},
+    graphics::{
+        self, spritebatch::SpriteBatch, Align, DrawMode, DrawParam, Image, MeshBuilder, Rect, Text,
+    },
     nalgebra::{Point2, Vector2},
     Context, GameResult,
 };
 
 use crate::{
-    event_handler::{BOARD_ORIGO_X, BOARD_ORIGO_Y, BOARD_SIZE, TILE_SIZE},
+    event_handler::BOARD_SIZE,
     game::Game,
+    move_entry::tile_name,
+    move_list::BoardView,
     piece::piece::{get_piece_rect, get_valid_move_indices, PieceColor},
 };
 
+// The size of one tile in the piece and marker sprite sheets
+pub(crate) const SPRITE_TILE_SIZE: f32 = 100.0;
+
+/// Where the board is drawn on screen, recomputed when the window is resized
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BoardGeometry {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) tile_size: f32,
+}
+
+impl BoardGeometry {
+    /// The largest board of whole pixel tiles which fits in the given area, centered in it
+    pub(crate) fn fit(area: Rect) -> BoardGeometry {
+        let tile_size = (f32::min(area.w, area.h) / BOARD_SIZE as f32)
+            .floor()
+            .max(1.0);
+        let width = tile_size * BOARD_SIZE as f32;
+        BoardGeometry {
+            x: (area.x + (area.w - width) / 2.0).round(),
+            y: (area.y + (area.h - width) / 2.0).round(),
+            tile_size,
+        }
+    }
+
+    pub(crate) fn width(&self) -> f32 {
+        self.tile_size * BOARD_SIZE as f32
+    }
+
+    pub(crate) fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width(), self.width())
+    }
+
+    /// The top left corner of a tile, in on screen board coordinates
+    pub(crate) fn tile_position(&self, x: usize, y: usize) -> Point2<f32> {
+        Point2::new(
+            self.x + x as f32 * self.tile_size,
+            self.y + y as f32 * self.tile_size,
+        )
+    }
+
+    pub(crate) fn tile_rect(&self, x: usize, y: usize) -> Rect {
+        let position = self.tile_position(x, y);
+        Rect::new(position.x, position.y, self.tile_size, self.tile_size)
+    }
+
+    /// Scales sprites made for SPRITE_TILE_SIZE to the tile size
+    pub(crate) fn sprite_scale(&self) -> Vector2<f32> {
+        let scale = self.tile_size / SPRITE_TILE_SIZE;
+        Vector2::new(scale, scale)
+    }
+
+    /// The tile at a point on screen, if it is on the board
+    pub(crate) fn tile_at(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        if !self.rect().contains(Point2::new(x, y)) {
+            return None;
+        }
+        Some((
+            (((x - self.x) / self.tile_size) as usize).min(BOARD_SIZE - 1),
+            (((y - self.y) / self.tile_size) as usize).min(BOARD_SIZE - 1),
+        ))
+    }
+}
+
+impl Game {
+    /// Whether White is drawn at the bottom of the board, which is the player's own side unless the view has been flipped
+    pub(crate) fn white_at_bottom(&self) -> bool {
+        self.playing_as_white != self.board_flipped
+    }
+
+    /// Translates between board indices and on screen tile indices in the current orientation, both ways
+    pub(crate) fn screen_index(&self, index: usize) -> usize {
+        if self.white_at_bottom() {
+            flip_index(index)
+        } else {
+            index
+        }
+    }
+
+    pub(crate) fn screen_pos(&self, pos: (usize, usize)) -> (usize, usize) {
+        if self.white_at_bottom() {
+            flip_pos(pos)
+        } else {
+            pos
+        }
+    }
+
+    pub(crate) fn flip_board(&mut self) {
+        self.board_flipped = !self.board_flipped;
+        self.grabbed_piece = None;
+        self.selected_piece = None;
+    }
+}
+
 pub(crate) fn flip_index(index: usize) -> usize {
     let list_size = BOARD_SIZE * BOARD_SIZE;
     return list_size - index - 1;
@@ -33,47 +131,101 @@ pub(crate) fn translate_to_coords(index: usize) -> (usize, usize) {
     return (x, y);
 }
 
-pub(crate) fn render_fog_and_pieces(game: &Game, ctx: &mut Context) -> GameResult<()> {
-    let piece_image = Image::new(ctx, "/pieces.png")?;
+/// Draws the files along the bottom edge and the ranks along the left edge of the board, in the corners of the tiles
+pub(crate) fn render_coordinates(game: &Game, ctx: &mut Context) -> GameResult<()> {
+    let geometry = game.board_geometry;
+    let size = geometry.tile_size * 0.2;
+    let margin = geometry.tile_size * 0.05;
+    let mut label = |screen_x: usize, screen_y: usize, text: String, align: Align| {
+        let tile = geometry.tile_rect(screen_x, screen_y);
+        // The label has the color of the other kind of tile
+        let color = if (screen_x + screen_y).is_multiple_of(2) {
+            game.theme().dark_tile
+        } else {
+            game.theme().light_tile
+        };
+        let mut text = Text::new(text);
+        text.set_font(game.menu.font, graphics::Scale::uniform(size));
+        text.set_bounds(Point2::new(tile.w - 2.0 * margin, size), align);
+        let y = if let Align::Left = align {
+            tile.y + margin
+        } else {
+            tile.y + tile.h - size - margin
+        };
+        graphics::draw(
+            ctx,
+            &text,
+            DrawParam::default()
+                .dest(Point2::new(tile.x + margin, y))
+                .color(color),
+        )
+    };
+
+    for screen in 0..BOARD_SIZE {
+        // Tile names such as "e4" hold both the file and the rank
+        let bottom_tile = tile_name(game.screen_index(translate_to_index(screen, BOARD_SIZE - 1)));
+        label(
+            screen,
+            BOARD_SIZE - 1,
+            bottom_tile[..1].to_string(),
+            Align::Right,
+        )?;
+
+        let left_tile = tile_name(game.screen_index(translate_to_index(0, screen)));
+        label(0, screen, left_tile[1..].to_string(), Align::Left)?;
+    }
+    Ok(())
+}
+
+// Scales the opacity of a color
+fn fade(color: graphics::Color, amount: f32) -> graphics::Color {
+    graphics::Color {
+        a: color.a * amount,
+        ..color
+    }
+}
+
+pub(crate) fn render_fog_and_pieces(
+    game: &Game,
+    view: &BoardView,
+    ctx: &mut Context,
+) -> GameResult<()> {
+    let piece_image = Image::new(ctx, &game.theme().pieces.image)?;
     let mut piece_batch = SpriteBatch::new(piece_image);
 
     let grabbed_index: Option<usize>;
     let grabbed_param: Option<DrawParam>;
 
     // Renders the grabbed piece
-    if let Some(piece) = &game.grabbed_piece {
-        let (x, y) = if game.playing_as_white {
-            flip_pos(piece.get_pos())
-        } else {
-            piece.get_pos()
-        };
+    if let (Some(piece), true) = (&game.grabbed_piece, view.live) {
+        let (x, y) = game.screen_pos(piece.get_pos());
         grabbed_index = Some(translate_to_index(x.clone(), y.clone()));
 
-        let rect = get_piece_rect(&piece);
+        let rect = get_piece_rect(&game.theme().pieces, piece);
         let (cursor_x, cursor_y) = (
             ggez::input::mouse::position(ctx).x,
             ggez::input::mouse::position(ctx).y,
         );
-        grabbed_param = Some(DrawParam::default().src(rect).dest(Point2::new(
-            cursor_x - TILE_SIZE as f32 / 2.0,
-            cursor_y - TILE_SIZE as f32 / 2.0,
-        )));
+        let tile_size = game.board_geometry.tile_size;
+        grabbed_param = Some(
+            DrawParam::default()
+                .src(rect)
+                .dest(Point2::new(
+                    cursor_x - tile_size / 2.0,
+                    cursor_y - tile_size / 2.0,
+                ))
+                .scale(game.board_geometry.sprite_scale()),
+        );
 
         // Renders a "ghost image" of the grabbed piece at its source location
-        let flipped_index = if game.playing_as_white {
-            flip_index(piece.get_index())
-        } else {
-            piece.get_index()
-        };
+        let flipped_index = game.screen_index(piece.get_index());
 
-        let rel_x = (flipped_index % BOARD_SIZE) as f32;
-        let rel_y = (flipped_index / BOARD_SIZE) as f32;
-        let abs_x = rel_x * TILE_SIZE as f32 + BOARD_ORIGO_X;
-        let abs_y = rel_y * TILE_SIZE as f32 + BOARD_ORIGO_Y;
-        let rect = get_piece_rect(piece);
+        let (x, y) = translate_to_coords(flipped_index);
+        let rect = get_piece_rect(&game.theme().pieces, piece);
         let param = DrawParam::default()
             .src(rect)
-            .dest(Point2::new(abs_x, abs_y))
+            .dest(game.board_geometry.tile_position(x, y))
+            .scale(game.board_geometry.sprite_scale())
             .color(graphics::Color::from_rgba(100, 100, 100, 100));
 
         piece_batch.add(param);
@@ -82,11 +234,11 @@ pub(crate) fn render_fog_and_pieces(game: &Game, ctx: &mut Context) -> GameResul
         grabbed_param = None;
     };
 
-    let mut hidden_tiles = MeshBuilder::new();
+    let mut hidden_tiles = Vec::new();
 
     // Render each piece in the board
-    for (index, tile) in game.board.iter().enumerate() {
-        if let Some(piece) = tile {
+    for (index, tile) in view.board.iter().enumerate() {
+        if let (Some(piece), true) = (tile, view.live) {
             // If the piece has been premoved, don't render it (it is instead rendered a bit down in this function, at the the premove destination).
             if let Some((premove_piece, _premove_dest)) = &game.premove {
                 if *piece == *premove_piece {
@@ -94,76 +246,88 @@ pub(crate) fn render_fog_and_pieces(game: &Game, ctx: &mut Context) -> GameResul
                 }
             }
         }
-        let flipped_index = if game.playing_as_white {
-            flip_index(index)
-        } else {
-            index
-        };
+        let flipped_index = game.screen_index(index);
 
-        let rel_x = (flipped_index % BOARD_SIZE) as f32;
-        let rel_y = (flipped_index / BOARD_SIZE) as f32;
-        let abs_x = rel_x * TILE_SIZE as f32 + BOARD_ORIGO_X;
-        let abs_y = rel_y * TILE_SIZE as f32 + BOARD_ORIGO_Y;
+        let (x, y) = translate_to_coords(flipped_index);
 
         // Don't draw the grabbed piece at its source index (it is drawn where it is grabbed on screen)
         if let Some(i) = &grabbed_index {
-            if game.playing_as_white {
-                if index == flip_index(*i) {
-                    continue;
-                }
-            } else {
-                if index == *i {
-                    continue;
-                }
+            if index == game.screen_index(*i) {
+                continue;
             }
         }
 
-        // Only draw the tiles which are in your vision
-        if game.available_moves.contains(&index) {
-            if let Some(piece) = tile {
-                let rect = get_piece_rect(piece);
+        // Only draw the tiles which are in your vision, tiles which have just been revealed are still partly covered
+        let visible = view.visible.contains(&index);
+        let fog_amount = if view.live {
+            game.animations.fog_amount(index, visible)
+        } else if visible {
+            0.0
+        } else {
+            1.0
+        };
+        if visible {
+            if let (Some(piece), false) = (tile, view.live && game.animations.hides_piece(index)) {
+                let rect = get_piece_rect(&game.theme().pieces, piece);
                 let param = DrawParam::default()
                     .src(rect)
-                    .dest(Point2::new(abs_x, abs_y));
+                    .dest(game.board_geometry.tile_position(x, y))
+                    .scale(game.board_geometry.sprite_scale())
+                    .color(graphics::Color::new(1.0, 1.0, 1.0, 1.0 - fog_amount));
 
                 piece_batch.add(param);
             }
         }
         // The other tiles are hidden in the fog of war
-        else {
-            let rect = Rect::new(abs_x, abs_y, TILE_SIZE as f32, TILE_SIZE as f32);
-            hidden_tiles.rectangle(
-                DrawMode::fill(),
-                rect,
-                graphics::Color::from_rgba(30, 30, 30, 240),
-            );
+        if fog_amount > 0.0 {
+            hidden_tiles.push((game.board_geometry.tile_rect(x, y), fog_amount));
         }
     }
 
     // Draws the destination of the final premove for each piece
-    if let Some((piece, index)) = &game.premove {
-        let flipped_index = if game.playing_as_white {
-            flip_index(*index)
-        } else {
-            *index
-        };
-
-        let rel_x = (flipped_index % BOARD_SIZE) as f32;
-        let rel_y = (flipped_index / BOARD_SIZE) as f32;
-        let abs_x = rel_x * TILE_SIZE as f32 + BOARD_ORIGO_X;
-        let abs_y = rel_y * TILE_SIZE as f32 + BOARD_ORIGO_Y;
+    if let (Some((piece, index)), true) = (&game.premove, view.live) {
+        let flipped_index = game.screen_index(*index);
 
-        let rect = get_piece_rect(&piece);
+        let (x, y) = translate_to_coords(flipped_index);
+        let rect = get_piece_rect(&game.theme().pieces, piece);
         let param = DrawParam::default()
             .src(rect)
-            .dest(Point2::new(abs_x, abs_y));
+            .dest(game.board_geometry.tile_position(x, y))
+            .scale(game.board_geometry.sprite_scale());
 
         piece_batch.add(param);
     }
 
-    // Draw hidden tiles (aka "fog")
-    let hidden_tiles_mesh = hidden_tiles.build(ctx)?;
-    graphics::draw(ctx, &hidden_tiles_mesh, (Point2::<f32>::new(0.0, 0.0),))?;
+    // Draw hidden tiles (aka "fog"), either in the theme's fog color or with its texture tinted in that color
+    let fog = &game.theme().fog;
+    if let Some(texture) = &fog.texture {
+        let image = Image::new(ctx, texture)?;
+        let scale = game.board_geometry.tile_size / image.width() as f32;
+        let mut fog_batch = SpriteBatch::new(image);
+        for (rect, amount) in &hidden_tiles {
+            fog_batch.add(
+                DrawParam::default()
+                    .dest(rect.point())
+                    .scale(Vector2::new(scale, scale))
+                    .color(fade(fog.color, *amount)),
+            );
+        }
+        graphics::draw(ctx, &fog_batch, (Point2::<f32>::new(0.0, 0.0),))?;
+    } else if !hidden_tiles.is_empty() {
+        let mut hidden_tiles_mesh = MeshBuilder::new();
+        for (rect, amount) in hidden_tiles {
+            hidden_tiles_mesh.rectangle(DrawMode::fill(), rect, fade(fog.color, amount));
+        }
+        let hidden_tiles_mesh = hidden_tiles_mesh.build(ctx)?;
+        graphics::draw(ctx, &hidden_tiles_mesh, (Point2::<f32>::new(0.0, 0.0),))?;
+    }
+
+    // Pieces which are moving, appearing or being captured
+    if view.live {
+        for param in game.animation_sprites() {
+            piece_batch.add(param);
+        }
+    }
 
     if let Some(param) = grabbed_param {
         piece_batch.add(param);
@@ -174,43 +338,37 @@ pub(crate) fn render_fog_and_pieces(game: &Game, ctx: &mut Context) -> GameResul
 }
 
 /// Renders highlighting for your available moves and for the prievious move (if it was visible to you)
-pub(crate) fn render_movement_indication(game: &Game, ctx: &mut Context) -> GameResult<()> {
+pub(crate) fn render_movement_indication(
+    game: &Game,
+    view: &BoardView,
+    ctx: &mut Context,
+) -> GameResult<()> {
     let is_premove = !game.active_turn;
-    let mut movement_indication_batch = SpriteBatch::new(Image::new(ctx, "/markers.png")?);
+    let mut movement_indication_batch = SpriteBatch::new(Image::new(ctx, &game.theme().markers)?);
 
     // Highlights the source- and destination tile of the previous move (if the moves are visible to you)
-    if let Some(m) = game.move_history.last() {
+    if let Some(m) = &view.last_move {
         // Source tile
-        if game.available_moves.contains(&m.piece.index) {
+        if view.visible.contains(&m.piece.index) {
             let dp_source_tile = DrawParam::default()
                 .src(Rect::new(5.0 / 6.0, 0.0, 1.0 / 6.0, 1.0))
                 .dest({
-                    let (x, y) = if game.playing_as_white {
-                        flip_pos(translate_to_coords(m.piece.index))
-                    } else {
-                        translate_to_coords(m.piece.index)
-                    };
-                    let x_pos = x as f32 * TILE_SIZE as f32 + BOARD_ORIGO_X;
-                    let y_pos = y as f32 * TILE_SIZE as f32 + BOARD_ORIGO_Y;
-                    Point2::new(x_pos, y_pos)
-                });
+                    let (x, y) = game.screen_pos(translate_to_coords(m.piece.index));
+                    game.board_geometry.tile_position(x, y)
+                })
+                .scale(game.board_geometry.sprite_scale());
             movement_indication_batch.add(dp_source_tile);
         }
 
         // Destination tile
-        if game.available_moves.contains(&m.piece_dest_index) {
+        if view.visible.contains(&m.piece_dest_index) {
             let dp_dest_tile = DrawParam::default()
                 .src(Rect::new(5.0 / 6.0, 0.0, 1.0 / 6.0, 1.0))
                 .dest({
-                    let (x, y) = if game.playing_as_white {
-                        flip_pos(translate_to_coords(m.piece_dest_index))
-                    } else {
-                        translate_to_coords(m.piece_dest_index)
-                    };
-                    let x_pos = x as f32 * TILE_SIZE as f32 + BOARD_ORIGO_X;
-                    let y_pos = y as f32 * TILE_SIZE as f32 + BOARD_ORIGO_Y;
-                    Point2::new(x_pos, y_pos)
-                });
+                    let (x, y) = game.screen_pos(translate_to_coords(m.piece_dest_index));
+                    game.board_geometry.tile_position(x, y)
+                })
+                .scale(game.board_geometry.sprite_scale());
             movement_indication_batch.add(dp_dest_tile);
         }
     }
@@ -221,30 +379,20 @@ pub(crate) fn render_movement_indication(game: &Game, ctx: &mut Context) -> Game
         let dp_source_tile = DrawParam::default()
             .src(Rect::new(4.0 / 6.0, 0.0, 1.0 / 6.0, 1.0))
             .dest({
-                let (x, y) = if game.playing_as_white {
-                    flip_pos(translate_to_coords(piece.index))
-                } else {
-                    translate_to_coords(piece.index)
-                };
-                let x_pos = x as f32 * TILE_SIZE as f32 + BOARD_ORIGO_X;
-                let y_pos = y as f32 * TILE_SIZE as f32 + BOARD_ORIGO_Y;
-                Point2::new(x_pos, y_pos)
-            });
+                let (x, y) = game.screen_pos(translate_to_coords(piece.index));
+                game.board_geometry.tile_position(x, y)
+            })
+            .scale(game.board_geometry.sprite_scale());
         movement_indication_batch.add(dp_source_tile);
 
         // Destination tile
         let dp_dest_tile = DrawParam::default()
             .src(Rect::new(4.0 / 6.0, 0.0, 1.0 / 6.0, 1.0))
             .dest({
-                let (x, y) = if game.playing_as_white {
-                    flip_pos(translate_to_coords(*piece_dest_index))
-                } else {
-                    translate_to_coords(*piece_dest_index)
-                };
-                let x_pos = x as f32 * TILE_SIZE as f32 + BOARD_ORIGO_X;
-                let y_pos = y as f32 * TILE_SIZE as f32 + BOARD_ORIGO_Y;
-                Point2::new(x_pos, y_pos)
-            });
+                let (x, y) = game.screen_pos(translate_to_coords(*piece_dest_index));
+                game.board_geometry.tile_position(x, y)
+            })
+            .scale(game.board_geometry.sprite_scale());
         movement_indication_batch.add(dp_dest_tile);
     }
 
@@ -255,27 +403,19 @@ pub(crate) fn render_movement_indication(game: &Game, ctx: &mut Context) -> Game
             ggez::input::mouse::position(ctx).y,
         );
 
-        let x_tile = ((cursor_x - BOARD_ORIGO_X) / TILE_SIZE as f32) as usize;
-        let y_tile = ((cursor_y - BOARD_ORIGO_Y) / TILE_SIZE as f32) as usize;
-
-        let mut hovered_index = translate_to_index(x_tile, y_tile);
-
-        if game.playing_as_white {
-            hovered_index = flip_index(hovered_index);
-        }
-
-        // Only highlights the square if it is a valid move
-        if get_valid_move_indices(game, piece, is_premove).contains(&hovered_index) {
-            let dest_rect = Point2::new(
-                x_tile as f32 * TILE_SIZE as f32 + BOARD_ORIGO_X,
-                y_tile as f32 * TILE_SIZE as f32 + BOARD_ORIGO_Y,
-            );
-            let src_rect = Rect::new(5.0 / 6.0, 0.0, 1.0 / 6.0, 1.0);
-            let dp = DrawParam::default()
-                .src(src_rect)
-                .dest(dest_rect)
-                .color(graphics::Color::from_rgba(100, 200, 100, 250));
-            movement_indication_batch.add(dp);
+        if let Some((x_tile, y_tile)) = game.board_geometry.tile_at(cursor_x, cursor_y) {
+            let hovered_index = game.screen_index(translate_to_index(x_tile, y_tile));
+
+            // Only highlights the square if it is a valid move
+            if get_valid_move_indices(game, piece, is_premove).contains(&hovered_index) {
+                let src_rect = Rect::new(5.0 / 6.0, 0.0, 1.0 / 6.0, 1.0);
+                let dp = DrawParam::default()
+                    .src(src_rect)
+                    .dest(game.board_geometry.tile_position(x_tile, y_tile))
+                    .scale(game.board_geometry.sprite_scale())
+                    .color(graphics::Color::from_rgba(100, 200, 100, 250));
+                movement_indication_batch.add(dp);
+            }
         }
     }
 
@@ -283,17 +423,13 @@ pub(crate) fn render_movement_indication(game: &Game, ctx: &mut Context) -> Game
     for piece in [game.grabbed_piece, game.selected_piece].iter() {
         if let Some(piece) = piece {
             for index in get_valid_move_indices(game, piece, is_premove) {
-                let (x, y) = if game.playing_as_white {
-                    flip_pos(translate_to_coords(index))
-                } else {
-                    translate_to_coords(index)
-                };
-                let x_pos = x as f32 * TILE_SIZE as f32 + BOARD_ORIGO_X + (TILE_SIZE / 2) as f32;
-                let y_pos = y as f32 * TILE_SIZE as f32 + BOARD_ORIGO_Y + (TILE_SIZE / 2) as f32;
-                let dest = Point2::new(x_pos, y_pos);
+                let (x, y) = game.screen_pos(translate_to_coords(index));
+                let tile = game.board_geometry.tile_rect(x, y);
+                let dest = Point2::new(tile.x + tile.w / 2.0, tile.y + tile.h / 2.0);
                 let mut dp = DrawParam::default()
                     .offset(Point2::new(0.5, 0.5))
-                    .dest(dest);
+                    .dest(dest)
+                    .scale(game.board_geometry.sprite_scale());
 
                 // Capture indication is displayed for premoves if the tile contains a visible piece.
                 let premove_capture = if let Some(p) = &game.board[index] {
@@ -318,7 +454,7 @@ pub(crate) fn render_movement_indication(game: &Game, ctx: &mut Context) -> Game
                         Rect::new(3.0 / 6.0, 0.0, 1.0 / 6.0, 1.0)
                     }
                 } else {
-                    dp = dp.scale(Vector2::new(0.3, 0.3));
+                    dp = dp.scale(game.board_geometry.sprite_scale() * 0.3);
                     dp.src = if !is_premove {
                         Rect::new(0.0 / 6.0, 0.0, 1.0 / 6.0, 1.0)
                     } else {
@@ -330,6 +466,15 @@ pub(crate) fn render_movement_indication(game: &Game, ctx: &mut Context) -> Game
         }
     }
 
+    // Earlier positions only show the previous move
+    if !view.live {
+        return graphics::draw(
+            ctx,
+            &movement_indication_batch,
+            (Point2::<f32>::new(0.0, 0.0),),
+        );
+    }
+
     graphics::draw(
         ctx,
         &movement_indication_batch,
@@ -0,0 +1,257 @@
+use ggez::{event::KeyCode, Context, GameResult};
+
+use crate::{
+    game::Game,
+    menu::{
+        clickable::ClickableGroup,
+        widgets::{ADDRESS_FIELD, MOVE_FIELD, NAME_FIELD},
+    },
+    piece, STATE,
+};
+
+/// The screens of the game. Only the scene on top of the stack gets input,
+/// while overlays are drawn on top of the scenes below them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Scene {
+    EnterName,
+    MainMenu,
+    DirectConnection,
+    Settings,
+    RoomSetup, // The clock screen shown before a game
+    InGame,
+    Promotion, // Choosing the piece a pawn promotes to
+    GameOver,
+}
+
+impl Scene {
+    fn is_overlay(&self) -> bool {
+        matches!(self, Scene::Promotion | Scene::GameOver)
+    }
+}
+
+pub(crate) struct SceneStack {
+    scenes: Vec<Scene>,
+}
+
+impl SceneStack {
+    pub(crate) fn new(first: Scene) -> SceneStack {
+        SceneStack {
+            scenes: vec![first],
+        }
+    }
+
+    pub(crate) fn current(&self) -> Scene {
+        *self.scenes.last().expect("The scene stack is never empty")
+    }
+
+    pub(crate) fn contains(&self, scene: Scene) -> bool {
+        self.scenes.contains(&scene)
+    }
+
+    pub(crate) fn push(&mut self, scene: Scene) {
+        if self.current() != scene {
+            self.scenes.push(scene);
+        }
+    }
+
+    /// Goes back to the previous scene, the bottom scene is never removed
+    pub(crate) fn pop(&mut self) {
+        if self.scenes.len() > 1 {
+            self.scenes.pop();
+        }
+    }
+
+    /// Removes a scene from anywhere in the stack
+    pub(crate) fn close(&mut self, scene: Scene) {
+        if self.scenes.len() > 1 {
+            self.scenes.retain(|existing| *existing != scene);
+        }
+    }
+
+    /// Closes all overlays, e.g. when a new game starts
+    pub(crate) fn close_overlays(&mut self) {
+        self.scenes.retain(|scene| !scene.is_overlay());
+    }
+
+    /// Replaces the scene on top of the stack
+    pub(crate) fn replace(&mut self, scene: Scene) {
+        self.scenes.pop();
+        self.scenes.push(scene);
+    }
+
+    /// Replaces the whole stack, e.g. when entering or leaving a room
+    pub(crate) fn reset(&mut self, scene: Scene) {
+        self.scenes = vec![scene];
+    }
+
+    /// The scenes to draw, from the bottom. An overlay is drawn together with the scenes below it.
+    pub(crate) fn visible(&self) -> Vec<Scene> {
+        let first = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        self.scenes[first..].to_vec()
+    }
+}
+
+impl Game {
+    /// The buttons which can be clicked in a scene
+    pub(crate) fn clickable_groups(&self, scene: Scene) -> Vec<ClickableGroup> {
+        match scene {
+            Scene::EnterName => vec![ClickableGroup::EnterName],
+            Scene::MainMenu => vec![ClickableGroup::MainMenu, ClickableGroup::MainMenuList],
+            Scene::DirectConnection => vec![ClickableGroup::DirectConnection],
+            Scene::Settings => vec![ClickableGroup::Settings],
+            Scene::RoomSetup => vec![ClickableGroup::TimeSelection],
+            Scene::InGame if self.spectating.is_some() => {
+                vec![ClickableGroup::Spectating, ClickableGroup::Board]
+            }
+            Scene::InGame if self.is_correspondence() => vec![
+                ClickableGroup::InGame,
+                ClickableGroup::Correspondence,
+                ClickableGroup::Board,
+            ],
+            Scene::InGame => vec![ClickableGroup::InGame, ClickableGroup::Board],
+            Scene::Promotion => Vec::new(),
+            Scene::GameOver => vec![ClickableGroup::GameOverMenu],
+        }
+    }
+
+    /// Called every update, after the network events have been handled
+    pub(crate) fn update_scene(&mut self) {
+        match self.scenes.current() {
+            // Check if lobbies have changed
+            Scene::MainMenu if self.lobby_sync != self.current_lobby_sync() => {
+                self.refresh_menu_list();
+            }
+            Scene::InGame | Scene::Promotion if self.game_active => {
+                self.check_low_time();
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn draw_scene(&mut self, ctx: &mut Context, scene: Scene) -> GameResult<()> {
+        match scene {
+            Scene::EnterName => {
+                self.menu.render_name_interface(ctx);
+                self.menu
+                    .draw_clickables(ctx, vec![ClickableGroup::EnterName]);
+            }
+            Scene::MainMenu => {
+                let background = self.theme().background;
+                self.menu.render(ctx, background)
+            }
+            Scene::DirectConnection => self.menu.render_direct_connection(ctx),
+            Scene::Settings => self.render_settings(ctx),
+            Scene::RoomSetup => self.render_time_interface(ctx),
+            Scene::InGame => self.draw_game(ctx)?,
+            Scene::Promotion => piece::promotion::render_promotion_interface(self, ctx)?,
+            Scene::GameOver => {
+                let background = self.theme().background;
+                self.menu.render_game_over(ctx, self.winner, background)
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn scene_mouse_down(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        let scene = self.scenes.current();
+        self.button_parsing(ctx, self.clickable_groups(scene));
+
+        // Left-clicking the board removes the arrows and highlights
+        if matches!(scene, Scene::InGame | Scene::GameOver)
+            && self.board_geometry.tile_at(x, y).is_some()
+        {
+            self.annotations.clear();
+        }
+
+        match scene {
+            Scene::InGame => {
+                // Clicks on the move list don't reach the board
+                let on_move_list = self.move_list_mouse_down(x, y);
+                if !on_move_list {
+                    self.board_mouse_down(ctx, x, y);
+                }
+            }
+            Scene::GameOver => {
+                self.move_list_mouse_down(x, y);
+            }
+            Scene::Settings => self.settings_mouse_down(x, y),
+            Scene::Promotion => {
+                if let Some((x_tile, y_tile)) = self.board_geometry.tile_at(x, y) {
+                    piece::promotion::check_promotion(self, x_tile, y_tile);
+                    self.scenes.close(Scene::Promotion);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn scene_mouse_up(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        if self.scenes.current() == Scene::InGame {
+            self.board_mouse_up(ctx, x, y);
+        }
+    }
+
+    /// The right button annotates the board, also when looking through a finished game
+    pub(crate) fn scene_right_mouse_down(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        if matches!(self.scenes.current(), Scene::InGame | Scene::GameOver) {
+            self.annotation_mouse_down(ctx, x, y);
+        }
+    }
+
+    pub(crate) fn scene_right_mouse_up(&mut self, x: f32, y: f32) {
+        if matches!(self.scenes.current(), Scene::InGame | Scene::GameOver) {
+            self.annotation_mouse_up(x, y);
+        }
+    }
+
+    pub(crate) fn scene_mouse_move(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        let groups = self.clickable_groups(self.scenes.current());
+        self.menu.on_mouse_move(ctx, x, y, groups);
+    }
+
+    pub(crate) fn scene_mouse_wheel(&mut self, ctx: &mut Context, y: f32) {
+        match self.scenes.current() {
+            Scene::InGame | Scene::GameOver => {
+                let position = ggez::input::mouse::position(ctx);
+                self.move_list_scroll(position.x, position.y, y);
+            }
+            _ => self.menu.on_mouse_wheel(ctx, y),
+        }
+    }
+
+    pub(crate) fn scene_text_input(&mut self, character: char) {
+        match self.scenes.current() {
+            // Name input when the game is launched
+            Scene::EnterName => {
+                NAME_FIELD.edit(&mut STATE.get().write().unwrap().name, character);
+            }
+            // Address input for direct connections
+            Scene::DirectConnection => {
+                ADDRESS_FIELD.edit(&mut STATE.get().write().unwrap().peer_address, character);
+            }
+            Scene::Settings => self.settings_text_input(character),
+            // Moves typed in algebraic notation
+            Scene::InGame if self.spectating.is_none() => {
+                MOVE_FIELD.edit(&mut self.move_entry.text, character);
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn scene_key_down(&mut self, keycode: KeyCode) {
+        match (self.scenes.current(), keycode) {
+            (Scene::InGame, KeyCode::Return) | (Scene::InGame, KeyCode::NumpadEnter) => {
+                self.submit_move_entry();
+            }
+            (Scene::InGame, KeyCode::F2) | (Scene::GameOver, KeyCode::F2) => self.flip_board(),
+            (Scene::Settings, KeyCode::Tab) => self.next_settings_field(),
+            // Earlier positions can be looked at both during and after the game
+            (Scene::InGame, _) | (Scene::GameOver, _) => self.move_list_key_down(keycode),
+            _ => {}
+        }
+    }
+}
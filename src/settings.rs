@@ -0,0 +1,435 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ggez::{graphics, Context};
+
+use crate::{
+    animation::ANIMATION_SPEEDS,
+    game::{Game, LIGHT_COLOR},
+    localization::{current_language, tr, tr_args},
+    menu::{
+        clickable::{ClickableGroup, UiAction},
+        widgets::{NAME_FIELD, SERVER_FIELD},
+    },
+    networking::connection::SERVER_URL,
+    scene::Scene,
+    sound::{SoundEvent, SOUND_EVENTS},
+    time::format_time_control,
+    time_presets::{standard_preset_key, standard_preset_name, STANDARD_PRESETS},
+    STATE,
+};
+
+// The settings are kept in a text file in the config directory, one "key=value" per line.
+// Unknown keys and values which can't be read are ignored, so an older or hand-edited file still loads.
+
+const SETTINGS_FILE: &str = "settings.txt";
+// The volume changes in steps of this size, going back to silent after full volume
+const VOLUME_STEP: f32 = 0.25;
+// The clock of a new room when no default time control is chosen
+const DEFAULT_INITIAL_TIME: u64 = 300;
+
+/// The text fields of the settings screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum SettingsField {
+    Name,
+    Server,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Settings {
+    pub(crate) name: String,
+    pub(crate) server_url: String, // Used the next time the game is started
+    pub(crate) theme: String,
+    pub(crate) volume: f32,
+    pub(crate) muted: bool,
+    pub(crate) sound_volumes: Vec<(SoundEvent, f32)>, // Multiplied with the master volume, saved as "volume_<event>"
+    pub(crate) auto_queen: bool, // Pawns are promoted to queens without showing the promotion menu
+    pub(crate) animation_speed: f32,
+    pub(crate) time_control: Option<usize>, // Standard preset a new room starts with, saved by its key
+    pub(crate) language: String,            // The code of the language, e.g. "sv"
+    pub(crate) editing: SettingsField,
+    pub(crate) edited_sound: SoundEvent, // The event whose volume is shown on the settings screen
+    path: PathBuf,
+}
+
+fn volume_label(volume: f32, muted: bool) -> String {
+    if muted {
+        tr("settings.volume_muted")
+    } else {
+        tr_args(
+            "settings.volume",
+            &[("percent", (volume * 100.0).round().to_string())],
+        )
+    }
+}
+
+// The next volume step, going back to silent after full volume
+fn next_volume(volume: f32) -> f32 {
+    let steps = (1.0 / VOLUME_STEP).round();
+    let step = (volume / VOLUME_STEP).round();
+    ((step + 1.0) % (steps + 1.0)) * VOLUME_STEP
+}
+
+fn animation_speed_label(speed: f32) -> String {
+    let name = ANIMATION_SPEEDS
+        .iter()
+        .find(|(_, s)| *s == speed)
+        .map_or("animation.custom", |(name, _)| name);
+    tr_args("settings.animations", &[("speed", tr(name))])
+}
+
+fn time_control_label(time_control: Option<usize>) -> String {
+    let clock = match time_control {
+        Some(index) => standard_preset_name(index),
+        None => format_time_control(DEFAULT_INITIAL_TIME, 0),
+    };
+    tr_args("settings.default_clock", &[("clock", clock)])
+}
+
+impl Settings {
+    pub(crate) fn load(config_dir: &Path) -> Settings {
+        let mut settings = Settings {
+            name: String::new(),
+            server_url: String::from(SERVER_URL),
+            theme: String::new(),
+            volume: 1.0,
+            muted: false,
+            sound_volumes: SOUND_EVENTS.iter().map(|event| (*event, 1.0)).collect(),
+            auto_queen: false,
+            animation_speed: 1.0,
+            time_control: None,
+            language: String::from("en"),
+            editing: SettingsField::Name,
+            edited_sound: SoundEvent::Move,
+            path: config_dir.join(SETTINGS_FILE),
+        };
+
+        let data = fs::read_to_string(&settings.path).unwrap_or_default();
+        for line in data.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "name" => settings.name = value.to_string(),
+                "server_url" if !value.is_empty() => settings.server_url = value.to_string(),
+                "theme" => settings.theme = value.to_string(),
+                "volume" => {
+                    if let Ok(volume) = value.parse::<f32>() {
+                        settings.volume = volume.clamp(0.0, 1.0);
+                    }
+                }
+                "muted" => settings.muted = value == "true",
+                _ if key.starts_with("volume_") => {
+                    let event = settings
+                        .sound_volumes
+                        .iter_mut()
+                        .find(|(event, _)| key == format!("volume_{}", event.key()));
+                    if let (Some((_, volume)), Ok(value)) = (event, value.parse::<f32>()) {
+                        *volume = value.clamp(0.0, 1.0);
+                    }
+                }
+                "auto_queen" => settings.auto_queen = value == "true",
+                "animation_speed" => {
+                    if let Ok(speed) = value.parse::<f32>() {
+                        settings.animation_speed = speed.max(0.0);
+                    }
+                }
+                "language" => settings.language = value.to_string(),
+                "time_control" => {
+                    settings.time_control =
+                        (0..STANDARD_PRESETS.len()).find(|i| standard_preset_key(*i) == value);
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    pub(crate) fn save(&self) {
+        let mut data = vec![
+            format!("name={}", self.name),
+            format!("server_url={}", self.server_url),
+            format!("theme={}", self.theme),
+            format!("volume={}", self.volume),
+            format!("muted={}", self.muted),
+            format!("auto_queen={}", self.auto_queen),
+            format!("animation_speed={}", self.animation_speed),
+            format!("language={}", self.language),
+            format!(
+                "time_control={}",
+                self.time_control
+                    .map(standard_preset_key)
+                    .unwrap_or_default()
+            ),
+        ];
+        for (event, volume) in &self.sound_volumes {
+            data.push(format!("volume_{}={}", event.key(), volume));
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).ok();
+        }
+        if let Err(e) = fs::write(&self.path, data.join("\n")) {
+            eprintln!("Could not save settings: {}", e);
+        }
+    }
+
+    /// The volume of an event relative to the master volume
+    pub(crate) fn event_volume(&self, event: SoundEvent) -> f32 {
+        self.sound_volumes
+            .iter()
+            .find(|(e, _)| *e == event)
+            .map_or(1.0, |(_, volume)| *volume)
+    }
+
+    /// The volume an event is played at
+    pub(crate) fn sound_volume(&self, event: SoundEvent) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume * self.event_volume(event)
+        }
+    }
+}
+
+impl Game {
+    /// Applies the loaded settings to the parts of the game they belong to, called at startup
+    pub(crate) fn apply_settings(&mut self, ctx: &mut Context) {
+        STATE.get().write().unwrap().name = self.settings.name.clone();
+        if self.themes.select(&self.settings.theme) {
+            self.board_mesh =
+                Game::get_board_mesh(ctx, self.board_geometry.tile_size, self.themes.current());
+        }
+        self.animations.speed = self.settings.animation_speed;
+        self.update_settings_labels();
+    }
+
+    /// Saves the settings, called whenever one of them is changed
+    pub(crate) fn save_settings(&mut self) {
+        self.settings.theme = self.theme().name.clone();
+        self.settings.animation_speed = self.animations.speed;
+        self.settings.save();
+        self.update_settings_labels();
+    }
+
+    fn update_settings_labels(&mut self) {
+        let labels = [
+            (
+                UiAction::CycleTheme,
+                tr_args("settings.theme", &[("theme", self.theme().name.clone())]),
+            ),
+            (
+                UiAction::CycleAnimationSpeed,
+                animation_speed_label(self.animations.speed),
+            ),
+            (
+                UiAction::CycleVolume,
+                volume_label(self.settings.volume, self.settings.muted),
+            ),
+            (
+                UiAction::ToggleMute,
+                tr(["settings.mute", "settings.unmute"][self.settings.muted as usize]),
+            ),
+            (
+                UiAction::CycleEditedSound,
+                tr_args(
+                    "settings.sound",
+                    &[("sound", self.settings.edited_sound.label())],
+                ),
+            ),
+            (
+                UiAction::CycleSoundVolume,
+                tr_args(
+                    "settings.sound_volume",
+                    &[
+                        ("sound", self.settings.edited_sound.label()),
+                        (
+                            "percent",
+                            (self.settings.event_volume(self.settings.edited_sound) * 100.0)
+                                .round()
+                                .to_string(),
+                        ),
+                    ],
+                ),
+            ),
+            (
+                UiAction::ToggleAutoQueen,
+                tr(["settings.auto_queen_off", "settings.auto_queen_on"]
+                    [self.settings.auto_queen as usize]),
+            ),
+            (
+                UiAction::CycleDefaultTimeControl,
+                time_control_label(self.settings.time_control),
+            ),
+            (
+                UiAction::CycleLanguage,
+                tr_args("settings.language", &[("language", current_language().1)]),
+            ),
+        ];
+        for (action, text) in labels {
+            self.menu.set_text(&action, text);
+        }
+    }
+
+    pub(crate) fn open_settings(&mut self) {
+        self.settings.editing = SettingsField::Name;
+        self.scenes.push(Scene::Settings);
+    }
+
+    /// Leaves the settings screen, the name is sent to the server if it was changed
+    pub(crate) fn close_settings(&mut self) {
+        let name = STATE.get().read().unwrap().name.clone();
+        if name.is_empty() {
+            // A player always has a name, so an emptied field goes back to the saved one
+            STATE.get().write().unwrap().name = self.settings.name.clone();
+        } else if name != self.settings.name {
+            self.settings.name = name.clone();
+            self.connection.send("set_name", &name);
+        }
+        if self.settings.server_url.is_empty() {
+            self.settings.server_url = String::from(SERVER_URL);
+        }
+        self.save_settings();
+        self.scenes.pop();
+    }
+
+    pub(crate) fn cycle_volume(&mut self) {
+        self.settings.volume = next_volume(self.settings.volume);
+        self.settings.muted = false;
+        self.save_settings();
+    }
+
+    pub(crate) fn toggle_mute(&mut self) {
+        self.settings.muted = !self.settings.muted;
+        self.save_settings();
+    }
+
+    /// Chooses which event's volume is changed on the settings screen
+    pub(crate) fn cycle_edited_sound(&mut self) {
+        let current = SOUND_EVENTS
+            .iter()
+            .position(|event| *event == self.settings.edited_sound)
+            .unwrap_or(0);
+        self.settings.edited_sound = SOUND_EVENTS[(current + 1) % SOUND_EVENTS.len()];
+        self.update_settings_labels();
+    }
+
+    /// Changes the volume of the chosen event and plays it, so the new volume can be heard
+    pub(crate) fn cycle_sound_volume(&mut self) {
+        let event = self.settings.edited_sound;
+        if let Some((_, volume)) = self
+            .settings
+            .sound_volumes
+            .iter_mut()
+            .find(|(e, _)| *e == event)
+        {
+            *volume = next_volume(*volume);
+        }
+        self.save_settings();
+        self.play_sound(event);
+    }
+
+    pub(crate) fn toggle_auto_queen(&mut self) {
+        self.settings.auto_queen = !self.settings.auto_queen;
+        self.save_settings();
+    }
+
+    /// Switches between the standard presets and the built-in clock of a new room
+    pub(crate) fn cycle_default_time_control(&mut self) {
+        self.settings.time_control = match self.settings.time_control {
+            None => Some(0),
+            Some(index) if index + 1 < STANDARD_PRESETS.len() => Some(index + 1),
+            Some(_) => None,
+        };
+        self.save_settings();
+    }
+
+    /// Sets the clock of a newly created room to the player's default time control
+    pub(crate) fn apply_default_time_control(&mut self) {
+        if let Some(index) = self.settings.time_control {
+            self.select_standard_preset(index);
+        }
+    }
+
+    /// Clicking a text field starts editing it
+    pub(crate) fn settings_mouse_down(&mut self, x: f32, y: f32) {
+        for (name, field) in [
+            ("settings_name", SettingsField::Name),
+            ("settings_server", SettingsField::Server),
+        ] {
+            if self.menu.layout.rect(name).contains([x, y]) {
+                self.settings.editing = field;
+            }
+        }
+    }
+
+    pub(crate) fn settings_text_input(&mut self, character: char) {
+        match self.settings.editing {
+            SettingsField::Name => {
+                NAME_FIELD.edit(&mut STATE.get().write().unwrap().name, character);
+            }
+            SettingsField::Server => SERVER_FIELD.edit(&mut self.settings.server_url, character),
+        }
+    }
+
+    /// Tab switches between the text fields
+    pub(crate) fn next_settings_field(&mut self) {
+        self.settings.editing = match self.settings.editing {
+            SettingsField::Name => SettingsField::Server,
+            SettingsField::Server => SettingsField::Name,
+        };
+    }
+
+    pub(crate) fn render_settings(&mut self, ctx: &mut Context) {
+        let title = self.menu.layout.rect("settings_title");
+        self.menu.draw_text(
+            ctx,
+            tr("settings.title"),
+            (title.x, title.y),
+            (title.w, title.h),
+            graphics::Color::from(LIGHT_COLOR),
+            graphics::Align::Center,
+        );
+
+        // The field being edited is shown with a cursor
+        let cursor = |field: SettingsField| {
+            if self.settings.editing == field {
+                "|"
+            } else {
+                ""
+            }
+        };
+        let name = format!(
+            "{}{}",
+            STATE.get().read().unwrap().name,
+            cursor(SettingsField::Name)
+        );
+        let server = format!(
+            "{}{}",
+            self.settings.server_url,
+            cursor(SettingsField::Server)
+        );
+        let (name_field, server_field) = (
+            self.menu.layout.rect("settings_name"),
+            self.menu.layout.rect("settings_server"),
+        );
+        NAME_FIELD.draw(&mut self.menu, ctx, &name, name_field);
+        SERVER_FIELD.draw(&mut self.menu, ctx, &server, server_field);
+
+        let note = self.menu.layout.rect("settings_note");
+        self.menu.draw_text(
+            ctx,
+            tr("settings.server_note"),
+            (note.x, note.y),
+            (note.w, note.h),
+            graphics::Color::from(LIGHT_COLOR),
+            graphics::Align::Left,
+        );
+
+        self.menu
+            .draw_clickables(ctx, vec![ClickableGroup::Settings]);
+    }
+}
@@ -0,0 +1,189 @@
+use ggez::{
+    audio::{SoundSource, Source},
+    Context,
+};
+
+use crate::{
+    game::Game,
+    localization::tr,
+    move_struct::{Move, MoveType},
+    piece::piece::{valid_move_indices, Piece, PieceType},
+};
+
+// Every sound is played at the master volume times the volume of its event, and not at all when muted.
+// There are only a few sound files, so several events share a file played at another pitch.
+// The game is playable without sound: events which could not be loaded are skipped, e.g. when there is no audio device.
+
+/// The things the game plays a sound for
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum SoundEvent {
+    Move,
+    Capture,
+    Castle,
+    KingThreat, // A king can be captured by a piece the player can see
+    GameEnd,
+    LowTime,
+    IllegalMove,
+    OpponentConnected,
+    OpponentDisconnected,
+    EnemySpotted, // An enemy piece comes into the player's vision
+}
+
+pub(crate) const SOUND_EVENTS: [SoundEvent; 10] = [
+    SoundEvent::Move,
+    SoundEvent::Capture,
+    SoundEvent::Castle,
+    SoundEvent::KingThreat,
+    SoundEvent::GameEnd,
+    SoundEvent::LowTime,
+    SoundEvent::IllegalMove,
+    SoundEvent::OpponentConnected,
+    SoundEvent::OpponentDisconnected,
+    SoundEvent::EnemySpotted,
+];
+
+impl SoundEvent {
+    /// The name of the event in the settings file
+    pub(crate) fn key(&self) -> &'static str {
+        match self {
+            SoundEvent::Move => "move",
+            SoundEvent::Capture => "capture",
+            SoundEvent::Castle => "castle",
+            SoundEvent::KingThreat => "king_threat",
+            SoundEvent::GameEnd => "game_end",
+            SoundEvent::LowTime => "low_time",
+            SoundEvent::IllegalMove => "illegal_move",
+            SoundEvent::OpponentConnected => "opponent_connected",
+            SoundEvent::OpponentDisconnected => "opponent_disconnected",
+            SoundEvent::EnemySpotted => "enemy_spotted",
+        }
+    }
+
+    pub(crate) fn label(&self) -> String {
+        tr(&format!("sound.{}", self.key()))
+    }
+
+    // The sound file and the pitch it is played at
+    fn sound(&self) -> (&'static str, f32) {
+        match self {
+            SoundEvent::Move => ("/move.ogg", 1.0),
+            SoundEvent::Capture => ("/capture.ogg", 1.0),
+            SoundEvent::Castle => ("/move.ogg", 0.8),
+            SoundEvent::KingThreat => ("/capture.ogg", 1.4),
+            SoundEvent::GameEnd => ("/game_end.ogg", 1.0),
+            SoundEvent::LowTime => ("/game_end.ogg", 1.5),
+            SoundEvent::IllegalMove => ("/move.ogg", 0.6),
+            SoundEvent::OpponentConnected => ("/game_end.ogg", 1.25),
+            SoundEvent::OpponentDisconnected => ("/game_end.ogg", 0.75),
+            SoundEvent::EnemySpotted => ("/capture.ogg", 0.7),
+        }
+    }
+}
+
+pub(crate) struct Sound {
+    sources: Vec<(SoundEvent, Source)>,
+    pub(crate) king_threatened: bool, // Whether a king was threatened after the last move, so the sound is only played once
+}
+
+impl Sound {
+    /// Loads the sound of every event. Without an audio device no sounds are loaded, since ggez can't create sources then.
+    pub(crate) fn load(ctx: &mut Context, audio: bool) -> Sound {
+        let mut sources = Vec::new();
+        if audio {
+            for event in SOUND_EVENTS.iter() {
+                let (file, pitch) = event.sound();
+                match Source::new(ctx, file) {
+                    Ok(mut source) => {
+                        source.set_pitch(pitch);
+                        sources.push((*event, source));
+                    }
+                    Err(e) => eprintln!("Could not load the {} sound: {}", event.label(), e),
+                }
+            }
+        } else {
+            eprintln!("No audio device found, the game is played without sound");
+        }
+        Sound {
+            sources,
+            king_threatened: false,
+        }
+    }
+
+    fn play(&mut self, event: SoundEvent, volume: f32) {
+        if let Some((_, source)) = self.sources.iter_mut().find(|(e, _)| *e == event) {
+            source.set_volume(volume);
+            if let Err(e) = source.play() {
+                eprintln!("Could not play the {} sound: {}", event.label(), e);
+            }
+        }
+    }
+}
+
+impl Game {
+    pub(crate) fn play_sound(&mut self, event: SoundEvent) {
+        let volume = self.settings.sound_volume(event);
+        if volume > 0.0 {
+            self.sound.play(event, volume);
+        }
+    }
+
+    pub(crate) fn play_move_sound(&mut self, move_: &Move) {
+        let event = if move_.captured_piece.is_some() {
+            SoundEvent::Capture
+        } else if let MoveType::Castle = move_.move_type {
+            SoundEvent::Castle
+        } else {
+            SoundEvent::Move
+        };
+        self.play_sound(event);
+    }
+
+    // Whether a piece the player can see is able to capture a king, their own or the opponent's
+    fn king_threatened(&self) -> bool {
+        let pieces: Vec<&Piece> = self
+            .board
+            .iter()
+            .flatten()
+            .filter(|piece| self.available_moves.contains(&piece.index))
+            .collect();
+        pieces.iter().any(|attacker| {
+            valid_move_indices(&self.board, &self.move_history, attacker, false)
+                .iter()
+                .any(|index| {
+                    pieces.iter().any(|king| {
+                        king.index == *index
+                            && king.color != attacker.color
+                            && matches!(king.piece_type, PieceType::King(_))
+                    })
+                })
+        })
+    }
+
+    /// Plays the sounds for what the player's vision shows after it has changed:
+    /// a king coming under threat, or enemy pieces on tiles which were hidden before
+    pub(crate) fn vision_sounds(&mut self, previous_vision: &[usize]) {
+        if self.spectating.is_some() || !self.game_active {
+            return;
+        }
+
+        let threatened = self.king_threatened();
+        if threatened && !self.sound.king_threatened {
+            self.play_sound(SoundEvent::KingThreat);
+        }
+        self.sound.king_threatened = threatened;
+
+        // Nothing is spotted in the first position of a game
+        if previous_vision.is_empty() {
+            return;
+        }
+        let own_color = self.own_color();
+        let spotted = self.board.iter().flatten().any(|piece| {
+            piece.color != own_color
+                && self.available_moves.contains(&piece.index)
+                && !previous_vision.contains(&piece.index)
+        });
+        if spotted {
+            self.play_sound(SoundEvent::EnemySpotted);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    game::Game,
+    localization::{tr, tr_args},
+    move_struct::{apply_move, white_to_move, Move},
+    STATE,
+};
+
+/// Which side of the board a spectator is watching from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Perspective {
+    White, // White's fog of war
+    Black, // Black's fog of war
+    Full,  // The whole board without any fog
+}
+
+pub(crate) struct Spectator {
+    pub(crate) perspective: Perspective,
+    pub(crate) white_name: String,
+    pub(crate) black_name: String,
+}
+
+/// White's and Black's time left
+pub(crate) type Clocks = (Duration, Duration);
+
+/// Holds back the moves of the game until the broadcast delay has passed,
+/// so that spectators can't relay hidden information to a player.
+/// Only the room admin broadcasts, since it sees the moves of both players.
+/// The clocks are held back as well, since the time used on a move tells when it was made.
+pub(crate) struct Broadcast {
+    pub(crate) delay: Duration,
+    queue: Vec<(Instant, Move, Clocks)>, // The clocks right after each move
+    shown_clocks: Option<Clocks>,        // The clocks of the last released move
+}
+
+impl Broadcast {
+    pub(crate) fn new() -> Broadcast {
+        Broadcast {
+            delay: Duration::from_secs(0),
+            queue: Vec::new(),
+            shown_clocks: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, move_: Move, clocks: Clocks) {
+        self.queue.push((Instant::now(), move_, clocks));
+    }
+
+    /// Returns the moves whose delay has passed, in the order they were made
+    pub(crate) fn release(&mut self) -> Vec<(Move, Clocks)> {
+        let delay = self.delay;
+        let due = self
+            .queue
+            .iter()
+            .take_while(|(made, _, _)| made.elapsed() >= delay)
+            .count();
+        let released: Vec<(Move, Clocks)> = self
+            .queue
+            .drain(..due)
+            .map(|(_, move_, clocks)| (move_, clocks))
+            .collect();
+        if let Some((_, clocks)) = released.last() {
+            self.shown_clocks = Some(*clocks);
+        }
+        released
+    }
+
+    /// Number of moves that have been made but not yet shown to spectators
+    pub(crate) fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.queue.clear();
+        self.shown_clocks = None;
+    }
+}
+
+/// Parses a "spectate_move" payload, stripped of its quotes.
+/// Format: "white_left:black_left;move", with the times in seconds
+pub(crate) fn parse_spectate_move(data: &str) -> Option<(Move, Clocks)> {
+    let (clocks, move_) = data.split_once(';')?;
+    let (white_left, black_left) = clocks.split_once(':')?;
+    let clocks = (
+        Duration::from_secs(white_left.parse::<u64>().ok()?),
+        Duration::from_secs(black_left.parse::<u64>().ok()?),
+    );
+    Some((Move::parse(move_)?, clocks))
+}
+
+/// The available broadcast delays, in seconds, cycled through on the clock screen
+pub(crate) const SPECTATOR_DELAYS: [u64; 5] = [0, 15, 30, 60, 120];
+
+pub(crate) fn delay_label(delay: Duration) -> String {
+    if delay.as_secs() == 0 {
+        tr("clock.spectator_delay_off")
+    } else {
+        tr_args(
+            "clock.spectator_delay",
+            &[("seconds", delay.as_secs().to_string())],
+        )
+    }
+}
+
+impl Game {
+    pub(crate) fn set_perspective(&mut self, perspective: Perspective) {
+        let spectator = match &mut self.spectating {
+            Some(spectator) => spectator,
+            None => return,
+        };
+        spectator.perspective = perspective;
+
+        // The board is shown from White's side unless watching Black's fog of war
+        let as_white = perspective != Perspective::Black;
+        if as_white != self.playing_as_white {
+            std::mem::swap(
+                &mut self.time.current_time_left,
+                &mut self.time.opponent_time_left,
+            );
+        }
+        self.playing_as_white = as_white;
+        // The "active" turn is the turn of the side at the bottom of the board
+        self.active_turn = white_to_move(self.move_history.len()) == self.playing_as_white;
+        self.update_available_moves();
+    }
+
+    /// The time left of White and Black
+    pub(crate) fn clocks(&self) -> Clocks {
+        if self.playing_as_white {
+            (self.time.current_time_left, self.time.opponent_time_left)
+        } else {
+            (self.time.opponent_time_left, self.time.current_time_left)
+        }
+    }
+
+    fn set_clocks(&mut self, (white_left, black_left): Clocks) {
+        if self.playing_as_white {
+            self.time.current_time_left = white_left;
+            self.time.opponent_time_left = black_left;
+        } else {
+            self.time.current_time_left = black_left;
+            self.time.opponent_time_left = white_left;
+        }
+    }
+
+    /// Sends a move which has passed the broadcast delay to the spectators
+    pub(crate) fn send_spectate_move(&mut self, move_: Move, (white_left, black_left): Clocks) {
+        let data = format!(
+            "{}:{};{}",
+            white_left.as_secs(),
+            black_left.as_secs(),
+            move_.to_string()
+        );
+        self.connection.send("spectate_move", &data);
+    }
+
+    /// Shows a released move of the spectated game. The move is applied without the side effects
+    /// of playing it, and the clocks are set to the times recorded when it was made.
+    pub(crate) fn apply_spectate_move(&mut self, move_: Move, clocks: Clocks) {
+        if self.board[move_.piece.index].is_none() {
+            eprintln!("Spectated move from an empty tile: {}", move_.to_string());
+            return;
+        }
+        apply_move(&mut self.board, &move_);
+        self.play_move_sound(&move_);
+        self.move_history.push(move_);
+        self.set_clocks(clocks);
+        self.time.start_turn();
+        self.game_active = self.winner.is_none();
+        self.active_turn = white_to_move(self.move_history.len()) == self.playing_as_white;
+        self.update_available_moves();
+    }
+
+    /// Sends the released part of the game to the spectators of the room, with the clocks as they were
+    /// after the last released move.
+    /// Format: "white_name;black_name;white_left:black_left:time_control;move;move;..."
+    pub(crate) fn send_spectate_sync(&mut self) {
+        let own_name = STATE.get().read().unwrap().name.replace(";", "");
+        let opponent_name = self
+            .connection
+            .state
+            .event_validation
+            .opponent_name
+            .clone()
+            .unwrap_or(tr("game.awaiting_player"))
+            .replace(";", "");
+
+        let (white_name, black_name) = if self.playing_as_white {
+            (own_name, opponent_name)
+        } else {
+            (opponent_name, own_name)
+        };
+        // Before the first released move both clocks still show their initial time
+        let (white_left, black_left) = if self.broadcast.pending() == 0 {
+            self.clocks()
+        } else {
+            self.broadcast
+                .shown_clocks
+                .unwrap_or((self.time.side(true).0, self.time.side(false).0))
+        };
+
+        let mut data = format!(
+            "{};{};{}:{}:{}",
+            white_name,
+            black_name,
+            white_left.as_secs(),
+            black_left.as_secs(),
+            self.time.control_string()
+        );
+
+        // Moves which are still delayed are left out, they arrive later through "spectate_move"
+        let released = self.move_history.len() - self.broadcast.pending();
+        for move_ in &self.move_history[..released] {
+            data.push(';');
+            data.push_str(&move_.to_string());
+        }
+        self.connection.send("spectate_sync", &data);
+    }
+
+    /// Rebuilds the spectated game from a "spectate_sync" payload
+    pub(crate) fn apply_spectate_sync(&mut self, data: &str) {
+        let mut parts = data.split(';');
+        let white_name = parts.next().map_or(tr("color.white"), String::from);
+        let black_name = parts.next().map_or(tr("color.black"), String::from);
+        let mut clock = parts.next().unwrap_or("").splitn(3, ':');
+        let white_left = clock.next().and_then(|value| value.parse::<u64>().ok());
+        let black_left = clock.next().and_then(|value| value.parse::<u64>().ok());
+        let time_control = clock.next();
+
+        let perspective = match &self.spectating {
+            Some(spectator) => spectator.perspective,
+            None => return,
+        };
+        self.spectating = Some(Spectator {
+            perspective,
+            white_name,
+            black_name,
+        });
+
+        let moves: Option<Vec<Move>> = parts
+            .filter(|part| !part.is_empty())
+            .map(Move::parse)
+            .collect();
+        let moves = match moves {
+            Some(moves) => moves,
+            None => {
+                eprintln!("Malformed spectate sync: {}", data);
+                return;
+            }
+        };
+
+        self.reset_game();
+        for move_ in moves {
+            apply_move(&mut self.board, &move_);
+            self.move_history.push(move_);
+        }
+
+        if let (Some(white_left), Some(black_left), Some(time_control)) =
+            (white_left, black_left, time_control)
+        {
+            self.time.apply_control_string(time_control);
+            self.set_clocks((
+                Duration::from_secs(white_left),
+                Duration::from_secs(black_left),
+            ));
+        }
+        self.game_active = !self.move_history.is_empty();
+        self.active_turn = white_to_move(self.move_history.len()) == self.playing_as_white;
+        self.update_available_moves();
+    }
+}
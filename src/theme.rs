@@ -0,0 +1,235 @@
+use std::{fs, path::Path};
+
+use ggez::{graphics::Color, Context};
+use serde_json::Value;
+
+use crate::{
+    game::Game,
+    piece::piece::{PieceColor, PieceType},
+};
+
+// Themes decide how the board is drawn: the tile and background colors, the fog of war and the sprite sheets.
+// The built-in themes are read from the resources, and users can add their own as json files in
+// the "themes" folder of the config directory. Images are looked up like other resources,
+// and a user's theme is skipped if one of its images doesn't exist.
+
+const DEFAULT_THEMES: &str = include_str!("../resources/themes.json");
+const THEME_DIR: &str = "themes";
+
+/// How the tiles outside of your vision are covered
+#[derive(Clone, Debug)]
+pub(crate) struct Fog {
+    pub(crate) color: Color,            // The alpha is the opacity of the fog
+    pub(crate) texture: Option<String>, // Drawn on every hidden tile, tinted with the color
+}
+
+/// A sprite sheet of pieces, where every column holds one piece type and every row one color
+#[derive(Clone, Debug)]
+pub(crate) struct PieceSheet {
+    pub(crate) image: String,
+    pub(crate) order: Vec<PieceType>,
+    pub(crate) rows: Vec<PieceColor>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Theme {
+    pub(crate) name: String,
+    pub(crate) light_tile: Color,
+    pub(crate) dark_tile: Color,
+    pub(crate) background: Color,
+    pub(crate) fog: Fog,
+    pub(crate) pieces: PieceSheet,
+    pub(crate) markers: String, // Sprite sheet of the move markers, laid out like markers.png
+}
+
+fn parse_color(value: &Value, opacity: f64) -> Result<Color, String> {
+    match value.as_array().map(|rgb| rgb.as_slice()) {
+        Some([r, g, b]) => {
+            let channel = |value: &Value| {
+                value
+                    .as_u64()
+                    .filter(|channel| *channel <= 255)
+                    .map(|channel| channel as u8)
+                    .ok_or(format!("Invalid color channel {}", value))
+            };
+            Ok(Color::from_rgba(
+                channel(r)?,
+                channel(g)?,
+                channel(b)?,
+                (opacity.clamp(0.0, 1.0) * 255.0) as u8,
+            ))
+        }
+        _ => Err(format!("Invalid color {}, expected [r, g, b]", value)),
+    }
+}
+
+fn parse_string(value: &Value, key: &str) -> Result<String, String> {
+    value[key]
+        .as_str()
+        .map(String::from)
+        .ok_or(format!("Theme has no {}", key))
+}
+
+fn piece_from_letter(letter: &str) -> Result<PieceType, String> {
+    match letter {
+        "K" => Ok(PieceType::King(false)),
+        "Q" => Ok(PieceType::Queen),
+        "R" => Ok(PieceType::Rook(false)),
+        "B" => Ok(PieceType::Bishop),
+        "N" => Ok(PieceType::Knight),
+        "P" => Ok(PieceType::Pawn(false)),
+        _ => Err(format!("Invalid piece {}", letter)),
+    }
+}
+
+fn parse_list<T>(value: &Value, parse: fn(&str) -> Result<T, String>) -> Result<Vec<T>, String> {
+    value
+        .as_array()
+        .ok_or(format!("Expected a list, found {}", value))?
+        .iter()
+        .map(|item| parse(item.as_str().unwrap_or_default()))
+        .collect()
+}
+
+impl PieceSheet {
+    fn parse(value: &Value) -> Result<PieceSheet, String> {
+        let sheet = PieceSheet {
+            image: parse_string(value, "image")?,
+            order: parse_list(&value["order"], piece_from_letter)?,
+            rows: parse_list(&value["rows"], |color| {
+                PieceColor::from_str(color).ok_or(format!("Invalid color {}", color))
+            })?,
+        };
+        if sheet.order.len() != 6 || sheet.rows.len() != 2 {
+            return Err(String::from(
+                "A piece sheet needs a column for every piece type and a row for each color",
+            ));
+        }
+        Ok(sheet)
+    }
+}
+
+impl Theme {
+    fn parse(value: &Value) -> Result<Theme, String> {
+        let fog = &value["fog"];
+        Ok(Theme {
+            name: parse_string(value, "name")?,
+            light_tile: parse_color(&value["light_tile"], 1.0)?,
+            dark_tile: parse_color(&value["dark_tile"], 1.0)?,
+            background: parse_color(&value["background"], 1.0)?,
+            fog: Fog {
+                color: parse_color(&fog["color"], fog["opacity"].as_f64().unwrap_or(1.0))?,
+                texture: fog["texture"].as_str().map(String::from),
+            },
+            pieces: PieceSheet::parse(&value["pieces"])?,
+            markers: parse_string(value, "markers")?,
+        })
+    }
+
+    /// Returns an error naming the first image of the theme which can't be found
+    fn check_images(&self, ctx: &Context) -> Result<(), String> {
+        let images = [
+            Some(&self.pieces.image),
+            Some(&self.markers),
+            self.fog.texture.as_ref(),
+        ];
+        match images
+            .iter()
+            .flatten()
+            .find(|image| !ggez::filesystem::exists(ctx, image.as_str()))
+        {
+            Some(image) => Err(format!("The image {} does not exist", image)),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads a list of themes, or a single theme
+    fn parse_all(data: &str) -> Result<Vec<Theme>, String> {
+        let value: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        match &value {
+            Value::Array(themes) => themes.iter().map(Theme::parse).collect(),
+            theme => Ok(vec![Theme::parse(theme)?]),
+        }
+    }
+}
+
+/// The themes which can be selected, the first one being the default
+pub(crate) struct Themes {
+    themes: Vec<Theme>,
+    selected: usize,
+}
+
+impl Themes {
+    /// Loads the built-in themes followed by the user's themes, files which can't be read are skipped
+    pub(crate) fn load(ctx: &Context, config_dir: &Path) -> Themes {
+        let mut themes = Theme::parse_all(DEFAULT_THEMES).expect("The default themes are invalid");
+
+        if let Ok(entries) = fs::read_dir(config_dir.join(THEME_DIR)) {
+            let mut paths: Vec<_> = entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "json")
+                })
+                .collect();
+            paths.sort();
+            for path in paths {
+                match fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| Theme::parse_all(&data))
+                    .and_then(|user_themes| {
+                        for theme in &user_themes {
+                            theme
+                                .check_images(ctx)
+                                .map_err(|e| format!("{}: {}", theme.name, e))?;
+                        }
+                        Ok(user_themes)
+                    }) {
+                    Ok(mut user_themes) => themes.append(&mut user_themes),
+                    Err(e) => println!("Could not read theme {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        Themes {
+            themes,
+            selected: 0,
+        }
+    }
+
+    pub(crate) fn current(&self) -> &Theme {
+        &self.themes[self.selected]
+    }
+
+    /// Selects the theme with the given name, returns false if there is no such theme
+    pub(crate) fn select(&mut self, name: &str) -> bool {
+        match self.themes.iter().position(|theme| theme.name == name) {
+            Some(index) => {
+                self.selected = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switches to the next theme, returning its name
+    pub(crate) fn cycle(&mut self) -> &str {
+        self.selected = (self.selected + 1) % self.themes.len();
+        &self.current().name
+    }
+}
+
+impl Game {
+    pub(crate) fn theme(&self) -> &Theme {
+        self.themes.current()
+    }
+
+    /// Switches to the next theme, the board is redrawn in its colors
+    pub(crate) fn cycle_theme(&mut self, ctx: &mut Context) {
+        println!("Theme: {}", self.themes.cycle());
+        self.board_mesh =
+            Game::get_board_mesh(ctx, self.board_geometry.tile_size, self.themes.current());
+        self.save_settings();
+    }
+}
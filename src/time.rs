@@ -1,5 +1,5 @@
 
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use ggez::{
     graphics::{self, DrawMode},
@@ -7,60 +7,423 @@ use ggez::{
 };
 
 use crate::{
-    event_handler::{BOARD_ORIGO_X, BOARD_ORIGO_Y, BOARD_WIDTH},
-    game::{Game, DARK_COLOR, LIGHT_COLOR},
-    menu::clickable::ClickableGroup,
-    SCREEN_HEIGHT, SCREEN_WIDTH,
+    clock::TimeSource,
+    correspondence::{days_label, format_deadline},
+    game::{Game, DARK_COLOR, ERROR_COLOR, LIGHT_COLOR},
+    localization::{tr, tr_args},
+    menu::clickable::{ClickableGroup, UiAction},
+    piece::piece::PieceColor,
+    sound::SoundEvent,
+    spectator::delay_label,
+    time_presets::{escape_preset, unescape_preset},
 };
 
-pub(crate) const TIME_TEXT_WIDTH: f32 = (SCREEN_WIDTH * 2.0 / 3.0 - 50.0) / 3.0;
-pub(crate) const TIME_TEXT_HEIGHT: f32 = 50.0;
-pub(crate) const TIME_BACKDROP_Y_OFFSET: f32 = 30.0;
-pub(crate) const TIME_TITLE_Y_OFFSET: f32 = 150.0;
-pub(crate) const TIME_TEXT_Y_POSITION: f32 = SCREEN_HEIGHT * 2.0 / 5.0;
-pub(crate) const TIME_TEXT_PADDING: f32 = 100.0;
-
 // Time is measured in seconds
 pub(crate) struct Time {
+    // White's initial time and increment, which are also Black's unless the game has time odds
     pub(crate) initial_time: Duration,
     pub(crate) current_time_left: Duration,
     pub(crate) opponent_time_left: Duration,
     pub(crate) turn_start: Instant,
-    pub(crate) increment: Duration,
-    pub(crate) time_set: bool,
+    pub(crate) increment: Duration, // Increment or delay, depending on the bonus
+    pub(crate) black_time: Option<(Duration, Duration)>, // Black's initial time and increment
+    pub(crate) editing: ClockSide, // The side changed on the clock screen
+    pub(crate) bonus: Bonus,
+    pub(crate) stages: Vec<TimeStage>,
+    pub(crate) preset: Option<String>, // Name of the selected preset, if unchanged
+    pub(crate) low_time_threshold: Duration, // Zero disables the warning
+    pub(crate) low_time_warned: bool,
+    pub(crate) days_per_move: Option<u64>, // Set in correspondence games, which have no running clock
+    pub(crate) last_move_time: SystemTime,
+    pub(crate) source: Box<dyn TimeSource>, // Where the current time is read from
 }
 
-impl Game {
-    fn format_time(time_left: u64) -> String {
-        let minutes = time_left / 60;
-        let seconds = time_left % 60;
+/// Which side's time is changed on the clock screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum ClockSide {
+    Both,
+    White,
+    Black,
+}
+
+impl ClockSide {
+    pub(crate) fn next(&self) -> ClockSide {
+        match self {
+            ClockSide::Both => ClockSide::White,
+            ClockSide::White => ClockSide::Black,
+            ClockSide::Black => ClockSide::Both,
+        }
+    }
+
+    pub(crate) fn label(&self) -> String {
+        match self {
+            ClockSide::Both => tr("clock.editing_both"),
+            ClockSide::White => tr("clock.editing_white"),
+            ClockSide::Black => tr("clock.editing_black"),
+        }
+    }
+}
+
+/// How the increment time is given to a player
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Bonus {
+    Increment,   // Fischer: added after every move
+    SimpleDelay, // The clock doesn't start running until the delay has passed
+    Bronstein,   // The time used is given back after the move, up to the delay
+}
+
+impl Bonus {
+    pub(crate) fn to_str(self) -> &'static str {
+        match self {
+            Bonus::Increment => "increment",
+            Bonus::SimpleDelay => "delay",
+            Bonus::Bronstein => "bronstein",
+        }
+    }
+
+    pub(crate) fn from_str(string: &str) -> Bonus {
+        match string {
+            "delay" => Bonus::SimpleDelay,
+            "bronstein" => Bonus::Bronstein,
+            _ => Bonus::Increment,
+        }
+    }
+
+    pub(crate) fn next(&self) -> Bonus {
+        match self {
+            Bonus::Increment => Bonus::SimpleDelay,
+            Bonus::SimpleDelay => Bonus::Bronstein,
+            Bonus::Bronstein => Bonus::Increment,
+        }
+    }
+
+    pub(crate) fn label(&self) -> String {
+        match self {
+            Bonus::Increment => tr("clock.bonus_increment"),
+            Bonus::SimpleDelay => tr("clock.bonus_delay"),
+            Bonus::Bronstein => tr("clock.bonus_bronstein"),
+        }
+    }
+}
+
+/// Time added to a player's clock once they have made the moves of the previous stage,
+/// e.g. 30 minutes after the first 40 moves
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct TimeStage {
+    pub(crate) moves: u32,
+    pub(crate) time: Duration,
+}
+
+/// The multi-stage controls that can be selected on the clock screen, as (moves, seconds) per stage
+pub(crate) const TIME_STAGE_PRESETS: [&[(u32, u64)]; 4] = [
+    &[],
+    &[(40, 30 * 60)],
+    &[(40, 60 * 60)],
+    &[(40, 60 * 60), (20, 30 * 60)],
+];
 
-        let mut string = String::from("");
-        if minutes < 10 {
-            string.push_str(&format!("0{}", minutes)[..]);
+pub(crate) fn stages_label(stages: &[TimeStage]) -> String {
+    if stages.is_empty() {
+        return tr("clock.stages_none");
+    }
+    let stages: Vec<String> = stages
+        .iter()
+        .map(|stage| {
+            tr_args(
+                "clock.stage",
+                &[
+                    ("moves", stage.moves.to_string()),
+                    ("minutes", (stage.time.as_secs() / 60).to_string()),
+                ],
+            )
+        })
+        .collect();
+    tr_args("clock.stages", &[("stages", stages.join(", "))])
+}
+
+/// The low time warning thresholds, in seconds, cycled through on the clock screen
+pub(crate) const LOW_TIME_THRESHOLDS: [u64; 5] = [0, 10, 20, 30, 60];
+
+pub(crate) fn low_time_label(threshold: Duration) -> String {
+    if threshold.as_secs() == 0 {
+        tr("clock.low_time_off")
+    } else {
+        tr_args(
+            "clock.low_time",
+            &[("seconds", threshold.as_secs().to_string())],
+        )
+    }
+}
+
+/// Formats a time control as "minutes+increment", e.g. "5+3" or "1:30+0"
+pub(crate) fn format_time_control(initial_time: u64, increment: u64) -> String {
+    if initial_time.is_multiple_of(60) {
+        format!("{}+{}", initial_time / 60, increment)
+    } else {
+        format!("{}:{:02}+{}", initial_time / 60, initial_time % 60, increment)
+    }
+}
+
+impl Time {
+    /// The initial time and increment of one color
+    pub(crate) fn side(&self, white: bool) -> (Duration, Duration) {
+        match self.black_time {
+            Some(black_time) if !white => black_time,
+            _ => (self.initial_time, self.increment),
+        }
+    }
+
+    /// Time taken from the clock of a color when `elapsed` has passed during its turn
+    pub(crate) fn time_used(&self, elapsed: Duration, white: bool) -> Duration {
+        let (_, increment) = self.side(white);
+        match self.bonus {
+            Bonus::SimpleDelay => elapsed.checked_sub(increment).unwrap_or_default(),
+            _ => elapsed,
+        }
+    }
+
+    pub(crate) fn start_turn(&mut self) {
+        self.turn_start = self.source.now();
+    }
+
+    pub(crate) fn turn_elapsed(&self) -> Duration {
+        self.source.now().saturating_duration_since(self.turn_start)
+    }
+
+    /// The time left on a clock. `running` is whether it is the turn of the clock's color,
+    /// in which case the time used this turn is counted down.
+    pub(crate) fn time_left(&self, own_clock: bool, running: bool, white: bool) -> Duration {
+        let time_left = if own_clock {
+            self.current_time_left
         } else {
-            string.push_str(&minutes.to_string()[..]);
+            self.opponent_time_left
+        };
+        if running {
+            time_left
+                .checked_sub(self.time_used(self.turn_elapsed(), white))
+                .unwrap_or_default()
+        } else {
+            time_left
         }
+    }
 
-        string.push_str(":");
+    /// Whether the player to move has run out of time
+    pub(crate) fn flagged(&self, own_turn: bool, white: bool) -> bool {
+        let time_left = if own_turn {
+            self.current_time_left
+        } else {
+            self.opponent_time_left
+        };
+        time_left < self.time_used(self.turn_elapsed(), white)
+    }
 
-        if seconds < 10 {
-            string.push_str(&format!("0{}", seconds)[..]);
+    /// The winner when the player to move has run out of time, which is the other color
+    pub(crate) fn flag_winner(&self, own_turn: bool, white: bool) -> Option<PieceColor> {
+        if !self.flagged(own_turn, white) {
+            return None;
+        }
+        Some(if white {
+            PieceColor::Black
         } else {
-            string.push_str(&seconds.to_string()[..]);
+            PieceColor::White
+        })
+    }
+
+    /// Takes the time used this turn from the clock of the player who moved and adds the bonus.
+    /// `moves_made` is the number of moves the player has made, including this one.
+    pub(crate) fn end_turn(&mut self, own_turn: bool, white: bool, moves_made: u32) {
+        let elapsed = self.turn_elapsed();
+        let used = self.time_used(elapsed, white);
+        let bonus = self.time_bonus(elapsed, moves_made, white);
+        let time_left = if own_turn {
+            &mut self.current_time_left
+        } else {
+            &mut self.opponent_time_left
+        };
+        *time_left = time_left.checked_sub(used).unwrap_or_default() + bonus;
+    }
+
+    /// Time given back to a color after a move which took `elapsed`.
+    /// `moves_made` is the number of moves the player has made, including this one.
+    pub(crate) fn time_bonus(&self, elapsed: Duration, moves_made: u32, white: bool) -> Duration {
+        let (_, increment) = self.side(white);
+        let bonus = match self.bonus {
+            Bonus::Increment => increment,
+            Bonus::SimpleDelay => Duration::from_secs(0),
+            Bonus::Bronstein => elapsed.min(increment),
+        };
+
+        let mut stage_end = 0;
+        for stage in &self.stages {
+            stage_end += stage.moves;
+            if moves_made == stage_end {
+                return bonus + stage.time;
+            }
+        }
+        bonus
+    }
+
+    /// The time control as sent with "set_clock_time".
+    /// Format: "initial:increment:bonus:stages:preset:black:days", where stages are "moves+seconds"
+    /// separated by ',', black is Black's "initial+increment" in games with time odds
+    /// and days is the days per move of a correspondence game
+    pub(crate) fn control_string(&self) -> String {
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| format!("{}+{}", stage.moves, stage.time.as_secs()))
+            .collect();
+        let preset = self.preset.clone().unwrap_or_default();
+        let black_time = match self.black_time {
+            Some((initial_time, increment)) => {
+                format!("{}+{}", initial_time.as_secs(), increment.as_secs())
+            }
+            None => String::from(""),
+        };
+        let days_per_move = match self.days_per_move {
+            Some(days) => days.to_string(),
+            None => String::from(""),
+        };
+        format!(
+            "{}:{}:{}:{}:{}:{}:{}",
+            self.initial_time.as_secs(),
+            self.increment.as_secs(),
+            self.bonus.to_str(),
+            stages.join(","),
+            escape_preset(&preset),
+            black_time,
+            days_per_move
+        )
+    }
+
+    /// Sets the time control from a control string. Everything after the increment is optional,
+    /// which keeps the plain "initial:increment" format working.
+    pub(crate) fn apply_control_string(&mut self, data: &str) {
+        let mut split = data.split(':');
+        if let Some(Ok(initial_time)) = split.next().map(|value| value.parse::<u64>()) {
+            self.initial_time = Duration::from_secs(initial_time);
+        }
+        if let Some(Ok(increment)) = split.next().map(|value| value.parse::<u64>()) {
+            self.increment = Duration::from_secs(increment);
+        }
+        self.bonus = Bonus::from_str(split.next().unwrap_or(""));
+        self.stages = split
+            .next()
+            .unwrap_or("")
+            .split(',')
+            .filter_map(|stage| {
+                let mut stage = stage.split('+');
+                Some(TimeStage {
+                    moves: stage.next()?.parse::<u32>().ok()?,
+                    time: Duration::from_secs(stage.next()?.parse::<u64>().ok()?),
+                })
+            })
+            .collect();
+        self.preset = split
+            .next()
+            .filter(|preset| !preset.is_empty())
+            .map(unescape_preset);
+        self.black_time = split.next().and_then(|black_time| {
+            let mut black_time = black_time.split('+');
+            Some((
+                Duration::from_secs(black_time.next()?.parse::<u64>().ok()?),
+                Duration::from_secs(black_time.next()?.parse::<u64>().ok()?),
+            ))
+        });
+        self.days_per_move = split.next().and_then(|days| days.parse::<u64>().ok());
+    }
+}
+
+impl Game {
+    /// Formats the time left as "mm:ss", "h:mm:ss" from one hour and "00:ss.t" below ten seconds
+    fn format_time(time_left: Duration) -> String {
+        let hours = time_left.as_secs() / 3600;
+        let minutes = time_left.as_secs() / 60 % 60;
+        let seconds = time_left.as_secs() % 60;
+
+        if hours > 0 {
+            format!("{}:{:02}:{:02}", hours, minutes, seconds)
+        } else if time_left < Duration::from_secs(10) {
+            format!("00:{:02}.{}", seconds, time_left.subsec_millis() / 100)
+        } else {
+            format!("{:02}:{:02}", minutes, seconds)
+        }
+    }
+
+    /// The time left on a clock, counting down the current turn.
+    /// `own_clock` is the clock at the bottom of the board.
+    pub(crate) fn clock_time_left(&self, own_clock: bool) -> Duration {
+        // The color whose clock it is
+        let white = own_clock == self.playing_as_white;
+        if !self.game_active {
+            return self.time.side(white).0;
+        }
+        self.time
+            .time_left(own_clock, own_clock == self.active_turn, white)
+    }
+
+    /// Correspondence games show the time left until the move deadline instead of a clock
+    fn clock_text(&self, own_clock: bool, time_left: Duration) -> String {
+        match self.time.days_per_move {
+            Some(_) if own_clock == self.active_turn && self.game_active => {
+                format_deadline(self.correspondence_time_left())
+            }
+            Some(days) => days_label(Some(days)),
+            None => Game::format_time(time_left),
+        }
+    }
+
+    fn is_low_time(&self, time_left: Duration) -> bool {
+        self.game_active
+            && self.time.days_per_move.is_none()
+            && time_left < self.time.low_time_threshold
+    }
+
+    /// Plays the warning sound once when the player's own clock runs low
+    pub(crate) fn check_low_time(&mut self) {
+        if self.spectating.is_some() || self.winner.is_some() {
+            return;
         }
-        return string;
+        let low_time = self.is_low_time(self.clock_time_left(true));
+        if low_time && !self.time.low_time_warned {
+            self.play_sound(SoundEvent::LowTime);
+        }
+        // The warning is given again if an increment brings the clock back above the threshold
+        self.time.low_time_warned = low_time;
+    }
+
+    pub(crate) fn cycle_low_time_threshold(&mut self) {
+        let current = LOW_TIME_THRESHOLDS
+            .iter()
+            .position(|threshold| *threshold == self.time.low_time_threshold.as_secs())
+            .unwrap_or(0);
+        let next = LOW_TIME_THRESHOLDS[(current + 1) % LOW_TIME_THRESHOLDS.len()];
+        self.time.low_time_threshold = Duration::from_secs(next);
+        self.update_time_labels();
     }
 
     pub(crate) fn render_time(&mut self, ctx: &mut Context) {
+        // The clocks are drawn in the panel right of the board, at the edges of the board
+        let board = self.board_geometry;
+        let panel = self.menu.layout.rect("side_panel");
+        // Your clock is at the bottom unless the board has been flipped
+        let (top, bottom) = (board.y, board.y + board.width() - 80.0);
+        let (own_y, opponent_y) = if self.board_flipped {
+            (top, bottom)
+        } else {
+            (bottom, top)
+        };
+        // The time controls are shown between the clocks
+        let time_control_y = |y: f32| if y == top { y + 90.0 } else { y - 35.0 };
+
         if !self.active_turn {
             match graphics::Mesh::new_rectangle(
                 ctx,
                 DrawMode::fill(),
                 graphics::Rect::new(
-                    BOARD_ORIGO_X + BOARD_WIDTH as f32 + 30.0,
-                    BOARD_ORIGO_Y,
-                    (SCREEN_WIDTH - BOARD_WIDTH as f32) / 2.0 - 60.0,
+                    panel.x + 30.0,
+                    opponent_y,
+                    panel.w - 60.0,
                     80.0,
                 ),
                 graphics::Color::from(DARK_COLOR),
@@ -74,21 +437,18 @@ impl Game {
         }
 
         // Opponent time left
-        let opponent_time = if self.game_active {
-            if !self.active_turn && self.time.opponent_time_left >= self.time.turn_start.elapsed() {
-                self.time.opponent_time_left - self.time.turn_start.elapsed()
-            } else {
-                self.time.opponent_time_left
-            }
+        let opponent_time = self.clock_time_left(false);
+        let color = if self.is_low_time(opponent_time) {
+            ERROR_COLOR
         } else {
-            self.time.initial_time
+            LIGHT_COLOR
         };
         self.menu.draw_text(
             ctx,
-            Game::format_time(opponent_time.as_secs()),
-            (BOARD_ORIGO_X + BOARD_WIDTH as f32, BOARD_ORIGO_Y + 20.0),
-            ((SCREEN_WIDTH - BOARD_WIDTH as f32) / 2.0, 40.0),
-            graphics::Color::from(LIGHT_COLOR),
+            self.clock_text(false, opponent_time),
+            (panel.x, opponent_y + 20.0),
+            (panel.w, 40.0),
+            graphics::Color::from(color),
             graphics::Align::Center,
         );
 
@@ -97,9 +457,9 @@ impl Game {
                 ctx,
                 DrawMode::fill(),
                 graphics::Rect::new(
-                    BOARD_ORIGO_X + BOARD_WIDTH as f32 + 30.0,
-                    BOARD_ORIGO_Y + BOARD_WIDTH as f32 - 80.0,
-                    (SCREEN_WIDTH - BOARD_WIDTH as f32) / 2.0 - 60.0,
+                    panel.x + 30.0,
+                    own_y,
+                    panel.w - 60.0,
                     80.0,
                 ),
                 graphics::Color::from(DARK_COLOR),
@@ -113,26 +473,43 @@ impl Game {
         }
 
         // User time left
-        let time_left = if self.game_active {
-            if self.active_turn && self.time.current_time_left >= self.time.turn_start.elapsed() {
-                self.time.current_time_left - self.time.turn_start.elapsed()
-            } else {
-                self.time.current_time_left
-            }
+        let time_left = self.clock_time_left(true);
+        let color = if self.is_low_time(time_left) {
+            ERROR_COLOR
         } else {
-            self.time.initial_time
+            LIGHT_COLOR
         };
         self.menu.draw_text(
             ctx,
-            Game::format_time(time_left.as_secs()),
-            (
-                BOARD_ORIGO_X + BOARD_WIDTH as f32,
-                BOARD_ORIGO_Y + BOARD_WIDTH as f32 - 60.0,
-            ),
-            ((SCREEN_WIDTH - BOARD_WIDTH as f32) / 2.0, 40.0),
-            graphics::Color::from(LIGHT_COLOR),
+            self.clock_text(true, time_left),
+            (panel.x, own_y + 20.0),
+            (panel.w, 40.0),
+            graphics::Color::from(color),
             graphics::Align::Center,
         );
+
+        // With time odds the time control of each side is shown next to its clock
+        if self.time.black_time.is_some() {
+            let (initial_time, increment) = self.time.side(!self.playing_as_white);
+            self.menu.draw_text(
+                ctx,
+                format_time_control(initial_time.as_secs(), increment.as_secs()),
+                (panel.x, time_control_y(opponent_y)),
+                (panel.w, 25.0),
+                graphics::Color::from(LIGHT_COLOR),
+                graphics::Align::Center,
+            );
+
+            let (initial_time, increment) = self.time.side(self.playing_as_white);
+            self.menu.draw_text(
+                ctx,
+                format_time_control(initial_time.as_secs(), increment.as_secs()),
+                (panel.x, time_control_y(own_y)),
+                (panel.w, 25.0),
+                graphics::Color::from(LIGHT_COLOR),
+                graphics::Align::Center,
+            );
+        }
     }
 
     pub(crate) fn modify_time(&mut self, count: Duration, positive: bool, is_increment: bool) {
@@ -140,189 +517,331 @@ impl Game {
             println!("This user is not an admin, thus does not have permission to edit the time");
             return;
         }
+        self.time.preset = None;
+
+        let (mut initial_time, mut increment) =
+            self.time.side(self.time.editing != ClockSide::Black);
 
         if is_increment {
             if positive {
-                if self.time.increment + count < Duration::from_secs(120) {
-                    self.time.increment += count;
+                if increment + count < Duration::from_secs(120) {
+                    increment += count;
                 }
             } else {
-                if self.time.increment >= count {
-                    self.time.increment -= count;
+                if increment >= count {
+                    increment -= count;
                 }
             }
-            return;
-        }
-
-        if positive {
+        } else if positive {
             // Cannot have games longer than 2 hours
-            if self.time.initial_time + count < Duration::from_secs(7200) {
-                self.time.initial_time += count
+            if initial_time + count < Duration::from_secs(7200) {
+                initial_time += count
             }
         } else {
             // Cannot have games with negative time
-            if self.time.initial_time > count
-                && self.time.initial_time - count >= Duration::from_secs(15)
-            {
-                self.time.initial_time -= count;
+            if initial_time > count && initial_time - count >= Duration::from_secs(15) {
+                initial_time -= count;
+            }
+        }
+
+        match self.time.editing {
+            ClockSide::Both => {
+                self.time.initial_time = initial_time;
+                self.time.increment = increment;
+                self.time.black_time = None;
+            }
+            ClockSide::White => {
+                // Black keeps its own time once the sides differ
+                self.time.black_time = Some(self.time.side(false));
+                self.time.initial_time = initial_time;
+                self.time.increment = increment;
             }
+            ClockSide::Black => self.time.black_time = Some((initial_time, increment)),
+        }
+        if self.time.black_time == Some((self.time.initial_time, self.time.increment)) {
+            self.time.black_time = None;
         }
     }
 
-    pub(crate) fn perform_time_increment(&mut self) {
-        if self.active_turn && self.game_active {
-            self.time.current_time_left -= self.time.turn_start.elapsed();
-            self.time.current_time_left += self.time.increment;
-        } else if self.game_active {
-            self.time.opponent_time_left -= self.time.turn_start.elapsed();
-            self.time.opponent_time_left += self.time.increment;
+    pub(crate) fn cycle_edited_side(&mut self) {
+        if !self.is_admin {
+            return;
         }
+        self.time.editing = self.time.editing.next();
+        self.update_time_labels();
     }
 
-    pub(crate) fn render_time_interface(&mut self, ctx: &mut Context) {
-        let minutes = self.time.initial_time.as_secs() / 60;
-        let seconds = self.time.initial_time.as_secs() % 60;
+    /// Sets both clocks to the initial time of their color
+    pub(crate) fn reset_clocks(&mut self) {
+        self.time.current_time_left = self.time.side(self.playing_as_white).0;
+        self.time.opponent_time_left = self.time.side(!self.playing_as_white).0;
+    }
 
-        // Draw page title
-        self.menu.draw_text(
-            ctx,
-            String::from("Chess clock"),
-            (0.0, SCREEN_HEIGHT * 0.05),
-            (SCREEN_WIDTH, TIME_TEXT_HEIGHT * 2.0),
-            graphics::Color::from(LIGHT_COLOR),
-            graphics::Align::Center,
-        );
+    /// Has to be called before the move is added to the move history
+    pub(crate) fn perform_time_increment(&mut self) {
+        // Correspondence games only keep track of when the last move was made
+        if self.time.days_per_move.is_some() {
+            self.time.last_move_time = self.time.source.system_time();
+            return;
+        }
 
-        match graphics::Mesh::new_rectangle(
-            ctx,
-            graphics::DrawMode::fill(),
-            graphics::Rect::new(
-                SCREEN_WIDTH / 2.0 - TIME_TEXT_WIDTH / 2.0 - TIME_TEXT_PADDING - TIME_TEXT_WIDTH,
-                TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET,
-                TIME_TEXT_WIDTH,
-                TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET * 2.0,
-            ),
-            graphics::Color::from(DARK_COLOR),
-        ) {
-            Ok(drawable) => {
-                graphics::draw(ctx, &drawable, graphics::DrawParam::default())
-                    .expect("Could not render background text");
-            }
-            Err(_) => {}
+        if self.game_active {
+            // The color of the moving player
+            let white = self.active_turn == self.playing_as_white;
+            // Moves made by the moving player, including this one
+            let moves_made = self.move_history.len() as u32 / 2 + 1;
+            self.time.end_turn(self.active_turn, white, moves_made);
+        }
+    }
+
+    pub(crate) fn cycle_bonus(&mut self) {
+        if !self.is_admin {
+            return;
         }
+        self.time.bonus = self.time.bonus.next();
+        self.time.preset = None;
+        self.update_time_labels();
+    }
 
-        // Draw minutes title
-        self.menu.draw_text(
-            ctx,
-            String::from("Minutes"),
-            (
-                SCREEN_WIDTH / 2.0 - TIME_TEXT_WIDTH / 2.0 - TIME_TEXT_PADDING - TIME_TEXT_WIDTH,
-                TIME_TEXT_Y_POSITION - TIME_TITLE_Y_OFFSET,
-            ),
-            (TIME_TEXT_WIDTH, TIME_TEXT_HEIGHT),
-            graphics::Color::from(LIGHT_COLOR),
-            graphics::Align::Center,
-        );
-        // Draw minutes
-        self.menu.draw_text(
-            ctx,
-            minutes.to_string(),
-            (
-                SCREEN_WIDTH / 2.0 - TIME_TEXT_WIDTH / 2.0 - TIME_TEXT_PADDING - TIME_TEXT_WIDTH,
-                TIME_TEXT_Y_POSITION,
-            ),
-            (TIME_TEXT_WIDTH, TIME_TEXT_HEIGHT),
-            graphics::Color::from(LIGHT_COLOR),
-            graphics::Align::Center,
-        );
+    pub(crate) fn cycle_time_stages(&mut self) {
+        if !self.is_admin {
+            return;
+        }
+        let presets: Vec<Vec<TimeStage>> = TIME_STAGE_PRESETS
+            .iter()
+            .map(|preset| {
+                preset
+                    .iter()
+                    .map(|(moves, time)| TimeStage {
+                        moves: *moves,
+                        time: Duration::from_secs(*time),
+                    })
+                    .collect()
+            })
+            .collect();
+        let current = presets
+            .iter()
+            .position(|preset| *preset == self.time.stages)
+            .unwrap_or(0);
+        self.time.stages = presets[(current + 1) % presets.len()].clone();
+        self.time.preset = None;
+        self.update_time_labels();
+    }
 
-        match graphics::Mesh::new_rectangle(
-            ctx,
-            graphics::DrawMode::fill(),
-            graphics::Rect::new(
-                SCREEN_WIDTH / 2.0 - TIME_TEXT_WIDTH / 2.0,
-                TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET,
-                TIME_TEXT_WIDTH,
-                TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET * 2.0,
-            ),
-            graphics::Color::from(DARK_COLOR),
-        ) {
-            Ok(drawable) => {
-                graphics::draw(ctx, &drawable, graphics::DrawParam::default())
-                    .expect("Could not render background text");
+    /// Updates the texts of the clock screen buttons which show the time control
+    pub(crate) fn update_time_labels(&mut self) {
+        for clickable in self.menu.clickables.iter_mut() {
+            match clickable.action {
+                UiAction::CycleBonus => clickable.text = self.time.bonus.label(),
+                UiAction::CycleTimeStages => clickable.text = stages_label(&self.time.stages),
+                UiAction::CycleEditedSide => clickable.text = self.time.editing.label(),
+                UiAction::CycleCorrespondenceDays => {
+                    clickable.text = days_label(self.time.days_per_move)
+                }
+                UiAction::CycleLowTimeThreshold => {
+                    clickable.text = low_time_label(self.time.low_time_threshold)
+                }
+                UiAction::CycleSpectatorDelay => clickable.text = delay_label(self.broadcast.delay),
+                _ => {}
             }
-            Err(_) => {}
         }
+    }
 
-        // Draw seconds title
-        self.menu.draw_text(
-            ctx,
-            String::from("Seconds"),
-            (
-                SCREEN_WIDTH / 2.0 - TIME_TEXT_WIDTH / 2.0,
-                TIME_TEXT_Y_POSITION - TIME_TITLE_Y_OFFSET,
-            ),
-            (TIME_TEXT_WIDTH, TIME_TEXT_HEIGHT),
-            graphics::Color::from(LIGHT_COLOR),
-            graphics::Align::Center,
-        );
-        // Draw seconds
+    pub(crate) fn render_time_interface(&mut self, ctx: &mut Context) {
+        let (initial_time, increment) = self.time.side(self.time.editing != ClockSide::Black);
+        let minutes = initial_time.as_secs() / 60;
+        let seconds = initial_time.as_secs() % 60;
+
+        // Draw page title
+        let title = self.menu.layout.rect("clock_title");
         self.menu.draw_text(
             ctx,
-            seconds.to_string(),
-            (
-                SCREEN_WIDTH / 2.0 - TIME_TEXT_WIDTH / 2.0,
-                TIME_TEXT_Y_POSITION,
-            ),
-            (TIME_TEXT_WIDTH, TIME_TEXT_HEIGHT),
+            tr("clock.title"),
+            (title.x, title.y),
+            (title.w, title.h),
             graphics::Color::from(LIGHT_COLOR),
             graphics::Align::Center,
         );
 
-        match graphics::Mesh::new_rectangle(
-            ctx,
-            graphics::DrawMode::fill(),
-            graphics::Rect::new(
-                SCREEN_WIDTH / 2.0 + TIME_TEXT_WIDTH / 2.0 + TIME_TEXT_PADDING,
-                TIME_TEXT_Y_POSITION - TIME_BACKDROP_Y_OFFSET,
-                TIME_TEXT_WIDTH,
-                TIME_TEXT_HEIGHT + TIME_BACKDROP_Y_OFFSET * 2.0,
-            ),
-            graphics::Color::from(DARK_COLOR),
-        ) {
-            Ok(drawable) => {
-                graphics::draw(ctx, &drawable, graphics::DrawParam::default())
-                    .expect("Could not render background text");
+        let increment_title = if self.time.bonus == Bonus::Increment {
+            "clock.increment"
+        } else {
+            "clock.delay"
+        };
+        let columns = [
+            ("minutes", "clock.minutes", minutes),
+            ("seconds", "clock.seconds", seconds),
+            ("increment", increment_title, increment.as_secs()),
+        ];
+        for (name, column_title, value) in columns.iter() {
+            let backdrop = self.menu.layout.rect(&format!("{}_backdrop", name));
+            match graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::fill(),
+                backdrop,
+                graphics::Color::from(DARK_COLOR),
+            ) {
+                Ok(drawable) => {
+                    graphics::draw(ctx, &drawable, graphics::DrawParam::default())
+                        .expect("Could not render background text");
+                }
+                Err(_) => {}
             }
-            Err(_) => {}
-        }
 
-        // Draw increment title
-        self.menu.draw_text(
-            ctx,
-            String::from("Increment"),
-            (
-                SCREEN_WIDTH / 2.0 + TIME_TEXT_WIDTH / 2.0 + TIME_TEXT_PADDING,
-                TIME_TEXT_Y_POSITION - TIME_TITLE_Y_OFFSET,
-            ),
-            (TIME_TEXT_WIDTH, TIME_TEXT_HEIGHT),
-            graphics::Color::from(LIGHT_COLOR),
-            graphics::Align::Center,
-        );
-        // Draw increment
-        self.menu.draw_text(
-            ctx,
-            self.time.increment.as_secs().to_string(),
-            (
-                SCREEN_WIDTH / 2.0 + TIME_TEXT_WIDTH / 2.0 + TIME_TEXT_PADDING,
-                TIME_TEXT_Y_POSITION,
-            ),
-            (TIME_TEXT_WIDTH, TIME_TEXT_HEIGHT),
-            graphics::Color::from(LIGHT_COLOR),
-            graphics::Align::Center,
-        );
+            // Draw column title
+            let title = self.menu.layout.rect(&format!("{}_title", name));
+            self.menu.draw_text(
+                ctx,
+                tr(column_title),
+                (title.x, title.y),
+                (title.w, title.h),
+                graphics::Color::from(LIGHT_COLOR),
+                graphics::Align::Center,
+            );
+            // Draw value
+            let text = self.menu.layout.rect(&format!("{}_value", name));
+            self.menu.draw_text(
+                ctx,
+                value.to_string(),
+                (text.x, text.y),
+                (text.w, text.h),
+                graphics::Color::from(LIGHT_COLOR),
+                graphics::Align::Center,
+            );
+        }
 
         self.menu
             .draw_clickables(ctx, vec![ClickableGroup::TimeSelection]);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn secs(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    /// One minute for each side with the given bonus, driven by the returned clock
+    fn time(bonus: Bonus, increment: u64) -> (Time, FakeClock) {
+        let clock = FakeClock::new();
+        let time = Time {
+            initial_time: MINUTE,
+            current_time_left: MINUTE,
+            opponent_time_left: MINUTE,
+            turn_start: clock.now(),
+            increment: secs(increment),
+            black_time: None,
+            editing: ClockSide::Both,
+            bonus,
+            stages: Vec::new(),
+            preset: None,
+            low_time_threshold: Duration::from_secs(0),
+            low_time_warned: false,
+            days_per_move: None,
+            last_move_time: clock.system_time(),
+            source: Box::new(clock.clone()),
+        };
+        (time, clock)
+    }
+
+    /// Plays one move of `seconds` on the own clock
+    fn move_taking(time: &mut Time, clock: &FakeClock, seconds: u64, moves_made: u32) {
+        time.start_turn();
+        clock.advance(secs(seconds));
+        assert_eq!(time.turn_elapsed(), secs(seconds));
+        time.end_turn(true, true, moves_made);
+    }
+
+    #[test]
+    fn increment_is_added_after_every_move() {
+        let (mut time, clock) = time(Bonus::Increment, 2);
+        move_taking(&mut time, &clock, 10, 1);
+        assert_eq!(time.current_time_left, secs(52));
+        move_taking(&mut time, &clock, 0, 2);
+        assert_eq!(time.current_time_left, secs(54));
+        assert_eq!(time.opponent_time_left, MINUTE);
+    }
+
+    #[test]
+    fn delay_is_not_taken_from_the_clock() {
+        let (mut time, clock) = time(Bonus::SimpleDelay, 5);
+        move_taking(&mut time, &clock, 3, 1);
+        assert_eq!(time.current_time_left, MINUTE);
+        move_taking(&mut time, &clock, 8, 2);
+        assert_eq!(time.current_time_left, secs(57));
+    }
+
+    #[test]
+    fn bronstein_gives_back_the_time_used_up_to_the_delay() {
+        let (mut time, clock) = time(Bonus::Bronstein, 5);
+        move_taking(&mut time, &clock, 3, 1);
+        assert_eq!(time.current_time_left, MINUTE);
+        move_taking(&mut time, &clock, 8, 2);
+        assert_eq!(time.current_time_left, secs(57));
+    }
+
+    #[test]
+    fn time_stages_are_added_after_their_last_move() {
+        let (mut time, clock) = time(Bonus::Increment, 0);
+        time.stages = vec![TimeStage {
+            moves: 2,
+            time: MINUTE,
+        }];
+        move_taking(&mut time, &clock, 10, 1);
+        assert_eq!(time.current_time_left, secs(50));
+        move_taking(&mut time, &clock, 10, 2);
+        assert_eq!(time.current_time_left, secs(100));
+    }
+
+    #[test]
+    fn control_strings_keep_preset_names() {
+        let (mut sent, _) = time(Bonus::Bronstein, 5);
+        sent.preset = Some(String::from("Custom 1:30+0; 50%"));
+        sent.days_per_move = Some(3);
+
+        let (mut received, _) = time(Bonus::Increment, 0);
+        received.apply_control_string(&sent.control_string());
+        assert_eq!(received.preset, sent.preset);
+        assert_eq!(received.bonus, Bonus::Bronstein);
+        assert_eq!(received.increment, secs(5));
+        assert_eq!(received.days_per_move, Some(3));
+    }
+
+    #[test]
+    fn flags_when_the_time_runs_out() {
+        let (mut time, clock) = time(Bonus::Increment, 2);
+        time.start_turn();
+        clock.advance(secs(59));
+        assert!(!time.flagged(true, true));
+        assert_eq!(time.flag_winner(true, true), None);
+        clock.advance(secs(2));
+        assert!(time.flagged(true, true));
+        assert_eq!(time.flag_winner(true, true), Some(PieceColor::Black));
+        // Only the clock of the player to move runs
+        time.opponent_time_left = secs(30);
+        assert!(time.flagged(false, false));
+        assert_eq!(time.flag_winner(false, false), Some(PieceColor::White));
+        clock.set_elapsed(secs(20));
+        time.start_turn();
+        assert!(!time.flagged(false, false));
+        assert_eq!(time.flag_winner(false, false), None);
+    }
+
+    #[test]
+    fn delay_postpones_flagging() {
+        let (mut time, clock) = time(Bonus::SimpleDelay, 5);
+        time.start_turn();
+        clock.advance(secs(64));
+        assert!(!time.flagged(true, true));
+        assert_eq!(time.time_left(true, true, true), secs(1));
+        clock.advance(secs(2));
+        assert!(time.flagged(true, true));
+        assert_eq!(time.flag_winner(true, true), Some(PieceColor::Black));
+        assert_eq!(time.time_left(true, true, true), Duration::from_secs(0));
+    }
+}
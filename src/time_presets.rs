@@ -0,0 +1,184 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use crate::{
+    game::Game,
+    localization::{tr, tr_args},
+    time::{format_time_control, Bonus},
+};
+
+/// The standard time controls shown on the clock screen, as (category, initial seconds, increment).
+/// The category is the key of its name in the language files, e.g. "preset.blitz".
+pub(crate) const STANDARD_PRESETS: [(&str, u64, u64); 12] = [
+    ("bullet", 60, 0),
+    ("bullet", 120, 1),
+    ("blitz", 180, 0),
+    ("blitz", 180, 2),
+    ("blitz", 300, 0),
+    ("blitz", 300, 3),
+    ("rapid", 600, 0),
+    ("rapid", 600, 5),
+    ("rapid", 900, 10),
+    ("classical", 1800, 0),
+    ("classical", 1800, 20),
+    ("classical", 5400, 30),
+];
+// The oldest custom preset is replaced when more than this are saved
+pub(crate) const MAX_CUSTOM_PRESETS: usize = 4;
+const PRESET_FILE: &str = "time_presets.txt";
+
+/// Escapes the characters which separate fields in the control string, the preset file
+/// and the network messages, so that names like "Custom 1:30+0" are sent as they are
+pub(crate) fn escape_preset(name: &str) -> String {
+    name.replace('%', "%25")
+        .replace(':', "%3A")
+        .replace(';', "%3B")
+}
+
+pub(crate) fn unescape_preset(name: &str) -> String {
+    name.replace("%3A", ":")
+        .replace("%3B", ";")
+        .replace("%25", "%")
+}
+
+/// The name of a standard preset in the selected language, e.g. "Blitz 3+2"
+pub(crate) fn standard_preset_name(index: usize) -> String {
+    let (category, initial_time, increment) = STANDARD_PRESETS[index];
+    format!(
+        "{} {}",
+        tr(&format!("preset.{}", category)),
+        format_time_control(initial_time, increment)
+    )
+}
+
+/// Identifies a standard preset independently of the language, e.g. "blitz 3+2"
+pub(crate) fn standard_preset_key(index: usize) -> String {
+    let (category, initial_time, increment) = STANDARD_PRESETS[index];
+    format!(
+        "{} {}",
+        category,
+        format_time_control(initial_time, increment)
+    )
+}
+
+/// A time control saved by the user
+#[derive(Clone, Debug)]
+pub(crate) struct TimePreset {
+    pub(crate) name: String,
+    pub(crate) control: String, // See Time::control_string
+}
+
+pub(crate) struct TimePresets {
+    pub(crate) custom: Vec<TimePreset>,
+    path: PathBuf,
+}
+
+impl TimePresets {
+    /// Loads the custom presets from the given config directory, one "name;control" per line
+    pub(crate) fn load(config_dir: PathBuf) -> TimePresets {
+        let path = config_dir.join(PRESET_FILE);
+        let custom = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut split = line.splitn(2, ';');
+                Some(TimePreset {
+                    name: unescape_preset(split.next()?),
+                    control: split.next()?.to_string(),
+                })
+            })
+            .collect();
+
+        TimePresets { custom, path }
+    }
+
+    pub(crate) fn add(&mut self, preset: TimePreset) {
+        self.custom.retain(|existing| existing.name != preset.name);
+        self.custom.push(preset);
+        if self.custom.len() > MAX_CUSTOM_PRESETS {
+            self.custom.remove(0);
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let data: Vec<String> = self
+            .custom
+            .iter()
+            .map(|preset| format!("{};{}", escape_preset(&preset.name), preset.control))
+            .collect();
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).ok();
+        }
+        if let Err(e) = fs::write(&self.path, data.join("\n")) {
+            eprintln!("Could not save time presets: {}", e);
+        }
+    }
+}
+
+impl Game {
+    pub(crate) fn select_standard_preset(&mut self, index: usize) {
+        if !self.is_admin || index >= STANDARD_PRESETS.len() {
+            return;
+        }
+        let (_, initial_time, increment) = STANDARD_PRESETS[index];
+        self.time.initial_time = Duration::from_secs(initial_time);
+        self.time.increment = Duration::from_secs(increment);
+        self.time.bonus = Bonus::Increment;
+        self.time.stages = Vec::new();
+        self.time.black_time = None;
+        self.time.preset = Some(standard_preset_name(index));
+        self.update_time_labels();
+    }
+
+    pub(crate) fn select_custom_preset(&mut self, index: usize) {
+        if !self.is_admin {
+            return;
+        }
+        if let Some(preset) = self.presets.custom.get(index).cloned() {
+            self.time.apply_control_string(&preset.control);
+            self.time.preset = Some(preset.name);
+            self.update_time_labels();
+        }
+    }
+
+    /// Saves the time control currently on the clock screen as a custom preset
+    pub(crate) fn save_custom_preset(&mut self) {
+        if !self.is_admin {
+            return;
+        }
+        let mut name = tr_args(
+            "preset.custom",
+            &[(
+                "time",
+                format_time_control(
+                    self.time.initial_time.as_secs(),
+                    self.time.increment.as_secs(),
+                ),
+            )],
+        );
+        match self.time.bonus {
+            Bonus::Increment => {}
+            Bonus::SimpleDelay => name.push_str(&format!(" {}", tr("preset.delay"))),
+            Bonus::Bronstein => name.push_str(&format!(" {}", tr("preset.bronstein"))),
+        }
+        if !self.time.stages.is_empty() {
+            name.push_str(&format!(" {}", tr("preset.staged")));
+        }
+        if let Some((initial_time, increment)) = self.time.black_time {
+            let black = format_time_control(initial_time.as_secs(), increment.as_secs());
+            name.push_str(&format!(
+                " {}",
+                tr_args("preset.versus", &[("time", black)])
+            ));
+        }
+
+        self.time.preset = Some(name.clone());
+        self.presets.add(TimePreset {
+            name,
+            control: self.time.control_string(),
+        });
+        self.menu
+            .create_custom_preset_clickables(&self.presets.custom);
+    }
+}